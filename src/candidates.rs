@@ -0,0 +1,183 @@
+//     _         _        __  __
+//    / \  _   _| |_ ___ |  \/  | ___  ___ ___  __ _  __ _  ___
+//   / _ \| | | | __/ _ \| |\/| |/ _ \/ __/ __|/ _` |/ _` |/ _ \
+//  / ___ \ |_| | || (_) | |  | |  __/\__ \__ \ (_| | (_| |  __/
+// /_/   \_\__,_|\__\___/|_|  |_|\___||___/___/\__,_|\__, |\___|
+//                                                   |___/
+//
+// Author: Sidney Zhang <zly@lyzhang.me>
+// Date: 2025-08-05
+// License: MIT
+//
+// Ranking several generated messages so the user can pick one.
+
+use clap::ValueEnum;
+
+use crate::conventional::ConventionalCommit;
+
+/// How a candidate is chosen without asking the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Pick {
+    /// The candidate with the highest heuristic score.
+    Best,
+    /// The first candidate that was generated.
+    First,
+}
+
+/// What a generated message is for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MessageKind {
+    #[default]
+    Commit,
+    Tag,
+}
+
+/// What a message is checked against.
+#[derive(Debug, Clone, Default)]
+pub struct Convention {
+    pub kind: MessageKind,
+    /// Maximum subject length in characters.
+    pub max_length: usize,
+    /// Allowed commit types; empty allows any.
+    pub types: Vec<String>,
+    /// Allowed scopes; empty allows any.
+    pub scopes: Vec<String>,
+    /// Whether the subject must be `type(scope): description`. Off for tags.
+    pub conventional: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub message: String,
+    /// Convention violations; empty when the message is valid.
+    pub problems: Vec<String>,
+    pub score: i32,
+}
+
+impl Candidate {
+    pub fn new(message: &str, convention: &Convention) -> Self {
+        let problems = check(message, convention);
+        let score = score(message, &problems, convention);
+        Candidate {
+            message: message.trim().to_string(),
+            problems,
+            score,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Build candidates from raw messages, dropping empty ones and duplicates
+/// that only differ in case or whitespace. Generation order is kept.
+pub fn collect(messages: &[String], convention: &Convention) -> Vec<Candidate> {
+    let mut seen = std::collections::HashSet::new();
+    messages
+        .iter()
+        .filter(|message| !message.trim().is_empty())
+        .filter(|message| seen.insert(normalize(message)))
+        .map(|message| Candidate::new(message, convention))
+        .collect()
+}
+
+/// Index of the candidate `pick` selects.
+pub fn choose(candidates: &[Candidate], pick: Pick) -> Option<usize> {
+    match pick {
+        Pick::First => (!candidates.is_empty()).then_some(0),
+        // Ties go to the earlier candidate.
+        Pick::Best => candidates
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|(_, candidate)| candidate.score)
+            .map(|(index, _)| index),
+    }
+}
+
+/// Convention violations of `message`.
+pub fn check(message: &str, convention: &Convention) -> Vec<String> {
+    let mut problems = Vec::new();
+    let subject = message.trim().lines().next().unwrap_or("").trim();
+    if subject.is_empty() {
+        problems.push("empty subject".to_string());
+        return problems;
+    }
+
+    let length = subject.chars().count();
+    if convention.max_length > 0 && length > convention.max_length {
+        problems.push(format!(
+            "subject is {} characters, over the limit of {}",
+            length, convention.max_length
+        ));
+    }
+
+    let lines: Vec<&str> = message.trim().lines().collect();
+    if lines.len() > 1 && !lines[1].trim().is_empty() {
+        problems.push("no blank line between subject and body".to_string());
+    }
+
+    if !convention.conventional {
+        return problems;
+    }
+
+    match ConventionalCommit::parse(subject) {
+        Ok(commit) => {
+            if !convention.types.is_empty() && !convention.types.contains(&commit.kind) {
+                problems.push(format!("type '{}' is not allowed", commit.kind));
+            }
+            if let Some(scope) = &commit.scope
+                && !convention.scopes.is_empty()
+                && !convention.scopes.iter().any(|s| s == scope)
+            {
+                problems.push(format!("scope '{}' is not allowed", scope));
+            }
+        }
+        Err(e) => problems.push(format!("subject is not 'type(scope): description': {}", e)),
+    }
+
+    problems
+}
+
+/// Heuristic quality score; higher is better.
+fn score(message: &str, problems: &[String], convention: &Convention) -> i32 {
+    let mut score = 100 - 25 * problems.len() as i32;
+
+    let subject = message.trim().lines().next().unwrap_or("").trim();
+    let description = subject
+        .split_once(": ")
+        .map(|(_, description)| description)
+        .unwrap_or(subject);
+    let length = description.chars().count();
+
+    // Very short descriptions rarely say enough; long ones crowd the log.
+    if length < 10 {
+        score -= 15;
+    }
+    if convention.max_length > 0 && subject.chars().count() * 10 > convention.max_length * 9 {
+        score -= 5;
+    }
+    if subject.ends_with('.') || subject.ends_with('。') {
+        score -= 5;
+    }
+    // Prefer the imperative mood ("add", not "added" or "adding").
+    let first_word = description.split_whitespace().next().unwrap_or("");
+    if first_word.len() > 3 && (first_word.ends_with("ed") || first_word.ends_with("ing")) {
+        score -= 5;
+    }
+    // Leftovers from the model such as quotes or markdown fences.
+    if message.contains("```") || subject.starts_with('"') {
+        score -= 20;
+    }
+
+    score
+}
+
+fn normalize(message: &str) -> String {
+    message
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
@@ -11,147 +11,150 @@
 //
 // A git changelog generator.
 
-use anyhow::{Context, Result};
+use anyhow::Result;
 use chrono::Local;
-use std::fs::{File, OpenOptions};
-use std::io::{Read, Write};
+use clap::ValueEnum;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
 
+use crate::config::Config;
+use crate::conventional::ConventionalCommit;
 use crate::git::CommitInfo;
 use crate::llm::MessageGenerator;
+use crate::version::Version;
 
 pub struct ChangelogGenerator {
     llm: MessageGenerator,
+    /// Version the section is headed with; "Unreleased" when not set.
+    version: Option<String>,
+    /// Prefix of the tags that link references point to.
+    tag_prefix: String,
+    mode: UpdateMode,
+    /// Newest commit the generated section covers, recorded in the file.
+    last_commit: Option<String>,
+}
+
+/// What to do when the changelog already has a section for the version.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum UpdateMode {
+    /// Replace the section with the generated one.
+    Replace,
+    /// Add the generated entries the section does not have yet.
+    #[default]
+    Merge,
+    /// Leave the section as it is.
+    Skip,
 }
 
 impl ChangelogGenerator {
     pub fn new() -> Result<Self> {
-        let llm = MessageGenerator::new()?;
-        Ok(ChangelogGenerator { llm })
+        let config = Config::load()?;
+        Self::from_config(&config)
     }
 
-    pub async fn generate_changelog(&self, commits: &[CommitInfo]) -> Result<String> {
-        let summary = self.llm.generate_changelog_summary(commits).await?;
-
-        let date = Local::now().format("%Y-%m-%d").to_string();
-        let version = self.detect_version_from_commits(commits)?;
-
-        let changelog = format!("## [{}] - {}\n\n{}", version, date, summary);
-
-        Ok(changelog)
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let llm = MessageGenerator::from_config(config)?;
+        Ok(ChangelogGenerator {
+            llm,
+            version: None,
+            tag_prefix: config.tag_prefix().to_string(),
+            mode: UpdateMode::default(),
+            last_commit: None,
+        })
     }
 
-    pub fn write_changelog(&self, content: &str, output_path: &str, append: bool) -> Result<()> {
-        if append {
-            self.append_to_changelog(content, output_path)?;
-        } else {
-            self.create_new_changelog(content, output_path)?;
-        }
-        Ok(())
+    /// Head the generated section with `version`, e.g. the next version
+    /// from [`crate::version::next_release`].
+    pub fn set_version(&mut self, version: impl Into<String>) {
+        self.version = Some(version.into());
     }
 
-    fn create_new_changelog(&self, content: &str, output_path: &str) -> Result<()> {
-        let mut file = File::create(output_path)?;
+    pub fn set_mode(&mut self, mode: UpdateMode) {
+        self.mode = mode;
+    }
 
-        let header = format!(
-            "# Changelog\n\nAll notable changes to this project will be documented in this file.\n\nThe format is based on [Keep a Changelog](https://keepachangelog.com/en/1.0.0/),\nand this project adheres to [Semantic Versioning](https://semver.org/spec/v2.0.0.html).\n\n"
-        );
+    /// Record `sha` in the written changelog as the newest commit it
+    /// covers, so the next run can start after it.
+    pub fn set_last_commit(&mut self, sha: impl Into<String>) {
+        self.last_commit = Some(sha.into());
+    }
 
-        write!(file, "{}{}", header, content)?;
-        Ok(())
+    fn version(&self) -> &str {
+        self.version.as_deref().unwrap_or("Unreleased")
     }
 
-    fn append_to_changelog(&self, content: &str, output_path: &str) -> Result<()> {
-        let mut file = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .open(output_path)?;
+    pub async fn generate_changelog(&self, commits: &[CommitInfo]) -> Result<String> {
+        let summary = self.llm.generate_changelog_summary(commits).await?;
 
-        let mut existing_content = String::new();
-        file.read_to_string(&mut existing_content)?;
+        let date = Local::now().format("%Y-%m-%d").to_string();
+        let changelog = format!("## [{}] - {}\n\n{}", self.version(), date, summary);
 
-        let new_content = if existing_content.contains("# Changelog") {
-            // Insert after the header
-            let lines: Vec<&str> = existing_content.lines().collect();
-            let mut new_lines = Vec::new();
+        Ok(changelog)
+    }
 
-            let mut header_end = 0;
-            for (i, line) in lines.iter().enumerate() {
-                new_lines.push(*line);
-                if line.trim().is_empty() && i > 0 && lines[i - 1].starts_with('#') {
-                    header_end = i + 1;
-                    break;
-                }
-            }
+    pub fn write_changelog(&self, content: &str, output_path: &str, append: bool) -> Result<()> {
+        let text = self.updated_changelog(content, output_path, append)?;
+        fs::write(output_path, text)?;
+        Ok(())
+    }
 
-            new_lines.push(content);
-            new_lines.extend_from_slice(&lines[header_end..]);
-            new_lines.join("\n")
+    /// The text `write_changelog` would write to `output_path`.
+    pub fn updated_changelog(
+        &self,
+        content: &str,
+        output_path: &str,
+        append: bool,
+    ) -> Result<String> {
+        if append {
+            let existing_content = match fs::read_to_string(output_path) {
+                Ok(existing) => existing,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+                Err(e) => return Err(e.into()),
+            };
+            Ok(self.append_to_changelog(content, &existing_content))
         } else {
-            // Create new changelog with existing content
-            format!("{}", content)
-        };
-
-        // Write back the updated content
-        let mut file = File::create(output_path)?;
-        write!(file, "{}", new_content)?;
-
-        Ok(())
+            Ok(self.create_new_changelog(content))
+        }
     }
 
-    fn detect_version_from_commits(&self, commits: &[CommitInfo]) -> Result<String> {
-        // Simple version detection based on commit messages
-        let mut version = "Unreleased".to_string();
+    fn create_new_changelog(&self, content: &str) -> String {
+        let header = "# Changelog\n\nAll notable changes to this project will be documented in this file.\n\nThe format is based on [Keep a Changelog](https://keepachangelog.com/en/1.0.0/),\nand this project adheres to [Semantic Versioning](https://semver.org/spec/v2.0.0.html).\n\n";
 
-        for commit in commits {
-            let message = commit.message.to_lowercase();
-            if message.contains("version") || message.contains("release") {
-                // Try to extract version from commit message
-                let words: Vec<&str> = message.split_whitespace().collect();
-                for (i, word) in words.iter().enumerate() {
-                    if word.contains("version") || word.contains("release") {
-                        if i + 1 < words.len() {
-                            let next_word = words[i + 1];
-                            if next_word.starts_with('v') && next_word.len() > 1 {
-                                version = next_word
-                                    .trim_matches(|c: char| !c.is_alphanumeric())
-                                    .to_string();
-                                break;
-                            }
-                        }
-                    }
-                }
-            }
+        let mut changelog = Changelog::parse(&format!("{}{}", header, content));
+        if self.last_commit.is_some() {
+            changelog.last_commit = self.last_commit.clone();
         }
+        changelog.to_string()
+    }
 
-        if version == "Unreleased" {
-            // Generate a version based on commit types
-            let has_feat = commits
-                .iter()
-                .any(|c| c.message.to_lowercase().starts_with("feat"));
-            let has_fix = commits
-                .iter()
-                .any(|c| c.message.to_lowercase().starts_with("fix"));
-
-            if has_feat {
-                version = "0.1.0".to_string();
-            } else if has_fix {
-                version = "0.0.1".to_string();
-            } else {
-                version = "0.0.1".to_string();
-            }
+    /// Add the generated `content` to an existing changelog: see
+    /// [`Changelog::update`]. Link references are kept up to date when the
+    /// changelog has them.
+    fn append_to_changelog(&self, content: &str, existing_content: &str) -> String {
+        if existing_content.trim().is_empty() {
+            return self.create_new_changelog(content);
         }
 
-        Ok(version)
+        let mut changelog = Changelog::parse(existing_content);
+        for release in Changelog::parse(content).releases {
+            changelog.update(release, self.mode);
+        }
+        if self.last_commit.is_some() {
+            changelog.last_commit = self.last_commit.clone();
+        }
+        if let Some(base_url) = changelog.link_base() {
+            changelog.regenerate_links(&base_url, &self.tag_prefix);
+        }
+        changelog.to_string()
     }
 
     pub fn generate_default_changelog(&self, commits: &[CommitInfo]) -> Result<String> {
         let date = Local::now().format("%Y-%m-%d").to_string();
-        let version = self.detect_version_from_commits(commits)?;
-
-        let mut changelog = format!("## [{}] - {}\n\n", version, date);
+        let mut changelog = format!("## [{}] - {}\n\n", self.version(), date);
 
         // Group commits by type
+        let mut breaking = Vec::new();
         let mut features = Vec::new();
         let mut fixes = Vec::new();
         let mut docs = Vec::new();
@@ -159,37 +162,32 @@ impl ChangelogGenerator {
 
         for commit in commits {
             let message = commit.message.trim();
-            if message.to_lowercase().starts_with("feat") {
-                features.push(format!(
-                    "- {} ({}) - {}",
-                    &commit.sha[..8],
-                    message,
-                    commit.author
-                ));
-            } else if message.to_lowercase().starts_with("fix") {
-                fixes.push(format!(
-                    "- {} ({}) - {}",
-                    &commit.sha[..8],
-                    message,
-                    commit.author
-                ));
-            } else if message.to_lowercase().starts_with("docs") {
-                docs.push(format!(
-                    "- {} ({}) - {}",
-                    &commit.sha[..8],
-                    message,
-                    commit.author
-                ));
+            let entry = format!("- {} ({}) - {}", &commit.sha[..8], message, commit.author);
+            let Ok(parsed) = ConventionalCommit::parse_lenient(message) else {
+                others.push(entry);
+                continue;
+            };
+
+            if let Some(description) = parsed.breaking_description() {
+                breaking.push(format!("- {} ({})", &commit.sha[..8], description));
+            }
+            if parsed.is("feat") {
+                features.push(entry);
+            } else if parsed.is("fix") {
+                fixes.push(entry);
+            } else if parsed.is("docs") {
+                docs.push(entry);
             } else {
-                others.push(format!(
-                    "- {} ({}) - {}",
-                    &commit.sha[..8],
-                    message,
-                    commit.author
-                ));
+                others.push(entry);
             }
         }
 
+        if !breaking.is_empty() {
+            changelog.push_str("### Breaking Changes\n\n");
+            changelog.push_str(&breaking.join("\n"));
+            changelog.push_str("\n\n");
+        }
+
         if !features.is_empty() {
             changelog.push_str("### Added\n\n");
             changelog.push_str(&features.join("\n"));
@@ -217,3 +215,748 @@ impl ChangelogGenerator {
         Ok(changelog)
     }
 }
+
+/// A changelog in the [Keep a Changelog](https://keepachangelog.com) format.
+///
+/// Parsing never fails: lines that are not release or section headings,
+/// entries or trailing link references are kept as they are, so a
+/// changelog renders back to the same bytes until it is edited. Edits keep
+/// its line endings, and headings and links that did not change are
+/// written as they were.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Changelog {
+    /// Everything before the first release, such as the title and intro.
+    pub preamble: Vec<String>,
+    /// Releases as they appear, normally newest first.
+    pub releases: Vec<Release>,
+    /// Link reference definitions at the end, e.g.
+    /// `[1.0.0]: https://github.com/owner/repo/compare/v0.9.0...v1.0.0`.
+    pub links: Vec<Link>,
+    /// Newest commit covered so far, kept in an HTML comment at the end.
+    pub last_commit: Option<String>,
+    /// Whether lines end with `\r\n`.
+    crlf: bool,
+    /// Whether the text ends without a line break.
+    missing_final_newline: bool,
+    /// Blank lines after the last link reference.
+    blanks_after_links: usize,
+    /// Each release with the version below it, as parsed: links of releases
+    /// whose neighbour is unchanged are kept as written.
+    parsed_order: Vec<(String, Option<String>)>,
+}
+
+/// A `## [version] - date` section.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Release {
+    /// A version such as `1.2.0`, or `Unreleased`.
+    pub version: String,
+    pub date: Option<String>,
+    /// The heading as written, used while version and date are unchanged.
+    heading: Option<String>,
+    /// Content between the heading and the first section.
+    pub intro: Vec<Block>,
+    pub sections: Vec<Section>,
+}
+
+/// A `### Added` style group of entries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub title: String,
+    pub blocks: Vec<Block>,
+}
+
+/// A line of content, or a list item with its continuation lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Block {
+    Entry(String),
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    pub label: String,
+    pub url: String,
+    /// The line as written, with any title, used while the URL is unchanged.
+    line: Option<String>,
+    /// Blank lines between this link and the one before it.
+    blank_before: usize,
+}
+
+/// Commit hashes mentioned in a changelog; see [`Changelog::mentions`].
+#[derive(Debug, Clone, Default)]
+pub struct Mentions(HashSet<String>);
+
+impl Mentions {
+    /// Whether the commit `sha` is mentioned by a hash of at least seven
+    /// characters.
+    pub fn contains(&self, sha: &str) -> bool {
+        let sha = sha.to_ascii_lowercase();
+        (MIN_HASH_LENGTH..=sha.len().min(40)).any(|length| self.0.contains(&sha[..length]))
+    }
+}
+
+const UNRELEASED: &str = "Unreleased";
+
+/// Shortest abbreviated commit hash taken as a mention.
+const MIN_HASH_LENGTH: usize = 7;
+
+/// Start of the comment recording [`Changelog::last_commit`].
+const LAST_COMMIT_MARKER: &str = "<!-- git-automessage last-commit:";
+
+impl Changelog {
+    pub fn parse(text: &str) -> Self {
+        let mut last_commit = None;
+        let mut lines: Vec<&str> = Vec::new();
+        for line in text.lines() {
+            match parse_last_commit(line) {
+                Some(sha) => {
+                    // The blank line written before the marker goes with it.
+                    if lines.last().is_some_and(|line| line.trim().is_empty()) {
+                        lines.pop();
+                    }
+                    last_commit = Some(sha);
+                }
+                None => lines.push(line),
+            }
+        }
+
+        // Link references at the very end, with the blank lines among them.
+        let mut start = lines.len();
+        while start > 0
+            && (lines[start - 1].trim().is_empty() || Link::parse(lines[start - 1]).is_some())
+        {
+            start -= 1;
+        }
+        let mut links: Vec<Link> = Vec::new();
+        let mut blanks = 0;
+        for line in &lines[start..] {
+            match Link::parse(line) {
+                Some(link) => {
+                    links.push(Link {
+                        blank_before: if links.is_empty() { 0 } else { blanks },
+                        ..link
+                    });
+                    blanks = 0;
+                }
+                None => blanks += 1,
+            }
+        }
+        if let Some(first) = lines[start..]
+            .iter()
+            .position(|line| Link::parse(line).is_some())
+        {
+            lines.truncate(start + first);
+        }
+
+        let mut changelog = Changelog {
+            blanks_after_links: if links.is_empty() { 0 } else { blanks },
+            links,
+            last_commit,
+            crlf: text.contains("\r\n"),
+            missing_final_newline: !text.is_empty() && !text.ends_with('\n'),
+            ..Default::default()
+        };
+        let mut in_fence = false;
+        for line in lines {
+            if line.trim_start().starts_with("```") {
+                in_fence = !in_fence;
+            } else if !in_fence {
+                if let Some(release) = Release::from_heading(line) {
+                    changelog.releases.push(release);
+                    continue;
+                }
+                if let Some(release) = changelog.releases.last_mut()
+                    && let Some(title) = line.strip_prefix("### ")
+                {
+                    release.sections.push(Section {
+                        title: title.trim().to_string(),
+                        blocks: Vec::new(),
+                    });
+                    continue;
+                }
+            }
+
+            match changelog.releases.last_mut() {
+                Some(release) => push_line(release.blocks_mut(), line, in_fence),
+                None => changelog.preamble.push(line.to_string()),
+            }
+        }
+        changelog.parsed_order = changelog.neighbours();
+        changelog
+    }
+
+    pub fn release(&self, version: &str) -> Option<&Release> {
+        self.releases.iter().find(|release| release.is(version))
+    }
+
+    pub fn release_mut(&mut self, version: &str) -> Option<&mut Release> {
+        self.releases.iter_mut().find(|release| release.is(version))
+    }
+
+    pub fn unreleased(&self) -> Option<&Release> {
+        self.release(UNRELEASED)
+    }
+
+    /// Add a generated release. A release the changelog already has is
+    /// replaced, merged into or left alone according to `mode`; any other
+    /// is added with [`Self::add_release`].
+    pub fn update(&mut self, release: Release, mode: UpdateMode) {
+        if self.release(&release.version).is_none() {
+            self.add_release(release);
+            return;
+        }
+        match mode {
+            UpdateMode::Replace => self.replace_release(release),
+            UpdateMode::Merge => {
+                self.merge_into(&release.version.clone(), &release);
+            }
+            UpdateMode::Skip => {}
+        }
+    }
+
+    /// Put `release` in place of the release with the same version.
+    pub fn replace_release(&mut self, mut release: Release) {
+        let Some(index) = self
+            .releases
+            .iter()
+            .position(|existing| existing.is(&release.version))
+        else {
+            self.insert_release(release);
+            return;
+        };
+        end_with_blank(release.blocks_mut());
+        self.releases[index] = release;
+    }
+
+    /// The commit hashes the changelog mentions, collected once so that
+    /// many commits can be looked up.
+    pub fn mentions(&self) -> Mentions {
+        let text = self.to_string();
+        Mentions(
+            text.split(|c: char| !c.is_ascii_alphanumeric())
+                .filter(|word| is_hash(word))
+                .map(str::to_ascii_lowercase)
+                .collect(),
+        )
+    }
+
+    /// Add a generated release the way a maintainer would: changes for
+    /// `Unreleased` are merged into it, and a new latest version takes over
+    /// the notes already collected under `Unreleased`. Other versions are
+    /// inserted in order.
+    pub fn add_release(&mut self, release: Release) {
+        if release.is_unreleased() {
+            self.merge_into_unreleased(&release);
+            return;
+        }
+
+        let collected = self.unreleased().is_some_and(Release::has_entries);
+        if collected && self.is_latest(&release.version) && self.release(&release.version).is_none()
+        {
+            let date = release.date.clone().unwrap_or_default();
+            // Cannot fail: Unreleased exists and the version does not.
+            let _ = self.promote_unreleased(&release.version, &date);
+            self.merge_into(&release.version, &release);
+        } else {
+            self.insert_release(release);
+        }
+    }
+
+    /// Insert `release` above the first older version, below `Unreleased`.
+    pub fn insert_release(&mut self, mut release: Release) {
+        let version = parse_version(&release.version);
+        let index = self
+            .releases
+            .iter()
+            .position(|existing| {
+                !existing.is_unreleased()
+                    && match (&version, parse_version(&existing.version)) {
+                        (Some(new), Some(old)) => old < *new,
+                        _ => version.is_none(),
+                    }
+            })
+            .unwrap_or(self.releases.len());
+
+        end_with_blank(release.blocks_mut());
+        match index.checked_sub(1) {
+            Some(previous) => end_with_blank(self.releases[previous].blocks_mut()),
+            None if self
+                .preamble
+                .last()
+                .is_some_and(|line| !line.trim().is_empty()) =>
+            {
+                self.preamble.push(String::new());
+            }
+            None => {}
+        }
+        self.releases.insert(index, release);
+    }
+
+    /// Merge the entries of `release` into `Unreleased`, which is created
+    /// at the top when missing. Returns the number of entries added.
+    pub fn merge_into_unreleased(&mut self, release: &Release) -> usize {
+        if self.unreleased().is_none() {
+            self.insert_release(Release::new(UNRELEASED, None));
+        }
+        self.merge_into(UNRELEASED, release)
+    }
+
+    /// Merge the entries of `release` into the release `version`, section
+    /// by section, skipping entries it already has. Returns the number of
+    /// entries added.
+    pub fn merge_into(&mut self, version: &str, release: &Release) -> usize {
+        let Some(target) = self.release_mut(version) else {
+            return 0;
+        };
+
+        let mut added = add_entries(&mut target.intro, entries(&release.intro));
+        for section in &release.sections {
+            let new = entries(&section.blocks);
+            match target
+                .sections
+                .iter_mut()
+                .find(|existing| existing.title.eq_ignore_ascii_case(&section.title))
+            {
+                Some(existing) => added += add_entries(&mut existing.blocks, new),
+                None if new.is_empty() => {}
+                None => {
+                    end_with_blank(target.blocks_mut());
+                    let mut blocks = vec![Block::Text(String::new())];
+                    added += add_entries(&mut blocks, new);
+                    target.sections.push(Section {
+                        title: section.title.clone(),
+                        blocks,
+                    });
+                }
+            }
+        }
+        end_with_blank(target.blocks_mut());
+        added
+    }
+
+    /// Turn `Unreleased` into the release `version` of `date`, and start a
+    /// new, empty `Unreleased` above it.
+    pub fn promote_unreleased(&mut self, version: &str, date: &str) -> Result<()> {
+        if self.release(version).is_some() {
+            anyhow::bail!("the changelog already has a section for {}", version);
+        }
+        let Some(index) = self
+            .releases
+            .iter()
+            .position(|release| release.is_unreleased())
+        else {
+            anyhow::bail!("the changelog has no Unreleased section");
+        };
+
+        let release = &mut self.releases[index];
+        release.version = version.to_string();
+        release.date = (!date.is_empty()).then(|| date.to_string());
+        end_with_blank(release.blocks_mut());
+        self.releases.insert(index, Release::new(UNRELEASED, None));
+        Ok(())
+    }
+
+    /// The repository URL the existing link references point into, e.g.
+    /// `https://github.com/owner/repo`.
+    pub fn link_base(&self) -> Option<String> {
+        self.links.iter().find_map(|link| {
+            ["/compare/", "/releases/tag/", "/tree/"]
+                .iter()
+                .find_map(|marker| link.url.split_once(marker))
+                .map(|(base, _)| base.to_string())
+        })
+    }
+
+    /// Point the link reference of every release at its changes on
+    /// `base_url`: a comparison with the previous version, or the tag for
+    /// the first one. Links of releases that are new or have a new previous
+    /// version are written; the others are kept as they are, as are other
+    /// link references.
+    pub fn regenerate_links(&mut self, base_url: &str, tag_prefix: &str) {
+        let base_url = base_url.trim_end_matches('/');
+        let tag = |version: &str| format!("{}{}", tag_prefix, version);
+        let neighbours = self.neighbours();
+
+        for (index, (version, previous)) in neighbours.iter().enumerate() {
+            let url = match previous {
+                Some(previous) if version.eq_ignore_ascii_case(UNRELEASED) => {
+                    format!("{}/compare/{}...HEAD", base_url, tag(previous))
+                }
+                None if version.eq_ignore_ascii_case(UNRELEASED) => continue,
+                Some(previous) => {
+                    format!("{}/compare/{}...{}", base_url, tag(previous), tag(version))
+                }
+                None => format!("{}/releases/tag/{}", base_url, tag(version)),
+            };
+            let is_link = |version: &str| {
+                let version = version.to_string();
+                move |link: &Link| same_version(&link.label, &version)
+            };
+
+            match self.links.iter().position(is_link(version)) {
+                Some(_) if self.parsed_order.contains(&neighbours[index]) => {}
+                Some(position) => self.links[position].set_url(url),
+                None => {
+                    // Beside the link of the nearest release that has one.
+                    let position = neighbours[index + 1..]
+                        .iter()
+                        .find_map(|(newer, _)| self.links.iter().position(is_link(newer)))
+                        .or_else(|| {
+                            neighbours[..index].iter().rev().find_map(|(later, _)| {
+                                self.links
+                                    .iter()
+                                    .position(is_link(later))
+                                    .map(|position| position + 1)
+                            })
+                        })
+                        .unwrap_or(0);
+                    self.links.insert(
+                        position,
+                        Link {
+                            label: version.clone(),
+                            url,
+                            line: None,
+                            blank_before: 0,
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    /// Each release with the version below it.
+    fn neighbours(&self) -> Vec<(String, Option<String>)> {
+        let versions: Vec<&String> = self
+            .releases
+            .iter()
+            .filter(|release| !release.is_unreleased())
+            .map(|release| &release.version)
+            .collect();
+        self.releases
+            .iter()
+            .map(|release| {
+                let below = if release.is_unreleased() {
+                    versions.first()
+                } else {
+                    versions
+                        .iter()
+                        .position(|version| **version == release.version)
+                        .and_then(|index| versions.get(index + 1))
+                };
+                (
+                    release.version.clone(),
+                    below.map(|version| version.to_string()),
+                )
+            })
+            .collect()
+    }
+
+    /// Whether `version` is newer than every version in the changelog.
+    fn is_latest(&self, version: &str) -> bool {
+        let Some(version) = parse_version(version) else {
+            return false;
+        };
+        self.releases
+            .iter()
+            .filter_map(|release| parse_version(&release.version))
+            .all(|existing| existing < version)
+    }
+}
+
+impl fmt::Display for Changelog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lines: Vec<&str> = self.preamble.iter().map(String::as_str).collect();
+        let headings: Vec<String> = self.releases.iter().map(Release::heading).collect();
+        let titles: Vec<Vec<String>> = self
+            .releases
+            .iter()
+            .map(|release| {
+                release
+                    .sections
+                    .iter()
+                    .map(|section| format!("### {}", section.title))
+                    .collect()
+            })
+            .collect();
+        for (index, release) in self.releases.iter().enumerate() {
+            lines.push(&headings[index]);
+            lines.extend(release.intro.iter().map(Block::text));
+            for (section, title) in release.sections.iter().zip(&titles[index]) {
+                lines.push(title);
+                lines.extend(section.blocks.iter().map(Block::text));
+            }
+        }
+
+        let mut tail: Vec<String> = Vec::new();
+        if !self.links.is_empty() {
+            if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+                tail.push(String::new());
+            }
+            for link in &self.links {
+                tail.extend(std::iter::repeat_n(String::new(), link.blank_before));
+                tail.push(link.line());
+            }
+            tail.extend(std::iter::repeat_n(String::new(), self.blanks_after_links));
+        }
+        if let Some(sha) = &self.last_commit {
+            let last = tail.last().map(String::as_str).or(lines.last().copied());
+            if last.is_some_and(|line| !line.trim().is_empty()) {
+                tail.push(String::new());
+            }
+            tail.push(format!("{} {} -->", LAST_COMMIT_MARKER, sha));
+        }
+
+        let eol = if self.crlf { "\r\n" } else { "\n" };
+        lines.extend(tail.iter().map(String::as_str));
+        for (index, line) in lines.iter().enumerate() {
+            f.write_str(line)?;
+            if index + 1 < lines.len() || !self.missing_final_newline {
+                f.write_str(eol)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Release {
+    pub fn new(version: &str, date: Option<&str>) -> Self {
+        Release {
+            version: version.to_string(),
+            date: date.map(str::to_string),
+            heading: None,
+            intro: vec![Block::Text(String::new())],
+            sections: Vec::new(),
+        }
+    }
+
+    /// Parse a heading such as `## [1.0.0] - 2024-01-31`, `## v1.0.0` or
+    /// `## [Unreleased]`. Other level-two headings are not releases.
+    fn from_heading(line: &str) -> Option<Self> {
+        let (version, date) = parse_heading(line)?;
+        Some(Release {
+            version,
+            date,
+            heading: Some(line.to_string()),
+            intro: Vec::new(),
+            sections: Vec::new(),
+        })
+    }
+
+    pub fn is_unreleased(&self) -> bool {
+        self.version.eq_ignore_ascii_case(UNRELEASED)
+    }
+
+    /// Whether this is the release `version`; a leading `v` is ignored.
+    pub fn is(&self, version: &str) -> bool {
+        same_version(&self.version, version)
+    }
+
+    pub fn has_entries(&self) -> bool {
+        !entries(&self.intro).is_empty()
+            || self
+                .sections
+                .iter()
+                .any(|section| !entries(&section.blocks).is_empty())
+    }
+
+    fn heading(&self) -> String {
+        if let Some(heading) = &self.heading
+            && parse_heading(heading) == Some((self.version.clone(), self.date.clone()))
+        {
+            return heading.clone();
+        }
+        match &self.date {
+            Some(date) => format!("## [{}] - {}", self.version, date),
+            None => format!("## [{}]", self.version),
+        }
+    }
+
+    /// The blocks the release ends with: those of its last section, or the
+    /// intro.
+    fn blocks_mut(&mut self) -> &mut Vec<Block> {
+        match self.sections.last_mut() {
+            Some(section) => &mut section.blocks,
+            None => &mut self.intro,
+        }
+    }
+}
+
+impl Block {
+    pub fn text(&self) -> &str {
+        match self {
+            Block::Entry(text) | Block::Text(text) => text,
+        }
+    }
+}
+
+impl Link {
+    /// Parse `[label]: url`.
+    fn parse(line: &str) -> Option<Self> {
+        let rest = line.strip_prefix('[')?;
+        let (label, rest) = rest.split_once("]:")?;
+        let url = rest.split_whitespace().next()?;
+        if label.is_empty() || label.contains(']') {
+            return None;
+        }
+        Some(Link {
+            label: label.to_string(),
+            url: url.to_string(),
+            line: Some(line.to_string()),
+            blank_before: 0,
+        })
+    }
+
+    /// Point the link at `url`, keeping any title.
+    fn set_url(&mut self, url: String) {
+        if let Some(line) = &mut self.line {
+            *line = line.replacen(&self.url, &url, 1);
+        }
+        self.url = url;
+    }
+
+    fn line(&self) -> String {
+        match &self.line {
+            Some(line)
+                if Link::parse(line)
+                    .is_some_and(|link| link.label == self.label && link.url == self.url) =>
+            {
+                line.clone()
+            }
+            _ => format!("[{}]: {}", self.label, self.url),
+        }
+    }
+}
+
+/// Version and date of a release heading.
+fn parse_heading(line: &str) -> Option<(String, Option<String>)> {
+    let rest = line.strip_prefix("## ")?.trim();
+    let (version, rest) = match rest.strip_prefix('[') {
+        Some(bracketed) => bracketed.split_once(']')?,
+        None => rest.split_once(char::is_whitespace).unwrap_or((rest, "")),
+    };
+    let version = version.trim();
+    if !version.eq_ignore_ascii_case(UNRELEASED) && parse_version(version).is_none() {
+        return None;
+    }
+
+    let date = rest
+        .split(|c: char| c.is_whitespace() || c == '(' || c == ')')
+        .find(|word| {
+            word.len() == 10
+                && word.chars().enumerate().all(|(i, c)| {
+                    if i == 4 || i == 7 {
+                        c == '-'
+                    } else {
+                        c.is_ascii_digit()
+                    }
+                })
+        })
+        .map(str::to_string);
+    Some((version.to_string(), date))
+}
+
+/// The commit recorded by a `<!-- git-automessage last-commit: <sha> -->` line.
+fn parse_last_commit(line: &str) -> Option<String> {
+    let sha = line
+        .trim()
+        .strip_prefix(LAST_COMMIT_MARKER)?
+        .strip_suffix("-->")?
+        .trim();
+    (!sha.is_empty() && sha.chars().all(|c| c.is_ascii_hexdigit())).then(|| sha.to_string())
+}
+
+/// Whether `word` looks like an abbreviated or full commit hash. Lookups
+/// are by real commit hashes, so a number that happens to be hex does no
+/// harm.
+fn is_hash(word: &str) -> bool {
+    (MIN_HASH_LENGTH..=40).contains(&word.len()) && word.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Whether two versions are the same; a leading `v` is ignored.
+fn same_version(a: &str, b: &str) -> bool {
+    let normalize = |v: &str| {
+        let v = v.trim();
+        v.strip_prefix('v').unwrap_or(v).to_ascii_lowercase()
+    };
+    normalize(a) == normalize(b)
+}
+
+fn parse_version(version: &str) -> Option<Version> {
+    Version::parse(version.strip_prefix('v').unwrap_or(version))
+}
+
+/// Add a line to `blocks`: a list item starts an entry, and indented lines
+/// continue the entry before them.
+fn push_line(blocks: &mut Vec<Block>, line: &str, in_fence: bool) {
+    let is_item = ["- ", "* ", "+ "]
+        .iter()
+        .any(|marker| line.starts_with(marker));
+    let continues = (in_fence || line.starts_with([' ', '\t'])) && !line.trim().is_empty();
+    match blocks.last_mut() {
+        Some(Block::Entry(entry)) if continues && !is_item => {
+            entry.push('\n');
+            entry.push_str(line);
+        }
+        _ if is_item => blocks.push(Block::Entry(line.to_string())),
+        _ => blocks.push(Block::Text(line.to_string())),
+    }
+}
+
+fn entries(blocks: &[Block]) -> Vec<&str> {
+    blocks
+        .iter()
+        .filter_map(|block| match block {
+            Block::Entry(entry) => Some(entry.as_str()),
+            Block::Text(_) => None,
+        })
+        .collect()
+}
+
+/// Add the `new` entries missing from `blocks` after its last entry.
+fn add_entries(blocks: &mut Vec<Block>, new: Vec<&str>) -> usize {
+    let existing: Vec<String> = entries(blocks)
+        .iter()
+        .map(|entry| entry.trim().to_string())
+        .collect();
+    let mut index = match blocks
+        .iter()
+        .rposition(|block| matches!(block, Block::Entry(_)))
+    {
+        Some(last) => last + 1,
+        None => {
+            // After the text leading up to the list, before trailing blanks.
+            let end = blocks
+                .iter()
+                .rposition(|block| !block.text().trim().is_empty())
+                .map_or(0, |last| last + 1);
+            if end > 0 {
+                blocks.insert(end, Block::Text(String::new()));
+                end + 1
+            } else {
+                blocks.len().min(1)
+            }
+        }
+    };
+
+    let mut added = 0;
+    for entry in new {
+        if existing.iter().any(|existing| existing == entry.trim()) {
+            continue;
+        }
+        blocks.insert(index, Block::Entry(entry.to_string()));
+        index += 1;
+        added += 1;
+    }
+    added
+}
+
+/// Make `blocks` end with exactly one blank line.
+fn end_with_blank(blocks: &mut Vec<Block>) {
+    while blocks
+        .last()
+        .is_some_and(|block| matches!(block, Block::Text(text) if text.trim().is_empty()))
+    {
+        blocks.pop();
+    }
+    blocks.push(Block::Text(String::new()));
+}
@@ -11,8 +11,11 @@
 //
 // CLI
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+use crate::git::DiffMode;
 
 #[derive(Parser)]
 #[command(name = "git-automessage")]
@@ -22,6 +25,10 @@ use clap::{Args, Parser, Subcommand};
     about = "AI-powered git message generator written in Rust"
 )]
 pub struct Cli {
+    /// Override a configuration value for this run (e.g. -c language=en)
+    #[arg(short = 'c', long = "set", value_name = "KEY=VALUE", global = true)]
+    pub overrides: Vec<String>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -36,6 +43,14 @@ pub enum Commands {
     Changelog(ChangelogArgs),
     /// Configuration management
     Config(ConfigArgs),
+    /// Manage the prepare-commit-msg git hook
+    Hook(HookArgs),
+    /// Check commit messages against the configured rules
+    Lint(LintArgs),
+    /// Generate a new message for an existing commit and rewrite it
+    Reword(RewordArgs),
+    /// Bump the version, update the changelog, then commit and tag the release
+    Release(ReleaseArgs),
 }
 
 #[derive(Args)]
@@ -44,13 +59,66 @@ pub struct CommitArgs {
     #[arg(long)]
     pub commit: bool,
 
-    /// Custom prompt for message generation
-    #[arg(long)]
+    /// Custom prompt for message generation; used as a full template when it
+    /// contains variables such as {diff}, otherwise added as extra instructions
+    #[arg(long, conflicts_with = "prompt_file")]
     pub prompt: Option<String>,
 
-    /// Maximum length for commit message
-    #[arg(long, default_value = "72")]
-    pub max_length: usize,
+    /// Read the prompt template from a file
+    #[arg(long, value_name = "PATH")]
+    pub prompt_file: Option<std::path::PathBuf>,
+
+    /// Maximum length for commit message (defaults to lint.max_header_length,
+    /// which a commitlint header-max-length rule also sets, or 72)
+    #[arg(long)]
+    pub max_length: Option<usize>,
+
+    /// Describe unstaged and untracked working tree changes instead of the index
+    #[arg(long, conflicts_with_all = ["all", "commit", "interactive"])]
+    pub unstaged: bool,
+
+    /// Review the message before committing: accept, edit, regenerate,
+    /// refine with feedback or switch type/scope
+    #[arg(long, short = 'i')]
+    pub interactive: bool,
+
+    /// Describe both staged and unstaged changes (stages everything with --commit)
+    #[arg(long, short = 'a')]
+    pub all: bool,
+
+    /// Describe HEAD together with the staged changes and replace HEAD
+    /// (with --commit), like `git commit --amend`
+    #[arg(long, conflicts_with_all = ["unstaged", "all"])]
+    pub amend: bool,
+
+    #[command(flatten)]
+    pub candidates: CandidateArgs,
+}
+
+#[derive(Args)]
+pub struct CandidateArgs {
+    /// Generate N alternative messages and pick one from a numbered list
+    #[arg(long, value_name = "N", default_value = "1",
+          value_parser = clap::value_parser!(u8).range(1..=10))]
+    pub candidates: u8,
+
+    /// Pick a candidate without asking (best ranks them by a heuristic score)
+    #[arg(long, value_enum, value_name = "HOW")]
+    pub pick: Option<crate::candidates::Pick>,
+}
+
+impl CommitArgs {
+    pub fn diff_mode(&self) -> DiffMode {
+        if self.amend {
+            DiffMode::Amend
+        } else if self.all {
+            DiffMode::All
+        } else if self.unstaged {
+            DiffMode::Unstaged
+        } else {
+            DiffMode::Staged
+        }
+    }
 }
 
 #[derive(Args)]
@@ -62,18 +130,30 @@ pub struct TagArgs {
     #[arg(long)]
     pub annotated: bool,
 
-    /// Custom prompt for tag message
-    #[arg(long)]
+    /// Custom prompt for tag message; used as a full template when it
+    /// contains variables such as {tag_name}, otherwise added as extra instructions
+    #[arg(long, conflicts_with = "prompt_file")]
     pub prompt: Option<String>,
 
+    /// Read the prompt template from a file
+    #[arg(long, value_name = "PATH")]
+    pub prompt_file: Option<std::path::PathBuf>,
+
     /// Reference to tag (commit SHA or branch)
     #[arg(long, default_value = "HEAD")]
     pub reference: String,
+
+    /// Describe the commits since this tag instead of the previous tag
+    #[arg(long, value_name = "TAG")]
+    pub since: Option<String>,
+
+    #[command(flatten)]
+    pub candidates: CandidateArgs,
 }
 
 #[derive(Args)]
 pub struct ChangelogArgs {
-    /// Number of recent commits to include
+    /// Number of recent commits to include when nothing calls for a release
     #[arg(long, default_value = "10")]
     pub commits: usize,
 
@@ -88,11 +168,122 @@ pub struct ChangelogArgs {
     /// Tag range for changelog (e.g., v1.0.0..v1.1.0)
     #[arg(long)]
     pub range: Option<String>,
+
+    /// What to do when the changelog already has a section for the version
+    #[arg(long, value_enum, default_value = "merge", requires = "append")]
+    pub mode: crate::changelog::UpdateMode,
+}
+
+#[derive(Args)]
+pub struct RewordArgs {
+    /// Commit to reword; later commits are rebased onto the new one
+    #[arg(default_value = "HEAD")]
+    pub revision: String,
+
+    /// Reword every commit in a range such as origin/main..HEAD in one pass
+    #[arg(long, value_name = "RANGE",
+          conflicts_with_all = ["revision", "interactive", "candidates", "pick"])]
+    pub range: Option<String>,
+
+    /// Only reword commits in --range whose message matches this regex
+    #[arg(long, value_name = "REGEX", requires = "range")]
+    pub only_matching: Option<String>,
+
+    /// Rewrite the --range commits without asking for confirmation
+    #[arg(long, short = 'y', requires = "range")]
+    pub yes: bool,
+
+    /// Rewrite commits that are already on a remote-tracking branch
+    #[arg(long)]
+    pub force: bool,
+
+    /// Custom prompt for message generation; used as a full template when it
+    /// contains variables such as {diff}, otherwise added as extra instructions
+    #[arg(long, conflicts_with = "prompt_file")]
+    pub prompt: Option<String>,
+
+    /// Read the prompt template from a file
+    #[arg(long, value_name = "PATH")]
+    pub prompt_file: Option<std::path::PathBuf>,
+
+    /// Maximum length for commit message
+    #[arg(long)]
+    pub max_length: Option<usize>,
+
+    /// Review the message before rewriting the commit
+    #[arg(long, short = 'i')]
+    pub interactive: bool,
+
+    #[command(flatten)]
+    pub candidates: CandidateArgs,
+}
+
+#[derive(Args)]
+pub struct ReleaseArgs {
+    /// Bump the major version, whatever the commits say
+    #[arg(long, group = "bump")]
+    pub major: bool,
+
+    /// Bump the minor version, whatever the commits say
+    #[arg(long, group = "bump")]
+    pub minor: bool,
+
+    /// Bump the patch version, whatever the commits say
+    #[arg(long, group = "bump")]
+    pub patch: bool,
+
+    /// Make a pre-release on this channel, e.g. rc or beta
+    #[arg(long, value_name = "CHANNEL")]
+    pub pre: Option<String>,
+
+    /// Show every planned change without writing, committing or tagging
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+impl ReleaseArgs {
+    /// The bump asked for on the command line, if any.
+    pub fn bump(&self) -> Option<crate::version::Bump> {
+        use crate::version::Bump;
+
+        if self.major {
+            Some(Bump::Major)
+        } else if self.minor {
+            Some(Bump::Minor)
+        } else if self.patch {
+            Some(Bump::Patch)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct LintArgs {
+    /// Commit message file to check, e.g. the file git passes to a
+    /// commit-msg hook
+    #[arg(value_name = "FILE", conflicts_with_all = ["commit", "range"])]
+    pub file: Option<PathBuf>,
+
+    /// Commit to check (defaults to HEAD)
+    #[arg(long, conflicts_with = "range")]
+    pub commit: Option<String>,
+
+    /// Check every commit in a range (e.g., origin/main..HEAD)
+    #[arg(long)]
+    pub range: Option<String>,
+
+    /// Output format
+    #[arg(long, value_enum, default_value = "text")]
+    pub format: crate::lint::LintFormat,
 }
 
 #[derive(Args)]
 pub struct ConfigArgs {
-    /// Initialize configuration file with default values
+    #[command(subcommand)]
+    pub command: Option<ConfigCommand>,
+
+    /// Initialize configuration file interactively (same as `config init`)
     #[arg(long)]
     pub init: bool,
 
@@ -103,28 +294,194 @@ pub struct ConfigArgs {
     /// Validate current configuration
     #[arg(long)]
     pub validate: bool,
+
+    /// Show the effective configuration and where each value comes from
+    #[arg(long)]
+    pub show_origin: bool,
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Create the global configuration file
+    Init(InitArgs),
+    /// Print a configuration value
+    Get {
+        /// Configuration key, e.g. llm.model
+        key: String,
+        #[command(flatten)]
+        scope: ScopeArgs,
+    },
+    /// Set a configuration value (in the global file unless --local is given)
+    Set {
+        /// Configuration key, e.g. llm.model
+        key: String,
+        /// New value; booleans take true/false, lists are comma separated.
+        /// llm.api_key also accepts keyring:, env:, cmd: and file: references
+        value: String,
+        #[command(flatten)]
+        scope: ScopeArgs,
+        /// Write llm.api_key into the config file instead of the OS keyring
+        #[arg(long)]
+        plain: bool,
+    },
+    /// Remove a configuration value (from the global file unless --local is given)
+    Unset {
+        /// Configuration key, e.g. llm.model
+        key: String,
+        #[command(flatten)]
+        scope: ScopeArgs,
+    },
+    /// List configuration values
+    List {
+        #[command(flatten)]
+        scope: ScopeArgs,
+    },
+}
+
+#[derive(Args)]
+pub struct ScopeArgs {
+    /// Use the global configuration file
+    #[arg(long, conflicts_with = "local")]
+    pub global: bool,
+
+    /// Use the repository's .git-automessage.yaml
+    #[arg(long)]
+    pub local: bool,
+}
+
+impl ScopeArgs {
+    /// The explicitly requested file, if any.
+    pub fn scope(&self) -> Option<crate::config::ConfigScope> {
+        use crate::config::ConfigScope;
+
+        if self.local {
+            Some(ConfigScope::Local)
+        } else if self.global {
+            Some(ConfigScope::Global)
+        } else {
+            None
+        }
+    }
 }
 
-pub async fn handle_commit(args: CommitArgs) -> Result<()> {
-    use crate::{git::GitRepo, llm::MessageGenerator};
+#[derive(Args)]
+pub struct InitArgs {
+    /// Never prompt; take values from flags and GAM_* environment variables
+    #[arg(long)]
+    pub non_interactive: bool,
+
+    /// LLM provider (OpenAI, DeepSeek, Kimi, Anthropic, Ollama); falls back to GAM_PROVIDER
+    #[arg(long)]
+    pub provider: Option<String>,
+
+    /// Model name; falls back to GAM_MODEL
+    #[arg(long)]
+    pub model: Option<String>,
+
+    /// API key; prefer GAM_API_KEY so the key is not stored in shell history
+    #[arg(long)]
+    pub api_key: Option<String>,
+
+    /// API base URL; defaults to the provider's URL or GAM_BASE_URL
+    #[arg(long)]
+    pub base_url: Option<String>,
+
+    /// Output language for generated messages
+    #[arg(long)]
+    pub language: Option<String>,
+
+    /// Overwrite an existing configuration file
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Args)]
+pub struct HookArgs {
+    #[command(subcommand)]
+    pub command: HookCommand,
+}
+
+#[derive(Subcommand)]
+pub enum HookCommand {
+    /// Install the prepare-commit-msg hook, chaining any existing hook
+    Install,
+    /// Remove the hook and restore any chained hook
+    Uninstall,
+    /// Show whether the hook is installed
+    Status,
+    /// Hook entry point invoked by git
+    #[command(hide = true)]
+    Run {
+        /// Path of the commit message file
+        message_file: PathBuf,
+        /// Source of the commit message (message, template, merge, squash or commit)
+        source: Option<String>,
+        /// Commit SHA, given for amends
+        sha: Option<String>,
+    },
+}
+
+pub async fn handle_commit(args: CommitArgs, overrides: &[String]) -> Result<()> {
+    use crate::llm::{Conversation, MessageGenerator};
+    use crate::{config::Config, git::GitRepo};
 
     let repo = GitRepo::open()?;
-    let generator = MessageGenerator::new()?;
+    let config = Config::load_with(overrides)?;
+    let mut generator = MessageGenerator::from_config(&config)?;
+    set_repo_variables(&mut generator, &repo);
+    let convention = commit_convention(&config, args.max_length);
+    generator.set_variable("max_length", convention.max_length.to_string());
+    let prompt = read_prompt(args.prompt.as_deref(), args.prompt_file.as_deref())?;
 
-    let staged_files = repo.get_staged_files()?;
-    if staged_files.is_empty() {
-        println!("No staged changes found. Please stage your changes first.");
+    let mode = args.diff_mode();
+    let changed_files = repo.get_changed_files(mode)?;
+    if changed_files.is_empty() {
+        match mode {
+            DiffMode::Staged => {
+                println!("No staged changes found. Please stage your changes first.")
+            }
+            DiffMode::Unstaged => println!("No unstaged changes found."),
+            DiffMode::All => println!("No changes found."),
+            DiffMode::Amend => println!("HEAD and the index have no changes to describe."),
+        }
         return Ok(());
     }
 
-    let diff = repo.get_staged_diff()?;
-    let message = generator
-        .generate_commit_message(&diff, &staged_files, args.prompt.as_deref())
+    let diff = repo.get_diff(mode)?;
+    let request = generator
+        .commit_prompt(&diff, &changed_files, prompt.as_deref())
         .await?;
 
-    if args.commit {
-        repo.create_commit(&message)?;
-        println!("Commit created successfully!");
+    let Some(mut message) =
+        generate_message(&generator, &request, &args.candidates, &convention).await?
+    else {
+        println!("Aborted; no commit created.");
+        return Ok(());
+    };
+
+    if args.interactive {
+        let mut conversation = Conversation::new();
+        conversation.record(&request, &message);
+        match review_message(&generator, &repo, &convention, conversation, message).await? {
+            Some(reviewed) => message = reviewed,
+            None => {
+                println!("Aborted; no commit created.");
+                return Ok(());
+            }
+        }
+    }
+
+    if args.commit || args.interactive {
+        if mode == DiffMode::All {
+            repo.stage_all()?;
+        }
+        if mode == DiffMode::Amend {
+            repo.amend_commit(&message)?;
+            println!("Commit amended successfully!");
+        } else {
+            repo.create_commit(&message)?;
+            println!("Commit created successfully!");
+        }
     } else {
         println!("Generated commit message:\n{}\n", message);
         println!("Use --commit flag to create the commit automatically.");
@@ -133,17 +490,380 @@ pub async fn handle_commit(args: CommitArgs) -> Result<()> {
     Ok(())
 }
 
-pub async fn handle_tag(args: TagArgs) -> Result<()> {
-    use crate::{git::GitRepo, llm::MessageGenerator};
+pub async fn handle_reword(args: RewordArgs, overrides: &[String]) -> Result<()> {
+    use crate::llm::{Conversation, MessageGenerator};
+    use crate::{config::Config, git::GitRepo};
 
     let repo = GitRepo::open()?;
-    let generator = MessageGenerator::new()?;
+    let config = Config::load_with(overrides)?;
+    let mut generator = MessageGenerator::from_config(&config)?;
+    set_repo_variables(&mut generator, &repo);
+    let convention = commit_convention(&config, args.max_length);
+    generator.set_variable("max_length", convention.max_length.to_string());
+    let prompt = read_prompt(args.prompt.as_deref(), args.prompt_file.as_deref())?;
 
-    let commit_info = repo.get_commit_info(&args.reference)?;
-    let message = generator
-        .generate_tag_message(&args.name, &commit_info, args.prompt.as_deref())
+    if let Some(range) = &args.range {
+        return reword_range(&args, range, &repo, &generator, &config, prompt.as_deref()).await;
+    }
+
+    let commit_info = repo.get_commit_info(&args.revision)?;
+    let (files, diff) = repo.get_commit_changes(&args.revision)?;
+    let request = generator
+        .commit_prompt(&diff, &files, prompt.as_deref())
         .await?;
 
+    let Some(mut message) =
+        generate_message(&generator, &request, &args.candidates, &convention).await?
+    else {
+        println!("Aborted; {} left unchanged.", &commit_info.sha[..8]);
+        return Ok(());
+    };
+
+    if args.interactive {
+        let mut conversation = Conversation::new();
+        conversation.record(&request, &message);
+        match review_message(&generator, &repo, &convention, conversation, message).await? {
+            Some(reviewed) => message = reviewed,
+            None => {
+                println!("Aborted; {} left unchanged.", &commit_info.sha[..8]);
+                return Ok(());
+            }
+        }
+    }
+
+    let reworded = repo.reword_commit(&args.revision, &message, args.force)?;
+    println!(
+        "Reworded {} as {}:\n{}\n",
+        &commit_info.sha[..8],
+        &reworded.commits[0].to_string()[..8],
+        message
+    );
+    if reworded.rebased > 0 {
+        println!("Rebased {} later commit(s).", reworded.rebased);
+    }
+    println!("The original commit is kept at {}", reworded.backups[0]);
+
+    Ok(())
+}
+
+/// Regenerate the messages of the commits in `range` from their own diffs,
+/// show them next to the old ones and rewrite the branch once confirmed.
+async fn reword_range(
+    args: &RewordArgs,
+    range: &str,
+    repo: &crate::git::GitRepo,
+    generator: &crate::llm::MessageGenerator,
+    config: &crate::config::Config,
+    prompt: Option<&str>,
+) -> Result<()> {
+    use futures::{StreamExt, TryStreamExt, stream};
+    use std::io::IsTerminal;
+
+    let pattern = args
+        .only_matching
+        .as_deref()
+        .map(regex::Regex::new)
+        .transpose()
+        .context("invalid --only-matching pattern")?;
+
+    // Oldest first, so the table reads like the branch.
+    let mut commits = repo.get_commits_in_range(range)?;
+    commits.reverse();
+    commits.retain(|commit| {
+        pattern
+            .as_ref()
+            .is_none_or(|pattern| pattern.is_match(&commit.message))
+    });
+    if commits.is_empty() {
+        println!("No commits to reword in {}.", range);
+        return Ok(());
+    }
+
+    let convention = commit_convention(config, args.max_length);
+    println!("Generating {} message(s)...", commits.len());
+    let messages: Vec<String> = stream::iter(commits.iter().map(|commit| {
+        let convention = &convention;
+        async move {
+            let (files, diff) = repo.get_commit_changes(&commit.sha)?;
+            let request = generator.commit_prompt(&diff, &files, prompt).await?;
+            let message = generator.generate_message(&request).await?;
+            generator
+                .conform(&message, convention)
+                .await
+                .with_context(|| format!("could not reword {}", &commit.sha[..8]))
+        }
+    }))
+    .buffered(config.parallelism())
+    .try_collect()
+    .await?;
+
+    let rows: Vec<(String, String, String)> = commits
+        .iter()
+        .zip(&messages)
+        .map(|(commit, message)| {
+            (
+                commit.sha[..8].to_string(),
+                commit.message.clone(),
+                message.clone(),
+            )
+        })
+        .collect();
+    println!("\n{}", crate::review::before_after_table(&rows, 50));
+
+    if !args.yes {
+        if !std::io::stdin().is_terminal() {
+            anyhow::bail!("confirmation needs a terminal; pass --yes to rewrite anyway");
+        }
+        let answer = ask(&format!("Rewrite {} commit(s)? [y/N] ", commits.len()))?;
+        if !matches!(answer.to_lowercase().as_str(), "y" | "yes") {
+            println!("Aborted; nothing rewritten.");
+            return Ok(());
+        }
+    }
+
+    let messages = commits
+        .iter()
+        .zip(messages)
+        .map(|(commit, message)| Ok((git2::Oid::from_str(&commit.sha)?, message)))
+        .collect::<Result<Vec<_>>>()?;
+    let reworded = repo.reword_commits(&messages, args.force)?;
+    println!("Reworded {} commit(s).", reworded.commits.len());
+    if reworded.rebased > 0 {
+        println!("Rebased {} other commit(s).", reworded.rebased);
+    }
+    println!(
+        "The original commits are kept under {}",
+        crate::git::BACKUP_REF_PREFIX
+    );
+
+    Ok(())
+}
+
+/// Rules generated commit messages are checked against. The length limit
+/// comes from --max-length, then lint.max_header_length.
+fn commit_convention(
+    config: &crate::config::Config,
+    max_length: Option<usize>,
+) -> crate::candidates::Convention {
+    crate::candidates::Convention {
+        kind: crate::candidates::MessageKind::Commit,
+        max_length: max_length
+            .or(config.lint.max_header_length)
+            .unwrap_or(crate::lint::DEFAULT_HEADER_LENGTH),
+        types: config.types.clone(),
+        scopes: config.scopes.clone(),
+        conventional: true,
+    }
+}
+
+/// Rules generated tag messages are checked against: free-form text.
+fn tag_convention() -> crate::candidates::Convention {
+    crate::candidates::Convention {
+        kind: crate::candidates::MessageKind::Tag,
+        ..Default::default()
+    }
+}
+
+/// Generate one message, or several candidates and let the user or `--pick`
+/// choose. `None` means the user aborted.
+async fn generate_message(
+    generator: &crate::llm::MessageGenerator,
+    request: &str,
+    args: &CandidateArgs,
+    convention: &crate::candidates::Convention,
+) -> Result<Option<String>> {
+    use crate::candidates::{self, Pick};
+    use crate::postprocess;
+    use std::io::IsTerminal;
+
+    if args.candidates <= 1 {
+        let message = generator.generate_message(request).await?;
+        return Ok(Some(generator.conform(&message, convention).await?));
+    }
+
+    let pick = match args.pick {
+        Some(pick) => Some(pick),
+        None if std::io::stdin().is_terminal() => None,
+        None => anyhow::bail!("--candidates needs a terminal to choose from; pass --pick best"),
+    };
+
+    let messages = generator
+        .generate_candidates(request, args.candidates as usize)
+        .await?;
+    let messages: Vec<String> = messages.iter().map(|m| postprocess::clean(m)).collect();
+    let candidates = candidates::collect(&messages, convention);
+    if candidates.len() < messages.len() {
+        eprintln!(
+            "Dropped {} duplicate or empty candidate(s).",
+            messages.len() - candidates.len()
+        );
+    }
+
+    if let Some(pick) = pick {
+        let index = candidates::choose(&candidates, pick).context("no candidate generated")?;
+        let message = &candidates[index].message;
+        return Ok(Some(generator.conform(message, convention).await?));
+    }
+
+    let best = candidates::choose(&candidates, Pick::Best).context("no candidate generated")?;
+    for (index, candidate) in candidates.iter().enumerate() {
+        let marker = if index == best { " (best)" } else { "" };
+        println!("[{}]{}", index + 1, marker);
+        println!("{}", candidate.message);
+        for problem in &candidate.problems {
+            println!("  ! {}", problem);
+        }
+        println!();
+    }
+
+    let index = loop {
+        let answer = ask(&format!(
+            "Pick a message [1-{}, q to abort] ({}): ",
+            candidates.len(),
+            best + 1
+        ))?;
+        match answer.as_str() {
+            "" => break best,
+            "q" | "quit" => return Ok(None),
+            _ => match answer.parse::<usize>() {
+                Ok(n) if (1..=candidates.len()).contains(&n) => break n - 1,
+                _ => println!("Enter a number between 1 and {}.", candidates.len()),
+            },
+        }
+    };
+    let message = &candidates[index].message;
+    Ok(Some(generator.conform(message, convention).await?))
+}
+
+/// Show `message` until the user accepts a version of it or aborts.
+/// `conversation` holds the request that produced it.
+async fn review_message(
+    generator: &crate::llm::MessageGenerator,
+    repo: &crate::git::GitRepo,
+    convention: &crate::candidates::Convention,
+    mut conversation: crate::llm::Conversation,
+    mut message: String,
+) -> Result<Option<String>> {
+    use crate::llm::Conversation;
+    use crate::review::{self, ReviewAction};
+    use std::io::IsTerminal;
+
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!("--interactive needs a terminal on standard input");
+    }
+    let request = conversation.first_prompt().unwrap_or_default();
+
+    loop {
+        println!("\n{}\n", message);
+        let answer = ask(&format!("{} > ", review::MENU))?;
+        let Some(action) = review::parse_action(&answer) else {
+            println!("Unknown choice '{}'.", answer.trim());
+            continue;
+        };
+
+        match action {
+            ReviewAction::Accept => return Ok(Some(message)),
+            ReviewAction::Abort => return Ok(None),
+            ReviewAction::Edit => {
+                let edited = review::edit_message(&repo.editor(), &message, repo.comment_char())?;
+                // Like git, an empty message aborts.
+                if edited.is_empty() {
+                    return Ok(None);
+                }
+                message = edited;
+                conversation.replace_last_reply(&message);
+            }
+            ReviewAction::Regenerate => {
+                conversation = Conversation::new();
+                let reply = generator.converse(&mut conversation, &request).await?;
+                message = generator.conform(&reply, convention).await?;
+                conversation.replace_last_reply(&message);
+            }
+            ReviewAction::Feedback(feedback) => {
+                let feedback = if feedback.is_empty() {
+                    ask("Feedback: ")?
+                } else {
+                    feedback
+                };
+                if !feedback.trim().is_empty() {
+                    let reply = generator.refine(&mut conversation, &feedback).await?;
+                    message = generator.conform(&reply, convention).await?;
+                    conversation.replace_last_reply(&message);
+                }
+            }
+            ReviewAction::TypeScope(spec) => {
+                let spec = if spec.is_empty() {
+                    ask("Type/scope (e.g. fix, fix(api) or (api)): ")?
+                } else {
+                    spec
+                };
+                match review::replace_type_scope(&message, &spec) {
+                    Ok(replaced) => {
+                        message = replaced;
+                        conversation.replace_last_reply(&message);
+                    }
+                    Err(e) => println!("{}", e),
+                }
+            }
+        }
+    }
+}
+
+/// Read one line from the terminal after printing `question`.
+fn ask(question: &str) -> Result<String> {
+    use std::io::Write;
+
+    print!("{}", question);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer)? == 0 {
+        anyhow::bail!("standard input closed");
+    }
+    Ok(answer.trim().to_string())
+}
+
+pub async fn handle_tag(args: TagArgs, overrides: &[String]) -> Result<()> {
+    use crate::llm::{MessageGenerator, TagRelease};
+    use crate::{config::Config, git::GitRepo};
+
+    let repo = GitRepo::open()?;
+    let mut generator = MessageGenerator::from_config(&Config::load_with(overrides)?)?;
+    set_repo_variables(&mut generator, &repo);
+    let prompt = read_prompt(args.prompt.as_deref(), args.prompt_file.as_deref())?;
+
+    let previous = match args.since {
+        Some(since) => Some(since),
+        None => repo.previous_tag(&args.reference)?,
+    };
+    let commits = match &previous {
+        Some(previous) => {
+            repo.get_commits_in_range(&format!("{}..{}", previous, args.reference))?
+        }
+        None => repo.get_commits_reachable(&args.reference)?,
+    };
+    match &previous {
+        Some(previous) => println!("Describing {} commit(s) since {}.", commits.len(), previous),
+        None => println!(
+            "No previous tag; describing all {} commit(s).",
+            commits.len()
+        ),
+    }
+
+    let release = TagRelease {
+        previous: previous.as_deref(),
+        commits: &commits,
+    };
+    let request = generator
+        .tag_prompt(&args.name, &release, prompt.as_deref())
+        .await?;
+    let convention = tag_convention();
+    let Some(message) =
+        generate_message(&generator, &request, &args.candidates, &convention).await?
+    else {
+        println!("Aborted; no tag created.");
+        return Ok(());
+    };
+
     if args.annotated {
         repo.create_annotated_tag(&args.name, &message, &args.reference)?;
         println!("Annotated tag '{}' created successfully!", args.name);
@@ -155,18 +875,99 @@ pub async fn handle_tag(args: TagArgs) -> Result<()> {
     Ok(())
 }
 
-pub async fn handle_changelog(args: ChangelogArgs) -> Result<()> {
-    use crate::{changelog::ChangelogGenerator, git::GitRepo};
+pub async fn handle_changelog(args: ChangelogArgs, overrides: &[String]) -> Result<()> {
+    use crate::changelog::{Changelog, ChangelogGenerator, UpdateMode};
+    use crate::version::{self, Version};
+    use crate::{config::Config, git::GitRepo};
 
     let repo = GitRepo::open()?;
-    let generator = ChangelogGenerator::new()?;
+    let config = Config::load_with(overrides)?;
+    let mut generator = ChangelogGenerator::from_config(&config)?;
 
-    let commits = if let Some(range) = &args.range {
-        repo.get_commits_in_range(range)?
-    } else {
-        repo.get_recent_commits(args.commits)?
+    let existing = match &args.output {
+        Some(path) if args.append => match std::fs::read_to_string(path) {
+            Ok(content) => Some(Changelog::parse(&content)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        },
+        _ => None,
     };
 
+    // A range ending at a version tag documents that version; otherwise
+    // the section is for the version the unreleased commits lead to.
+    let tagged = args
+        .range
+        .as_deref()
+        .and_then(|range| range.split_once(".."))
+        .and_then(|(_, end)| Version::from_tag(end, config.tag_prefix()));
+    let release = match tagged {
+        Some(_) => None,
+        None => Some(version::next_release(
+            &repo,
+            config.tag_prefix(),
+            None,
+            crate::release::manifest_version(repo.workdir()?)?.as_ref(),
+        )?),
+    };
+    let version = tagged.or_else(|| release.as_ref().and_then(|release| release.next.clone()));
+    let heading = version
+        .as_ref()
+        .map_or("Unreleased".to_string(), Version::to_string);
+    if args.mode == UpdateMode::Skip
+        && let Some(existing) = &existing
+        && existing.release(&heading).is_some()
+    {
+        println!(
+            "The changelog already has a section for {}; skipped.",
+            heading
+        );
+        return Ok(());
+    }
+    if let Some(version) = version {
+        generator.set_version(version.to_string());
+    }
+
+    // Without a range, continue after the last commit the changelog covers.
+    let recorded = existing
+        .as_ref()
+        .and_then(|existing| existing.last_commit.clone());
+    let last_commit = recorded
+        .clone()
+        .filter(|last| repo.is_ancestor(last, "HEAD").unwrap_or(false));
+    let mut commits = match (&args.range, release) {
+        (Some(range), _) => repo.get_commits_in_range(range)?,
+        // A section headed with the next version lists the commits leading to it.
+        (None, Some(release)) if release.next.is_some() => {
+            let mut commits = release.commits;
+            if let Some(last) = &last_commit {
+                commits.retain(|commit| !repo.is_ancestor(&commit.sha, last).unwrap_or(false));
+            }
+            commits
+        }
+        (None, _) => match &last_commit {
+            Some(last) => repo.get_commits_in_range(&format!("{}..HEAD", last))?,
+            None => repo.get_recent_commits(args.commits)?,
+        },
+    };
+    // The marker only moves forward, and never for an explicit range.
+    if args.range.is_none()
+        && let Some(newest) = commits.first()
+        && recorded
+            .as_deref()
+            .is_none_or(|recorded| repo.is_ancestor(recorded, &newest.sha).unwrap_or(false))
+    {
+        generator.set_last_commit(newest.sha.clone());
+    }
+    if let Some(existing) = &existing {
+        let mentions = existing.mentions();
+        commits.retain(|commit| !mentions.contains(&commit.sha));
+    }
+    if commits.is_empty() {
+        println!("No new commits for the changelog.");
+        return Ok(());
+    }
+    generator.set_mode(args.mode);
+
     let changelog = generator.generate_changelog(&commits).await?;
 
     if let Some(output_path) = &args.output {
@@ -179,19 +980,214 @@ pub async fn handle_changelog(args: ChangelogArgs) -> Result<()> {
     Ok(())
 }
 
-pub async fn handle_config(args: ConfigArgs) -> Result<()> {
+pub async fn handle_release(args: ReleaseArgs, overrides: &[String]) -> Result<()> {
+    use crate::llm::{MessageGenerator, TagRelease};
+    use crate::release::{self, FileChange, ReleasePlan};
+    use crate::version::{self, Version};
+    use crate::{changelog::ChangelogGenerator, config::Config, git::GitRepo};
+
+    let repo = GitRepo::open()?;
+    let config = Config::load_with(overrides)?;
+    let prefix = config.tag_prefix();
+    let channel = args.pre.as_deref();
+
+    let workdir = repo.workdir()?.to_path_buf();
+    let current = release::manifest_version(&workdir)?;
+    let release = version::next_release(&repo, prefix, channel, current.as_ref())?;
+    let version = match args.bump() {
+        Some(bump) => release
+            .released
+            .clone()
+            .unwrap_or(Version::new(0, 0, 0))
+            .advance(bump, channel),
+        None => release.next.clone(),
+    };
+    let Some(version) = version else {
+        let since = release
+            .previous
+            .as_ref()
+            .map_or("the first commit".to_string(), |previous| {
+                previous.tag.clone()
+            });
+        anyhow::bail!(
+            "no commit since {} calls for a release; pass --major, --minor or --patch",
+            since
+        );
+    };
+    if let Some(current) = current
+        && version < current
+    {
+        anyhow::bail!(
+            "version {} is lower than the current version {} in the manifests",
+            version,
+            current
+        );
+    }
+    let tag = format!("{}{}", prefix, version);
+    if repo.tag_exists(&tag) {
+        anyhow::bail!("tag '{}' already exists", tag);
+    }
+
+    let mut changes = release::version_changes(&workdir, &version, &config.release.files)?;
+
+    let changelog_path = config
+        .release
+        .changelog
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(release::DEFAULT_CHANGELOG));
+    let mut paths: Vec<PathBuf> = changes.iter().map(|change| change.path.clone()).collect();
+    paths.push(changelog_path.clone());
+    if let Err(e) = release::check_clean(&repo, &paths) {
+        if !args.dry_run {
+            return Err(e);
+        }
+        eprintln!("Warning: {}", e);
+    }
+
+    println!("Preparing release {}...", version);
+    let mut changelog = ChangelogGenerator::from_config(&config)?;
+    changelog.set_version(version.to_string());
+    if let Some(newest) = release.commits.first() {
+        changelog.set_last_commit(newest.sha.clone());
+    }
+    let section = changelog.generate_changelog(&release.commits).await?;
+    let full_path = workdir.join(&changelog_path);
+    let before = std::fs::read_to_string(&full_path).ok();
+    let after =
+        changelog.updated_changelog(&section, &full_path.to_string_lossy(), before.is_some())?;
+    changes.push(FileChange {
+        path: changelog_path,
+        before,
+        after,
+        summary: format!("add section [{}]", version),
+    });
+
+    let mut generator = MessageGenerator::from_config(&config)?;
+    set_repo_variables(&mut generator, &repo);
+    let tag_release = TagRelease {
+        previous: release
+            .previous
+            .as_ref()
+            .map(|previous| previous.tag.as_str()),
+        commits: &release.commits,
+    };
+    let request = generator.tag_prompt(&tag, &tag_release, None).await?;
+    let tag_message = generator
+        .conform(
+            &generator.generate_message(&request).await?,
+            &tag_convention(),
+        )
+        .await?;
+
+    let plan = ReleasePlan {
+        previous: release.previous,
+        version,
+        bump: release.bump,
+        commits: release.commits.len(),
+        changes,
+        commit_message: format!("chore(release): {}", tag),
+        tag: tag.clone(),
+        tag_message,
+    };
+
+    println!("{}", plan.describe());
+    if args.dry_run {
+        println!("Dry run; nothing was changed.");
+        return Ok(());
+    }
+
+    plan.apply(&repo)?;
+    println!("Released {}: committed and tagged {}.", plan.version, tag);
+    println!("Push it with: git push --follow-tags");
+
+    Ok(())
+}
+
+pub async fn handle_lint(args: LintArgs, overrides: &[String]) -> Result<()> {
+    use crate::config::ConfigLoader;
+    use crate::git::GitRepo;
+    use crate::lint::{self, LintFormat, LintRules, Report};
+
+    // Linting needs no LLM settings, so never start the setup wizard.
+    let config = ConfigLoader::new()?.overrides(overrides)?.load()?.config;
+    let rules = LintRules::from_config(&config);
+
+    let reports = match &args.file {
+        Some(file) => {
+            let text = std::fs::read_to_string(file)
+                .with_context(|| format!("could not read {:?}", file))?;
+            let comment_char = GitRepo::open().map_or('#', |repo| repo.comment_char());
+            let message = lint::clean_message(&text, comment_char);
+            vec![Report::new(file.display().to_string(), &message, &rules)]
+        }
+        None => {
+            let repo = GitRepo::open()?;
+            let commits = match &args.range {
+                Some(range) => repo.get_commits_in_range(range)?,
+                None => vec![repo.get_commit_info(args.commit.as_deref().unwrap_or("HEAD"))?],
+            };
+            commits
+                .iter()
+                .map(|commit| Report::new(&commit.sha[..8], &commit.message, &rules))
+                .collect()
+        }
+    };
+
+    match args.format {
+        LintFormat::Text => print!("{}", lint::format_text(&reports)),
+        LintFormat::Json => println!("{}", lint::format_json(&reports)?),
+    }
+
+    let problems: usize = reports.iter().map(|report| report.diagnostics.len()).sum();
+    if problems > 0 {
+        anyhow::bail!("{} problem(s) found", problems);
+    }
+    Ok(())
+}
+
+pub async fn handle_config(args: ConfigArgs, overrides: &[String]) -> Result<()> {
     use crate::config::Config;
 
+    if let Some(command) = args.command {
+        // Keyring access blocks, so keep it off the async runtime.
+        let overrides = overrides.to_vec();
+        return tokio::task::spawn_blocking(move || run_config_command(command, &overrides))
+            .await
+            .context("config command panicked")?;
+    }
+
     if args.init {
         Config::create_config()?;
     } else if args.path {
         let config_path = Config::get_config_path()?;
         println!("配置文件路径: {:?}", config_path);
+    } else if args.show_origin {
+        let layered = Config::load_layered(overrides)?;
+        let entries = layered.entries();
+        let key_width = entries
+            .iter()
+            .map(|(key, _, _)| key.len())
+            .max()
+            .unwrap_or(0);
+        let value_width = entries
+            .iter()
+            .map(|(_, value, _)| value.lines().next().unwrap_or("").chars().count())
+            .max()
+            .unwrap_or(0);
+        for (key, value, origin) in entries {
+            println!(
+                "{:<key_width$}  {:<value_width$}  {}",
+                key,
+                first_line(&value),
+                origin,
+                value_width = value_width + 4
+            );
+        }
     } else if args.validate {
-        match Config::load() {
+        match Config::load_with(overrides) {
             Ok(config) => match config.validate() {
                 Ok(_) => println!("配置验证成功！"),
-                Err(e) => println!("配置验证失败: {}", e),
+                Err(e) => println!("配置验证失败: {:#}", e),
             },
             Err(e) => println!("无法加载配置: {}", e),
         }
@@ -200,12 +1196,262 @@ pub async fn handle_config(args: ConfigArgs) -> Result<()> {
         let config_path = Config::get_config_path()?;
         println!("git-automessage 配置管理");
         println!("配置文件路径: {:?}", config_path);
-        println!("");
+        println!();
         println!("可用命令:");
         println!("  git-automessage config --init      初始化配置文件");
+        println!(
+            "  git-automessage config init --non-interactive --provider <名称> --model <模型>"
+        );
+        println!("                                     非交互初始化（适用于 CI）");
+        println!("  git-automessage config get|set|unset <key> [--global|--local]");
+        println!("                                     读取或修改配置项");
+        println!("  git-automessage config list        列出生效的配置");
         println!("  git-automessage config --path      显示配置文件路径");
         println!("  git-automessage config --validate  验证当前配置");
+        println!("  git-automessage config --show-origin  显示生效的配置及其来源");
+    }
+
+    Ok(())
+}
+
+fn run_config_command(command: ConfigCommand, overrides: &[String]) -> Result<()> {
+    use crate::config::{Config, ConfigFile, ConfigKey, ConfigScope};
+    use crate::secret::KeyringStore;
+
+    const SECRET_KEY: &str = "llm.api_key";
+
+    match command {
+        ConfigCommand::Init(init) => init_config(init)?,
+        ConfigCommand::Get { key, scope } => {
+            ConfigKey::find(&key)?;
+            let value = match scope.scope() {
+                Some(scope) => ConfigFile::open(scope)?.get(&key)?,
+                None => Config::load_layered(overrides)?
+                    .entries()
+                    .into_iter()
+                    .find(|(name, _, _)| *name == key)
+                    .map(|(_, value, _)| value),
+            };
+            match value {
+                Some(value) => println!("{}", value),
+                None => anyhow::bail!("配置项 {} 未设置", key),
+            }
+        }
+        ConfigCommand::Set {
+            key,
+            value,
+            scope,
+            plain,
+        } => {
+            let mut file = ConfigFile::open(scope.scope().unwrap_or(ConfigScope::Global))?;
+            if key == SECRET_KEY && !plain {
+                let stored = file.set_secret(&key, &value, &KeyringStore::new())?;
+                file.save()?;
+                println!("已设置 {} = {}（{:?}）", key, stored, file.path());
+            } else {
+                file.set(&key, &value)?;
+                file.save()?;
+                println!("已设置 {}（{:?}）", key, file.path());
+            }
+        }
+        ConfigCommand::Unset { key, scope } => {
+            let mut file = ConfigFile::open(scope.scope().unwrap_or(ConfigScope::Global))?;
+            let removed = if key == SECRET_KEY {
+                file.unset_secret(&key, &KeyringStore::new())?
+            } else {
+                file.unset(&key)?
+            };
+            if removed {
+                file.save()?;
+                println!("已删除 {}（{:?}）", key, file.path());
+            } else {
+                println!("{:?} 中没有设置 {}", file.path(), key);
+            }
+        }
+        ConfigCommand::List { scope } => {
+            let entries: Vec<(String, String)> = match scope.scope() {
+                Some(scope) => ConfigFile::open(scope)?.entries(),
+                None => Config::load_layered(overrides)?
+                    .entries()
+                    .into_iter()
+                    .map(|(key, value, _)| (key, value))
+                    .collect(),
+            };
+            for (key, value) in entries {
+                println!("{}={}", key, first_line(&value));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Multi-line values such as prompts are shown by their first line only.
+fn first_line(value: &str) -> String {
+    let mut lines = value.lines();
+    let mut shown = lines.next().unwrap_or("").to_string();
+    if lines.next().is_some() {
+        shown.push_str(" ...");
+    }
+    shown
+}
+
+fn init_config(args: InitArgs) -> Result<()> {
+    use crate::config::Config;
+
+    let config_path = Config::get_config_path()?;
+    if config_path.exists() && !args.force {
+        println!("配置文件已存在: {:?}（使用 --force 覆盖）", config_path);
+        return Ok(());
+    }
+
+    let mut config = Config::create(
+        args.provider.as_deref(),
+        args.base_url.as_deref(),
+        args.api_key.as_deref(),
+        args.model.as_deref(),
+        !args.non_interactive,
+    )?;
+    if let Some(language) = args.language {
+        config.language = language;
+    }
+    config.validate().context("配置验证失败")?;
+    config.save()?;
+
+    println!("已创建配置文件: {:?}", config_path);
+    Ok(())
+}
+
+pub async fn handle_hook(args: HookArgs, overrides: &[String]) -> Result<()> {
+    use crate::git::GitRepo;
+    use crate::hook::{HookManager, HookStatus};
+
+    let manager = || -> Result<HookManager> { HookManager::new(&GitRepo::open()?) };
+
+    match args.command {
+        HookCommand::Install => {
+            let manager = manager()?;
+            match manager.install()? {
+                HookStatus::Installed { chained: true } => println!(
+                    "Hook installed at {:?}; existing hook kept as {:?} and run first.",
+                    manager.hook_path(),
+                    manager.chained_path()
+                ),
+                _ => println!("Hook installed at {:?}", manager.hook_path()),
+            }
+        }
+        HookCommand::Uninstall => {
+            let manager = manager()?;
+            manager.uninstall()?;
+            println!("Hook removed from {:?}", manager.hook_path());
+        }
+        HookCommand::Status => {
+            let manager = manager()?;
+            match manager.status()? {
+                HookStatus::NotInstalled => {
+                    println!("Hook not installed ({:?})", manager.hook_path())
+                }
+                HookStatus::Installed { chained } => {
+                    println!("Hook installed at {:?}", manager.hook_path());
+                    if chained {
+                        println!("Chaining existing hook {:?}", manager.chained_path());
+                    }
+                }
+                HookStatus::Foreign => println!(
+                    "Another prepare-commit-msg hook is installed at {:?}",
+                    manager.hook_path()
+                ),
+            }
+        }
+        HookCommand::Run {
+            message_file,
+            source,
+            ..
+        } => {
+            // A failing hook aborts the commit, so only warn and let git carry on.
+            if let Err(e) = run_hook(&message_file, source.as_deref(), overrides).await {
+                eprintln!(
+                    "git-automessage: could not generate commit message: {:#}",
+                    e
+                );
+            }
+        }
     }
 
     Ok(())
 }
+
+async fn run_hook(
+    message_file: &std::path::Path,
+    source: Option<&str>,
+    overrides: &[String],
+) -> Result<()> {
+    use crate::{config::Config, git::GitRepo, hook, llm::MessageGenerator};
+
+    if !hook::should_generate(source) {
+        return Ok(());
+    }
+
+    let mut repo = GitRepo::open()?;
+    if let Ok(index_file) = std::env::var("GIT_INDEX_FILE") {
+        repo.use_index_file(index_file)?;
+    }
+
+    // With commit.template set, git fills the file with the template; it
+    // only counts as a message once the user changed it.
+    let template = match source {
+        Some("template") => repo.commit_template(),
+        _ => None,
+    };
+    let existing = std::fs::read_to_string(message_file).unwrap_or_default();
+    if hook::has_message(&existing, template.as_deref(), repo.comment_char()) {
+        return Ok(());
+    }
+
+    let staged_files = repo.get_staged_files()?;
+    if staged_files.is_empty() {
+        return Ok(());
+    }
+
+    let config = Config::load_with(overrides)?;
+    let mut generator = MessageGenerator::from_config(&config)?;
+    set_repo_variables(&mut generator, &repo);
+    let diff = repo.get_staged_diff()?;
+    let message = generator
+        .generate_commit_message(&diff, &staged_files, None)
+        .await?;
+    let message = generator
+        .conform(&message, &commit_convention(&config, None))
+        .await?;
+
+    hook::write_message_file(message_file, &message, repo.comment_char())
+}
+
+/// Number of recent commit subjects offered to templates as {recent_commits}.
+const RECENT_COMMITS: usize = 5;
+
+/// Fill the template variables that come from the repository.
+fn set_repo_variables(generator: &mut crate::llm::MessageGenerator, repo: &crate::git::GitRepo) {
+    if let Some(branch) = repo.current_branch() {
+        generator.set_variable("branch", branch);
+    }
+
+    let subjects: Vec<String> = repo
+        .get_recent_commits(RECENT_COMMITS)
+        .unwrap_or_default()
+        .iter()
+        .map(|commit| format!("- {}", commit.message.lines().next().unwrap_or("").trim()))
+        .collect();
+    generator.set_variable("recent_commits", subjects.join("\n"));
+}
+
+/// The custom prompt given inline or through --prompt-file.
+fn read_prompt(
+    prompt: Option<&str>,
+    prompt_file: Option<&std::path::Path>,
+) -> Result<Option<String>> {
+    match prompt_file {
+        Some(path) => Ok(Some(crate::prompts::load_template_file(path)?)),
+        None => Ok(prompt.map(str::to_string)),
+    }
+}
@@ -0,0 +1,367 @@
+//     _         _        __  __
+//    / \  _   _| |_ ___ |  \/  | ___  ___ ___  __ _  __ _  ___
+//   / _ \| | | | __/ _ \| |\/| |/ _ \/ __/ __|/ _` |/ _` |/ _ \
+//  / ___ \ |_| | || (_) | |  | |  __/\__ \__ \ (_| | (_| |  __/
+// /_/   \_\__,_|\__\___/|_|  |_|\___||___/___/\__,_|\__, |\___|
+//                                                   |___/
+//
+// Author: Sidney Zhang <zly@lyzhang.me>
+// Date: 2025-08-05
+// License: MIT
+//
+// Reading the rules of an existing commitlint configuration.
+
+use anyhow::{Context, Result};
+use serde_yaml::{Mapping, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Configuration files in the order commitlint looks for them.
+pub const FILE_NAMES: &[&str] = &[
+    "package.json",
+    ".commitlintrc",
+    ".commitlintrc.json",
+    ".commitlintrc.yaml",
+    ".commitlintrc.yml",
+    ".commitlintrc.js",
+    ".commitlintrc.cjs",
+    ".commitlintrc.mjs",
+    ".commitlintrc.ts",
+    "commitlint.config.js",
+    "commitlint.config.cjs",
+    "commitlint.config.mjs",
+    "commitlint.config.ts",
+];
+
+/// The rules git-automessage shares with commitlint. `None` and empty lists
+/// leave the setting to git-automessage's own configuration.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommitlintRules {
+    /// `type-enum`
+    pub types: Vec<String>,
+    /// `scope-enum`
+    pub scopes: Vec<String>,
+    /// `header-max-length`
+    pub header_max_length: Option<usize>,
+    /// `body-max-line-length`
+    pub body_max_line_length: Option<usize>,
+}
+
+impl CommitlintRules {
+    /// The rules of `@commitlint/config-conventional`.
+    pub fn conventional() -> Self {
+        CommitlintRules {
+            types: [
+                "build", "chore", "ci", "docs", "feat", "fix", "perf", "refactor", "revert",
+                "style", "test",
+            ]
+            .iter()
+            .map(|kind| kind.to_string())
+            .collect(),
+            scopes: Vec::new(),
+            header_max_length: Some(100),
+            body_max_line_length: Some(100),
+        }
+    }
+
+    /// The rules as a configuration layer: `types`, `scopes`,
+    /// `lint.max_header_length` and `lint.body_width`.
+    pub fn to_layer(&self) -> Value {
+        let list =
+            |items: &[String]| Value::Sequence(items.iter().map(|s| s.as_str().into()).collect());
+
+        let mut values = Mapping::new();
+        if !self.types.is_empty() {
+            values.insert("types".into(), list(&self.types));
+        }
+        if !self.scopes.is_empty() {
+            values.insert("scopes".into(), list(&self.scopes));
+        }
+        let mut lint = Mapping::new();
+        if let Some(length) = self.header_max_length {
+            lint.insert("max_header_length".into(), (length as u64).into());
+        }
+        if let Some(width) = self.body_max_line_length {
+            lint.insert("body_width".into(), (width as u64).into());
+        }
+        if !lint.is_empty() {
+            values.insert("lint".into(), Value::Mapping(lint));
+        }
+        Value::Mapping(values)
+    }
+
+    /// Apply a `rules` object on top of these rules.
+    fn apply(&mut self, rules: &Value) {
+        for (name, rule) in rules.as_mapping().into_iter().flatten() {
+            let Some(name) = name.as_str() else { continue };
+            let setting = Setting::parse(rule);
+            match name {
+                "type-enum" => self.types = setting.list(),
+                "scope-enum" => self.scopes = setting.list(),
+                "header-max-length" => self.header_max_length = setting.number(),
+                "body-max-line-length" => self.body_max_line_length = setting.number(),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// A rule such as `[2, 'always', ['feat', 'fix']]`. Disabled rules and
+/// `never` rules carry no value we can use.
+struct Setting<'a>(Option<&'a Value>);
+
+impl<'a> Setting<'a> {
+    fn parse(rule: &'a Value) -> Self {
+        let Some(items) = rule.as_sequence() else {
+            return Setting(None);
+        };
+        let level = items.first().and_then(Value::as_u64).unwrap_or(0);
+        let applicable = items.get(1).and_then(Value::as_str).unwrap_or("always");
+        if level == 0 || applicable != "always" {
+            return Setting(None);
+        }
+        Setting(items.get(2))
+    }
+
+    fn list(&self) -> Vec<String> {
+        self.0
+            .and_then(Value::as_sequence)
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn number(&self) -> Option<usize> {
+        self.0
+            .and_then(Value::as_u64)
+            .filter(|&n| n > 0)
+            .map(|n| n as usize)
+    }
+}
+
+/// The commitlint configuration in `dir`, if there is one.
+pub fn find(dir: &Path) -> Option<PathBuf> {
+    FILE_NAMES
+        .iter()
+        .map(|name| dir.join(name))
+        .filter(|path| path.is_file())
+        .find(|path| {
+            // package.json only counts when it has a "commitlint" section.
+            !path.ends_with("package.json")
+                || fs::read_to_string(path)
+                    .ok()
+                    .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+                    .is_some_and(|json| json.get("commitlint").is_some())
+        })
+}
+
+/// Read the rules from a commitlint configuration file. JavaScript and
+/// TypeScript files are not run; only a literal `rules` object is read.
+pub fn load(path: &Path) -> Result<CommitlintRules> {
+    let content = fs::read_to_string(path).with_context(|| format!("could not read {:?}", path))?;
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("");
+
+    let rules = if name == "package.json" {
+        let json: Value = serde_yaml::from_str(&content)
+            .with_context(|| format!("invalid JSON in {:?}", path))?;
+        from_config(json.get("commitlint").unwrap_or(&Value::Null))
+    } else if [".js", ".cjs", ".mjs", ".ts"]
+        .iter()
+        .any(|extension| name.ends_with(extension))
+    {
+        from_script(&content).with_context(|| format!("could not read the rules in {:?}", path))?
+    } else {
+        // JSON is valid YAML.
+        let config: Value = serde_yaml::from_str(&content)
+            .with_context(|| format!("invalid YAML in {:?}", path))?;
+        from_config(&config)
+    };
+    Ok(rules)
+}
+
+/// Rules of a parsed JSON or YAML configuration.
+fn from_config(config: &Value) -> CommitlintRules {
+    let extends_conventional = match config.get("extends") {
+        Some(Value::String(preset)) => preset.contains("config-conventional"),
+        Some(Value::Sequence(presets)) => presets
+            .iter()
+            .filter_map(Value::as_str)
+            .any(|preset| preset.contains("config-conventional")),
+        _ => false,
+    };
+
+    let mut rules = if extends_conventional {
+        CommitlintRules::conventional()
+    } else {
+        CommitlintRules::default()
+    };
+    if let Some(object) = config.get("rules") {
+        rules.apply(object);
+    }
+    rules
+}
+
+/// Rules of a JavaScript configuration, read from its `rules: { ... }`
+/// object literal.
+fn from_script(source: &str) -> Result<CommitlintRules> {
+    let source = strip_comments(source);
+    let mut rules = if source.contains("config-conventional") {
+        CommitlintRules::conventional()
+    } else {
+        CommitlintRules::default()
+    };
+
+    if let Some(object) = object_after(&source, "rules") {
+        let object = object
+            .replace("RuleConfigSeverity.Disabled", "0")
+            .replace("RuleConfigSeverity.Warning", "1")
+            .replace("RuleConfigSeverity.Error", "2");
+        // The literal is close enough to a YAML flow mapping once trailing
+        // commas are gone: keys may be unquoted and strings single-quoted.
+        let value: Value = serde_yaml::from_str(&strip_trailing_commas(&object))
+            .context("the rules object is not a plain literal")?;
+        rules.apply(&value);
+    }
+    Ok(rules)
+}
+
+/// The `{ ... }` literal assigned to `key`, e.g. in `rules: {` or `'rules': {`.
+fn object_after<'a>(source: &'a str, key: &str) -> Option<&'a str> {
+    let mut search = 0;
+    while let Some(found) = source[search..].find(key) {
+        let start = search + found;
+        search = start + key.len();
+
+        let before = source[..start].chars().next_back();
+        if before.is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '$' || c == '.') {
+            continue;
+        }
+        let rest = source[search..]
+            .trim_start_matches(['\'', '"'])
+            .trim_start();
+        let Some(rest) = rest.strip_prefix(':') else {
+            continue;
+        };
+        let rest = rest.trim_start();
+        if !rest.starts_with('{') {
+            continue;
+        }
+        let open = source.len() - rest.len();
+        return matching_brace(source, open).map(|close| &source[open..=close]);
+    }
+    None
+}
+
+/// Index of the `}` closing the `{` at `open`, skipping strings.
+fn matching_brace(source: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    for (index, c) in source[open..].char_indices() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '\'' | '"' | '`' => quote = Some(c),
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + index);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Remove `//` and `/* */` comments outside strings.
+fn strip_comments(source: &str) -> String {
+    let mut output = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    let mut quote = None;
+    let mut escaped = false;
+
+    while let Some(c) = chars.next() {
+        if let Some(q) = quote {
+            output.push(c);
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('/', Some('/')) => {
+                for next in chars.by_ref() {
+                    if next == '\n' {
+                        output.push('\n');
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut last = ' ';
+                for next in chars.by_ref() {
+                    if last == '*' && next == '/' {
+                        break;
+                    }
+                    last = next;
+                }
+            }
+            ('\'' | '"' | '`', _) => {
+                quote = Some(c);
+                output.push(c);
+            }
+            _ => output.push(c),
+        }
+    }
+    output
+}
+
+/// Drop commas directly before `]` or `}`, outside strings.
+fn strip_trailing_commas(source: &str) -> String {
+    let mut output = String::with_capacity(source.len());
+    let mut quote = None;
+    for c in source.chars() {
+        match quote {
+            Some(q) => {
+                if c == q {
+                    quote = None;
+                }
+            }
+            None => match c {
+                '\'' | '"' => quote = Some(c),
+                ']' | '}' => {
+                    let trimmed = output.trim_end().len();
+                    if output[..trimmed].ends_with(',') {
+                        output.truncate(trimmed - 1);
+                    }
+                }
+                _ => {}
+            },
+        }
+        output.push(c);
+    }
+    output
+}
@@ -0,0 +1,303 @@
+//     _         _        __  __
+//    / \  _   _| |_ ___ |  \/  | ___  ___ ___  __ _  __ _  ___
+//   / _ \| | | | __/ _ \| |\/| |/ _ \/ __/ __|/ _` |/ _` |/ _ \
+//  / ___ \ |_| | || (_) | |  | |  __/\__ \__ \ (_| | (_| |  __/
+// /_/   \_\__,_|\__\___/|_|  |_|\___||___/___/\__,_|\__, |\___|
+//                                                   |___/
+//
+// Author: Sidney Zhang <zly@lyzhang.me>
+// Date: 2025-08-05
+// License: MIT
+//
+// Shrinking large diffs to fit a token budget.
+
+/// Rough token count of `text`: about four ASCII characters per token, and
+/// one token per other character (CJK text tokenizes close to that).
+pub fn estimate_tokens(text: &str) -> usize {
+    let (ascii, other) = text.chars().fold((0usize, 0usize), |(ascii, other), c| {
+        if c.is_ascii() {
+            (ascii + 1, other)
+        } else {
+            (ascii, other + 1)
+        }
+    });
+    ascii.div_ceil(4) + other
+}
+
+/// Whether `path` is a lockfile, generated or vendored file, which says
+/// little about the intent of a change.
+pub fn is_noise(path: &str) -> bool {
+    const LOCKFILES: &[&str] = &[
+        "Cargo.lock",
+        "package-lock.json",
+        "npm-shrinkwrap.json",
+        "yarn.lock",
+        "pnpm-lock.yaml",
+        "bun.lockb",
+        "poetry.lock",
+        "Pipfile.lock",
+        "uv.lock",
+        "Gemfile.lock",
+        "composer.lock",
+        "go.sum",
+        "flake.lock",
+        "packages.lock.json",
+    ];
+    const GENERATED_SUFFIXES: &[&str] = &[
+        ".min.js", ".min.css", ".js.map", ".css.map", ".pb.go", "_pb2.py", ".g.dart", ".snap",
+    ];
+    const VENDORED_DIRS: &[&str] = &["vendor", "node_modules", "third_party", "third-party"];
+
+    let name = path.rsplit('/').next().unwrap_or(path);
+    LOCKFILES.contains(&name)
+        || name.ends_with(".lock")
+        || GENERATED_SUFFIXES
+            .iter()
+            .any(|suffix| name.ends_with(suffix))
+        || name.contains(".generated.")
+        || path
+            .split('/')
+            .rev()
+            .skip(1)
+            .any(|dir| VENDORED_DIRS.contains(&dir))
+}
+
+/// How much of a file's diff is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Detail {
+    Full,
+    HunkHeaders,
+    Stats,
+    Dropped,
+}
+
+/// One file of a unified diff.
+#[derive(Debug, Clone)]
+pub struct FileDiff {
+    pub path: String,
+    /// `diff --git`, `index`, `---`/`+++` and similar lines.
+    header: Vec<String>,
+    hunks: Vec<Hunk>,
+    pub added: usize,
+    pub removed: usize,
+    detail: Detail,
+}
+
+#[derive(Debug, Clone)]
+struct Hunk {
+    header: String,
+    lines: Vec<String>,
+}
+
+impl FileDiff {
+    fn render(&self) -> String {
+        match self.detail {
+            Detail::Full => {
+                let mut text = self.header.join("\n");
+                for hunk in &self.hunks {
+                    text.push('\n');
+                    text.push_str(&hunk.header);
+                    for line in &hunk.lines {
+                        text.push('\n');
+                        text.push_str(line);
+                    }
+                }
+                text.push('\n');
+                text
+            }
+            Detail::HunkHeaders => {
+                let mut text = self.header.join("\n");
+                for hunk in &self.hunks {
+                    let added = hunk.lines.iter().filter(|l| l.starts_with('+')).count();
+                    let removed = hunk.lines.iter().filter(|l| l.starts_with('-')).count();
+                    text.push_str(&format!("\n{} (+{} -{})", hunk.header, added, removed));
+                }
+                text.push('\n');
+                text
+            }
+            Detail::Stats => format!("{} | +{} -{}\n", self.path, self.added, self.removed),
+            Detail::Dropped => String::new(),
+        }
+    }
+
+    fn tokens(&self) -> usize {
+        estimate_tokens(&self.render())
+    }
+}
+
+/// Split a unified diff as produced by `git diff` into files.
+pub fn parse_diff(diff: &str) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = Vec::new();
+
+    for line in diff.lines() {
+        if line.starts_with("diff --git ") || files.is_empty() {
+            files.push(FileDiff {
+                path: path_from_header(line).unwrap_or_default(),
+                header: Vec::new(),
+                hunks: Vec::new(),
+                added: 0,
+                removed: 0,
+                detail: Detail::Full,
+            });
+        }
+        let file = files.last_mut().expect("a file was just pushed");
+
+        if line.starts_with("@@") {
+            file.hunks.push(Hunk {
+                header: line.to_string(),
+                lines: Vec::new(),
+            });
+        } else if let Some(hunk) = file.hunks.last_mut() {
+            if line.starts_with('+') {
+                file.added += 1;
+            } else if line.starts_with('-') {
+                file.removed += 1;
+            }
+            hunk.lines.push(line.to_string());
+        } else {
+            if let Some(path) = line.strip_prefix("+++ b/") {
+                file.path = path.to_string();
+            }
+            file.header.push(line.to_string());
+        }
+    }
+
+    files
+}
+
+/// `b/` path of a `diff --git a/old b/new` line.
+fn path_from_header(line: &str) -> Option<String> {
+    let rest = line.strip_prefix("diff --git ")?;
+    let index = rest.find(" b/")?;
+    Some(rest[index + 3..].to_string())
+}
+
+/// A diff shrunk to fit a token budget, with a record of what was left out.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Compaction {
+    pub diff: String,
+    /// Estimated tokens of the original diff.
+    pub original_tokens: usize,
+    /// Estimated tokens of `diff`.
+    pub tokens: usize,
+    /// Lockfiles, generated and vendored files left out entirely.
+    pub dropped: Vec<String>,
+    /// Files reduced to their hunk headers.
+    pub collapsed: Vec<String>,
+    /// Files reduced to added/removed line counts.
+    pub stats_only: Vec<String>,
+}
+
+impl Compaction {
+    /// Whether anything was elided.
+    pub fn is_partial(&self) -> bool {
+        !self.dropped.is_empty() || !self.collapsed.is_empty() || !self.stats_only.is_empty()
+    }
+
+    /// Whether the result still exceeds `budget`.
+    pub fn exceeds(&self, budget: usize) -> bool {
+        self.tokens > budget
+    }
+}
+
+/// Shrink `diff` until it fits `budget` tokens: first drop lockfiles,
+/// generated and vendored files, then collapse the largest files to their
+/// hunk headers, then reduce the largest to per-file stats. The result can
+/// still exceed the budget when even the stats are too long.
+pub fn compact_diff(diff: &str, budget: usize) -> Compaction {
+    let original_tokens = estimate_tokens(diff);
+    if original_tokens <= budget {
+        return Compaction {
+            diff: diff.to_string(),
+            original_tokens,
+            tokens: original_tokens,
+            ..Compaction::default()
+        };
+    }
+
+    let mut files = parse_diff(diff);
+    for file in files.iter_mut().filter(|file| is_noise(&file.path)) {
+        file.detail = Detail::Dropped;
+    }
+
+    let mut tokens: Vec<usize> = files.iter().map(FileDiff::tokens).collect();
+    let mut total: usize = tokens.iter().sum();
+    for detail in [Detail::HunkHeaders, Detail::Stats] {
+        while total > budget {
+            // Shrink the file that currently takes the most room.
+            let Some(index) = (0..files.len())
+                .filter(|&index| files[index].detail < detail)
+                .max_by_key(|&index| tokens[index])
+            else {
+                break;
+            };
+            files[index].detail = detail;
+            let shrunk = files[index].tokens();
+            total = total - tokens[index] + shrunk;
+            tokens[index] = shrunk;
+        }
+    }
+
+    let paths = |detail: Detail| -> Vec<String> {
+        files
+            .iter()
+            .filter(|file| file.detail == detail)
+            .map(|file| file.path.clone())
+            .collect()
+    };
+    let text: String = files.iter().map(FileDiff::render).collect();
+    Compaction {
+        tokens: estimate_tokens(&text),
+        diff: text,
+        original_tokens,
+        dropped: paths(Detail::Dropped),
+        collapsed: paths(Detail::HunkHeaders),
+        stats_only: paths(Detail::Stats),
+    }
+}
+
+/// Pack `items` in order into chunks of at most `budget` tokens, joined by
+/// newlines. An item larger than the budget gets a chunk of its own.
+pub fn split_by_budget(items: &[String], budget: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut current_tokens = 0;
+
+    for item in items {
+        let tokens = estimate_tokens(item) + 1;
+        if !current.is_empty() && current_tokens + tokens > budget {
+            chunks.push(std::mem::take(&mut current));
+            current_tokens = 0;
+        }
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(item.trim_end());
+        current_tokens += tokens;
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
+/// Split `diff` into parts of whole files that each fit `budget` tokens, for
+/// summarizing one part at a time. Lockfiles, generated and vendored files are
+/// left out, and a file too large on its own is compacted first.
+pub fn split_diff(diff: &str, budget: usize) -> Vec<String> {
+    let files: Vec<String> = parse_diff(diff)
+        .into_iter()
+        .filter(|file| !is_noise(&file.path))
+        .map(|file| {
+            let text = file.render();
+            if estimate_tokens(&text) > budget {
+                compact_diff(&text, budget).diff
+            } else {
+                text
+            }
+        })
+        .collect();
+
+    split_by_budget(&files, budget)
+}
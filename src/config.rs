@@ -14,8 +14,16 @@
 use anyhow::{Context, Result};
 use dirs::config_dir;
 use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
-use std::path::PathBuf;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+
+use crate::commitlint;
+use crate::prompts;
+use crate::secret::{KeyringStore, SecretRef};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LLMConfig {
@@ -26,29 +34,42 @@ pub struct LLMConfig {
 }
 
 impl LLMConfig {
+    /// 按提供商名称创建配置，缺少的值从 `GAM_*` 环境变量读取，仍然缺少时交互输入
     pub fn from_name(
         name: &str,
         base_url: Option<&str>,
         api_key: Option<&str>,
         model: Option<&str>,
-    ) -> Self {
-        let provider = match name {
-            "openai" => LLMProvider::OpenAI,
-            "deepseek" => LLMProvider::DeepSeek,
-            "kimi" => LLMProvider::Kimi,
-            "anthropic" => LLMProvider::Anthropic,
-            "ollama" => LLMProvider::Ollama,
-            _ => LLMProvider::OpenAI,
-        };
+    ) -> Result<Self> {
+        Self::build(name, base_url, api_key, model, input_info)
+    }
+
+    /// 与 [`LLMConfig::from_name`] 相同，但缺少的值直接报错，从不读取标准输入
+    pub fn from_values(
+        name: &str,
+        base_url: Option<&str>,
+        api_key: Option<&str>,
+        model: Option<&str>,
+    ) -> Result<Self> {
+        Self::build(name, base_url, api_key, model, missing_value)
+    }
+
+    fn build(
+        name: &str,
+        base_url: Option<&str>,
+        api_key: Option<&str>,
+        model: Option<&str>,
+        ask: fn(&str) -> Result<String>,
+    ) -> Result<Self> {
+        let known = LLMProvider::from_name(name);
+        let provider = known.unwrap_or(LLMProvider::OpenAI);
         let base_url = match base_url {
             Some(url) => url.to_string(),
             None => match get_env_var("GAM_BASE_URL") {
                 Some(_) => "ENV".to_string(),
-                None => match name {
-                    "openai" | "deepseek" | "kimi" | "anthropic" | "ollama" => {
-                        "default_baseurl".to_string()
-                    }
-                    _ => input_info("base url"),
+                None => match known {
+                    Some(provider) => provider.default_base_url().to_string(),
+                    None => ask("base url")?,
                 },
             },
         };
@@ -56,25 +77,59 @@ impl LLMConfig {
             Some(key) => key.to_string(),
             None => match get_env_var("GAM_API_KEY") {
                 Some(_) => "ENV".to_string(),
-                None => input_info("api key"),
+                None if provider == LLMProvider::Ollama => String::new(),
+                None => ask("api key")?,
             },
         };
         let model = match model {
             Some(model) => model.to_string(),
             None => match get_env_var("GAM_MODEL") {
                 Some(_) => "ENV".to_string(),
-                None => input_info("model"),
+                None => ask("model")?,
             },
         };
-        LLMConfig {
+        Ok(LLMConfig {
             provider,
             base_url,
             api_key,
             model,
+        })
+    }
+
+    /// 解析实际使用的 Base URL（处理 ENV 和默认地址）
+    pub fn resolved_base_url(&self) -> String {
+        match self.base_url.as_str() {
+            "ENV" => get_env_var("GAM_BASE_URL")
+                .unwrap_or_else(|| self.provider.default_base_url().to_string()),
+            "" | DEFAULT_BASE_URL => self.provider.default_base_url().to_string(),
+            url => url.to_string(),
+        }
+    }
+
+    /// 解析实际使用的 API Key（支持 `keyring:`、`env:`、`cmd:`、`file:` 引用）
+    pub fn resolved_api_key(&self) -> Result<String> {
+        self.resolved_api_key_with(&KeyringStore::new())
+    }
+
+    /// 使用指定的密钥存储解析 API Key
+    pub fn resolved_api_key_with(&self, store: &KeyringStore) -> Result<String> {
+        SecretRef::parse(&self.api_key)
+            .resolve(store)
+            .context("无法获取 API Key")
+    }
+
+    /// 解析实际使用的模型名称
+    pub fn resolved_model(&self) -> Result<String> {
+        match self.model.as_str() {
+            "ENV" => get_env_var("GAM_MODEL").context("环境变量 GAM_MODEL 未设置"),
+            model => Ok(model.to_string()),
         }
     }
 }
 
+/// 旧版本配置中表示“使用提供商默认地址”的占位值
+const DEFAULT_BASE_URL: &str = "default_baseurl";
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub enum LLMProvider {
     OpenAI,
@@ -85,7 +140,7 @@ pub enum LLMProvider {
 }
 
 impl LLMProvider {
-    fn list_providers() -> Vec<LLMProvider> {
+    pub fn list_providers() -> Vec<LLMProvider> {
         vec![
             LLMProvider::OpenAI,
             LLMProvider::DeepSeek,
@@ -95,14 +150,14 @@ impl LLMProvider {
         ]
     }
 
-    fn from_name(name: &str) -> Option<LLMProvider> {
+    pub fn from_name(name: &str) -> Option<LLMProvider> {
         Self::list_providers()
             .iter()
-            .find(|&&provider| provider.get_name() == name)
+            .find(|&&provider| provider.get_name().eq_ignore_ascii_case(name.trim()))
             .cloned()
     }
 
-    fn get_name(&self) -> &str {
+    pub fn get_name(&self) -> &str {
         match self {
             LLMProvider::OpenAI => "OpenAI",
             LLMProvider::DeepSeek => "DeepSeek",
@@ -111,40 +166,265 @@ impl LLMProvider {
             LLMProvider::Ollama => "Ollama",
         }
     }
+
+    /// 提供商的默认 API 地址
+    pub fn default_base_url(&self) -> &'static str {
+        match self {
+            LLMProvider::OpenAI => "https://api.openai.com/v1",
+            LLMProvider::DeepSeek => "https://api.deepseek.com",
+            LLMProvider::Kimi => "https://api.moonshot.cn/v1",
+            LLMProvider::Anthropic => "https://api.anthropic.com",
+            LLMProvider::Ollama => "http://localhost:11434",
+        }
+    }
+
+    /// 是否需要 API Key（本地运行的 Ollama 不需要）
+    pub fn requires_api_key(&self) -> bool {
+        !matches!(self, LLMProvider::Ollama)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub llm: LLMConfig,
+    #[serde(default = "default_language")]
     pub language: String,
+    #[serde(default)]
     pub prompt: Option<String>,
+    #[serde(default)]
     pub emoji: bool,
+    #[serde(default)]
     pub multi_line: bool,
+    /// 允许使用的提交类型（为空时不限制）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub types: Vec<String>,
+    /// 允许使用的提交范围（为空时不限制）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scopes: Vec<String>,
+    /// 从文件加载的提示词模板
+    #[serde(default, skip_serializing_if = "TemplateFiles::is_empty")]
+    pub templates: TemplateFiles,
+    /// 代码差异在提示词中可占用的 token 数，超出时会精简差异
+    #[serde(default, skip_serializing_if = "TokenBudget::is_empty")]
+    pub token_budget: TokenBudget,
+    /// 分段摘要时最多同时发送的请求数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parallelism: Option<usize>,
+    /// `lint` 子命令的检查规则
+    #[serde(default, skip_serializing_if = "LintConfig::is_empty")]
+    pub lint: LintConfig,
+    /// 版本号与发布相关的设置
+    #[serde(default, skip_serializing_if = "ReleaseConfig::is_empty")]
+    pub release: ReleaseConfig,
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        println!("请选择一个LLM提供商(如需自定义请问输入custom):");
-        for (i, provider) in LLMProvider::list_providers().iter().enumerate() {
-            println!("{}. {}", i + 1, provider.get_name());
+/// 未配置时代码差异的 token 上限
+pub const DEFAULT_TOKEN_BUDGET: usize = 16_000;
+
+/// 本地模型的上下文通常较小
+pub const DEFAULT_OLLAMA_TOKEN_BUDGET: usize = 4_000;
+
+/// 未配置时分段摘要的并发请求数
+pub const DEFAULT_PARALLELISM: usize = 4;
+
+/// 代码差异的 token 上限，可按模型单独设置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TokenBudget {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<usize>,
+    /// 按模型名称设置的上限，优先于 `default`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub models: BTreeMap<String, usize>,
+}
+
+impl TokenBudget {
+    pub fn is_empty(&self) -> bool {
+        self.default.is_none() && self.models.is_empty()
+    }
+}
+
+/// 检查提交消息的规则；允许的类型和范围沿用 `types` 和 `scopes`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LintConfig {
+    /// 标题的最大长度，默认 72
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_header_length: Option<usize>,
+    /// 正文每行的最大长度，默认 72
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_width: Option<usize>,
+    /// 必须包含的脚注，如 `Signed-off-by`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required_trailers: Vec<String>,
+    /// 是否允许 WIP、fixup! 之类的临时提交
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub allow_wip: bool,
+}
+
+impl LintConfig {
+    pub fn is_empty(&self) -> bool {
+        self.max_header_length.is_none()
+            && self.body_width.is_none()
+            && self.required_trailers.is_empty()
+            && !self.allow_wip
+    }
+}
+
+/// 版本号与发布相关的设置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReleaseConfig {
+    /// 版本标签的前缀，如 `v` 或 `pkg@`，默认 `v`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag_prefix: Option<String>,
+    /// 发布时更新的更新日志文件，默认 `CHANGELOG.md`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changelog: Option<PathBuf>,
+    /// 除 Cargo.toml、package.json 和 pyproject.toml 外需要更新版本号的文件
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<VersionFile>,
+}
+
+impl ReleaseConfig {
+    pub fn is_empty(&self) -> bool {
+        self.tag_prefix.is_none() && self.changelog.is_none() && self.files.is_empty()
+    }
+}
+
+/// 需要更新版本号的文件：`pattern` 是正则表达式，第一个捕获组为版本号
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionFile {
+    /// 相对于仓库根目录的路径
+    pub path: PathBuf,
+    pub pattern: String,
+}
+
+/// 各类消息的提示词模板文件路径（相对路径以当前仓库目录为准）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TemplateFiles {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changelog: Option<PathBuf>,
+}
+
+impl TemplateFiles {
+    pub fn is_empty(&self) -> bool {
+        self.commit.is_none() && self.tag.is_none() && self.changelog.is_none()
+    }
+
+    /// 获取指定类型的模板文件路径
+    pub fn path_for(&self, prompt_type: &str) -> Option<&Path> {
+        match prompt_type {
+            "commit" => self.commit.as_deref(),
+            "tag" => self.tag.as_deref(),
+            "changelog" => self.changelog.as_deref(),
+            _ => None,
         }
-        let provider_name = input_info("请输入提供商名称");
-        let api_key = input_info("API Key");
-        Self::with_provider(&provider_name, &api_key)
     }
 }
 
-impl Config {
-    /// 创建一个新的配置实例，指定LLM提供商
-    pub fn with_provider(provider: &str, api_key: &str) -> Self {
+fn default_language() -> String {
+    "zh-CN".to_string()
+}
+
+impl Default for Config {
+    fn default() -> Self {
         Self {
-            llm: LLMConfig::from_name(provider, None, Some(api_key), None),
-            language: "zh-CN".to_string(),
+            llm: LLMConfig {
+                provider: LLMProvider::OpenAI,
+                base_url: String::new(),
+                api_key: String::new(),
+                model: String::new(),
+            },
+            language: default_language(),
             prompt: None,
             emoji: false,
             multi_line: false,
+            types: Vec::new(),
+            scopes: Vec::new(),
+            templates: TemplateFiles::default(),
+            token_budget: TokenBudget::default(),
+            parallelism: None,
+            lint: LintConfig::default(),
+            release: ReleaseConfig::default(),
         }
     }
+}
+
+impl Config {
+    /// 分段摘要时的并发请求数
+    pub fn parallelism(&self) -> usize {
+        self.parallelism.unwrap_or(DEFAULT_PARALLELISM).max(1)
+    }
+
+    /// 版本标签的前缀
+    pub fn tag_prefix(&self) -> &str {
+        self.release
+            .tag_prefix
+            .as_deref()
+            .unwrap_or(crate::version::DEFAULT_TAG_PREFIX)
+    }
+
+    /// 当前模型的代码差异 token 上限
+    pub fn token_budget(&self) -> usize {
+        let model = self.llm.resolved_model().unwrap_or_default();
+        self.token_budget
+            .models
+            .get(&model)
+            .copied()
+            .or(self.token_budget.default)
+            .unwrap_or(match self.llm.provider {
+                LLMProvider::Ollama => DEFAULT_OLLAMA_TOKEN_BUDGET,
+                _ => DEFAULT_TOKEN_BUDGET,
+            })
+    }
+
+    /// 创建一个新的配置实例，指定LLM提供商
+    pub fn with_provider(provider: &str, api_key: &str) -> Result<Self> {
+        Ok(Self {
+            llm: LLMConfig::from_name(provider, None, Some(api_key), None)?,
+            ..Self::default()
+        })
+    }
+    /// 交互式创建配置；标准输入不是终端时报错
+    pub fn wizard() -> Result<Self> {
+        Self::create(None, None, None, None, true)
+    }
+    /// 用给定的值创建配置，缺少的值从 `GAM_*` 环境变量读取；
+    /// `interactive` 为 false 时仍然缺少的值直接报错
+    pub fn create(
+        provider: Option<&str>,
+        base_url: Option<&str>,
+        api_key: Option<&str>,
+        model: Option<&str>,
+        interactive: bool,
+    ) -> Result<Self> {
+        let provider_name = match provider
+            .map(str::to_string)
+            .or_else(|| get_env_var("GAM_PROVIDER"))
+        {
+            Some(provider) => provider,
+            None if interactive => {
+                ensure_terminal("LLM提供商")?;
+                println!("请选择一个LLM提供商(如需自定义请问输入custom):");
+                for (i, provider) in LLMProvider::list_providers().iter().enumerate() {
+                    println!("{}. {}", i + 1, provider.get_name());
+                }
+                input_info("请输入提供商名称")?
+            }
+            None => anyhow::bail!("缺少 LLM 提供商，请通过 --provider 或 GAM_PROVIDER 提供"),
+        };
+        let llm = if interactive {
+            LLMConfig::from_name(&provider_name, base_url, api_key, model)?
+        } else {
+            LLMConfig::from_values(&provider_name, base_url, api_key, model)?
+        };
+        Ok(Self {
+            llm,
+            ..Self::default()
+        })
+    }
     /// 获取配置文件路径（根据操作系统）
     pub fn get_config_path() -> Result<PathBuf> {
         let config_dir = config_dir()
@@ -157,37 +437,39 @@ impl Config {
     }
 
     /// 设置配置项
-    pub fn set_config(&mut self, key: &str, value: &str) {
-        match key {
-            "llm.provider" => self.llm.provider = LLMProvider::from_name(value).unwrap(),
-            "llm.base_url" => self.llm.base_url = value.to_string(),
-            "llm.api_key" => self.llm.api_key = value.to_string(),
-            "llm.model" => self.llm.model = value.to_string(),
-            "language" => self.language = value.to_string(),
-            "prompt" => self.prompt = Some(value.to_string()),
-            "emoji" => self.emoji = value.parse().unwrap(),
-            "multi_line" => self.multi_line = value.parse().unwrap(),
-            _ => println!("Unknown key: {}", key),
-        }
+    pub fn set_config(&mut self, key: &str, value: &str) -> Result<()> {
+        let value = ConfigKey::find(key)?.validate(value)?;
+        let mut values = serde_yaml::to_value(&*self).context("无法序列化配置")?;
+        set_value(&mut values, key, value);
+        *self = serde_yaml::from_value(values).with_context(|| format!("{} 的值无效", key))?;
+        Ok(())
     }
 
-    /// 从配置文件加载配置
+    /// 加载配置（默认值 < 全局配置 < 仓库配置 < 环境变量）
     pub fn load() -> Result<Self> {
-        let config_path = Self::get_config_path()?;
+        Self::load_with(&[])
+    }
 
-        if !config_path.exists() {
-            let default_config = Self::default();
-            default_config.save()?;
-            return Ok(default_config);
-        }
+    /// 加载配置，并应用命令行中的 `KEY=VALUE` 覆盖项
+    pub fn load_with(overrides: &[String]) -> Result<Self> {
+        Ok(Self::load_layered(overrides)?.config)
+    }
 
-        let content = fs::read_to_string(&config_path)
-            .with_context(|| format!("无法读取配置文件: {:?}", config_path))?;
+    /// 加载分层配置，同时记录每个配置项的来源
+    pub fn load_layered(overrides: &[String]) -> Result<LayeredConfig> {
+        let config_path = Self::get_config_path()?;
 
-        let config: Config = serde_yaml::from_str(&content)
-            .with_context(|| format!("无法解析YAML配置文件: {:?}", config_path))?;
+        // 首次使用时在终端中引导创建配置；非交互环境只使用环境变量和命令行参数
+        if !config_path.exists()
+            && std::io::stdin().is_terminal()
+            && get_env_var("GAM_PROVIDER").is_none()
+            && get_env_var("GAM_API_KEY").is_none()
+        {
+            println!("未找到配置文件 {:?}，开始初始化配置：", config_path);
+            Self::wizard()?.save()?;
+        }
 
-        Ok(config)
+        ConfigLoader::new()?.overrides(overrides)?.load()
     }
 
     /// 保存配置到文件
@@ -211,7 +493,7 @@ impl Config {
             return Ok(());
         }
 
-        let example_config = Self::default();
+        let example_config = Self::wizard()?;
         example_config.save()?;
 
         println!("已创建配置文件: {:?}", config_path);
@@ -223,40 +505,787 @@ impl Config {
 
     /// 验证配置是否完整
     pub fn validate(&self) -> Result<()> {
-        match self.llm.api_key.as_str() {
-            "ENV" => {
-                if get_env_var("GAM_API_KEY").is_none() {
-                    anyhow::bail!("API Key不能为空");
+        if self.llm.provider.requires_api_key() {
+            match self.llm.api_key.as_str() {
+                "ENV" if get_env_var("GAM_API_KEY").is_none() => {
+                    anyhow::bail!("API Key不能为空：配置为 ENV，但环境变量 GAM_API_KEY 未设置")
                 }
+                "" => anyhow::bail!(
+                    "API Key不能为空：请设置 GAM_API_KEY 环境变量或运行 git-automessage config init"
+                ),
+                key => SecretRef::parse(key)
+                    .check()
+                    .with_context(|| format!("API Key 引用无效: {}", key))?,
             }
-            "" => anyhow::bail!("API Key不能为空"),
-            _ => {}
         }
 
-        if self.llm.base_url.is_empty() {
+        if self.llm.resolved_base_url().is_empty() {
             anyhow::bail!("Base URL不能为空");
         }
 
-        if self.llm.model.is_empty() {
+        if self.llm.resolved_model().unwrap_or_default().is_empty() {
             anyhow::bail!("模型名称不能为空");
         }
 
+        if let Some(prompt) = &self.prompt {
+            prompts::check_template(prompt).context("prompt 字段中的模板无效")?;
+        }
+        for prompt_type in ["commit", "tag", "changelog"] {
+            if let Some(path) = self.templates.path_for(prompt_type) {
+                prompts::load_template_file(path)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// 仓库根目录下的配置文件名
+pub const REPO_CONFIG_FILE: &str = ".git-automessage.yaml";
+
+/// 仓库配置文件中不允许设置的项：仓库内容不可信，不能决定把代码发往何处
+const REPO_FORBIDDEN_KEYS: &[&str] = &["llm.api_key", "llm.base_url"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    Text,
+    Bool,
+    List,
+    Path,
+    /// 正整数
+    Number,
+}
+
+/// 可设置的配置项
+pub struct ConfigKey {
+    pub name: &'static str,
+    pub env: Option<&'static str>,
+    pub kind: ValueKind,
+}
+
+pub const CONFIG_KEYS: &[ConfigKey] = &[
+    ConfigKey {
+        name: "llm.provider",
+        env: Some("GAM_PROVIDER"),
+        kind: ValueKind::Text,
+    },
+    ConfigKey {
+        name: "llm.base_url",
+        env: Some("GAM_BASE_URL"),
+        kind: ValueKind::Text,
+    },
+    ConfigKey {
+        name: "llm.api_key",
+        env: Some("GAM_API_KEY"),
+        kind: ValueKind::Text,
+    },
+    ConfigKey {
+        name: "llm.model",
+        env: Some("GAM_MODEL"),
+        kind: ValueKind::Text,
+    },
+    ConfigKey {
+        name: "language",
+        env: Some("GAM_LANGUAGE"),
+        kind: ValueKind::Text,
+    },
+    ConfigKey {
+        name: "prompt",
+        env: Some("GAM_PROMPT"),
+        kind: ValueKind::Text,
+    },
+    ConfigKey {
+        name: "emoji",
+        env: Some("GAM_EMOJI"),
+        kind: ValueKind::Bool,
+    },
+    ConfigKey {
+        name: "multi_line",
+        env: Some("GAM_MULTI_LINE"),
+        kind: ValueKind::Bool,
+    },
+    ConfigKey {
+        name: "types",
+        env: Some("GAM_TYPES"),
+        kind: ValueKind::List,
+    },
+    ConfigKey {
+        name: "scopes",
+        env: Some("GAM_SCOPES"),
+        kind: ValueKind::List,
+    },
+    ConfigKey {
+        name: "templates.commit",
+        env: None,
+        kind: ValueKind::Path,
+    },
+    ConfigKey {
+        name: "templates.tag",
+        env: None,
+        kind: ValueKind::Path,
+    },
+    ConfigKey {
+        name: "templates.changelog",
+        env: None,
+        kind: ValueKind::Path,
+    },
+    ConfigKey {
+        name: "token_budget.default",
+        env: Some("GAM_TOKEN_BUDGET"),
+        kind: ValueKind::Number,
+    },
+    ConfigKey {
+        name: "parallelism",
+        env: Some("GAM_PARALLELISM"),
+        kind: ValueKind::Number,
+    },
+    ConfigKey {
+        name: "lint.max_header_length",
+        env: None,
+        kind: ValueKind::Number,
+    },
+    ConfigKey {
+        name: "lint.body_width",
+        env: None,
+        kind: ValueKind::Number,
+    },
+    ConfigKey {
+        name: "lint.required_trailers",
+        env: None,
+        kind: ValueKind::List,
+    },
+    ConfigKey {
+        name: "lint.allow_wip",
+        env: None,
+        kind: ValueKind::Bool,
+    },
+    ConfigKey {
+        name: "release.tag_prefix",
+        env: None,
+        kind: ValueKind::Text,
+    },
+    ConfigKey {
+        name: "release.changelog",
+        env: None,
+        kind: ValueKind::Path,
+    },
+];
+
+impl ConfigKey {
+    pub fn find(name: &str) -> Result<&'static ConfigKey> {
+        CONFIG_KEYS
+            .iter()
+            .find(|key| key.name == name)
+            .with_context(|| {
+                let names: Vec<&str> = CONFIG_KEYS.iter().map(|key| key.name).collect();
+                format!("未知的配置项: {}（可用配置项: {}）", name, names.join(", "))
+            })
+    }
+
+    /// 检查并转换要写入配置文件的值
+    pub fn validate(&self, raw: &str) -> Result<Value> {
+        match self.name {
+            "llm.provider" => {
+                let provider = LLMProvider::from_name(raw).with_context(|| {
+                    let names: Vec<String> = LLMProvider::list_providers()
+                        .iter()
+                        .map(|provider| provider.get_name().to_string())
+                        .collect();
+                    format!("未知的 LLM 提供商: {}（可用: {}）", raw, names.join(", "))
+                })?;
+                return Ok(Value::String(provider.get_name().to_string()));
+            }
+            "language" if raw.trim().is_empty() => anyhow::bail!("language 不能为空"),
+            "prompt" => prompts::check_template(raw).context("prompt 中的模板无效")?,
+            _ => {}
+        }
+        self.parse_value(raw)
+    }
+
+    /// 把字符串形式的值转换为配置值
+    pub fn parse_value(&self, raw: &str) -> Result<Value> {
+        match self.kind {
+            ValueKind::Text | ValueKind::Path => Ok(Value::String(raw.to_string())),
+            ValueKind::Bool => match raw.trim().to_lowercase().as_str() {
+                "true" | "yes" | "on" | "1" => Ok(Value::Bool(true)),
+                "false" | "no" | "off" | "0" => Ok(Value::Bool(false)),
+                _ => anyhow::bail!("{} 需要布尔值（true/false），而不是 \"{}\"", self.name, raw),
+            },
+            ValueKind::Number => match raw.trim().parse::<u64>() {
+                Ok(number) if number > 0 => Ok(Value::Number(number.into())),
+                _ => anyhow::bail!("{} 需要正整数，而不是 \"{}\"", self.name, raw),
+            },
+            ValueKind::List => Ok(Value::Sequence(
+                raw.split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(|item| Value::String(item.to_string()))
+                    .collect(),
+            )),
+        }
+    }
+}
+
+/// 配置项的来源
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    Default,
+    Global(PathBuf),
+    Commitlint(PathBuf),
+    Repo(PathBuf),
+    Env(String),
+    Cli,
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::Default => write!(f, "default"),
+            ConfigSource::Global(path) => write!(f, "global:{}", path.display()),
+            ConfigSource::Commitlint(path) => write!(f, "commitlint:{}", path.display()),
+            ConfigSource::Repo(path) => write!(f, "repo:{}", path.display()),
+            ConfigSource::Env(name) => write!(f, "env:{}", name),
+            ConfigSource::Cli => write!(f, "command line"),
+        }
+    }
+}
+
+/// 合并后的配置及各项来源
+#[derive(Debug)]
+pub struct LayeredConfig {
+    pub config: Config,
+    values: Value,
+    origins: BTreeMap<String, ConfigSource>,
+}
+
+impl LayeredConfig {
+    /// 某个配置项的最终来源
+    pub fn origin(&self, key: &str) -> Option<&ConfigSource> {
+        self.origins.get(key)
+    }
+
+    /// 所有生效的配置项：(名称, 显示值, 来源)，API Key 会被遮盖
+    pub fn entries(&self) -> Vec<(String, String, ConfigSource)> {
+        let mut leaves = Vec::new();
+        flatten(&self.values, "", &mut leaves);
+        leaves
+            .into_iter()
+            .filter_map(|(key, value)| {
+                let origin = self.origins.get(&key)?.clone();
+                let value = if key == "llm.api_key" {
+                    mask_secret(&value)
+                } else {
+                    value
+                };
+                Some((key, value, origin))
+            })
+            .collect()
+    }
+}
+
+/// 按优先级合并各层配置
+pub struct ConfigLoader {
+    global: Option<PathBuf>,
+    commitlint: Option<PathBuf>,
+    repo: Option<PathBuf>,
+    env: Vec<(String, String)>,
+    overrides: Vec<(String, String)>,
+}
+
+impl ConfigLoader {
+    /// 使用默认位置：全局配置文件、当前仓库的配置文件和进程环境变量
+    pub fn new() -> Result<Self> {
+        let workdir = git2::Repository::discover(".")
+            .ok()
+            .and_then(|repo| repo.workdir().map(Path::to_path_buf));
+
+        Ok(Self {
+            global: Some(Config::get_config_path()?),
+            commitlint: workdir.as_deref().and_then(commitlint::find),
+            repo: workdir.map(|dir| dir.join(REPO_CONFIG_FILE)),
+            env: std::env::vars().collect(),
+            overrides: Vec::new(),
+        })
+    }
+
+    /// 不读取任何文件和环境变量，只有内置默认值
+    pub fn empty() -> Self {
+        Self {
+            global: None,
+            commitlint: None,
+            repo: None,
+            env: Vec::new(),
+            overrides: Vec::new(),
+        }
+    }
+
+    pub fn global_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.global = Some(path.into());
+        self
+    }
+
+    /// 仓库中的 commitlint 配置文件，优先级在全局配置和仓库配置之间
+    pub fn commitlint_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.commitlint = Some(path.into());
+        self
+    }
+
+    pub fn repo_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.repo = Some(path.into());
+        self
+    }
+
+    pub fn env_vars(mut self, vars: impl IntoIterator<Item = (String, String)>) -> Self {
+        self.env = vars.into_iter().collect();
+        self
+    }
+
+    /// 命令行中的 `KEY=VALUE` 覆盖项
+    pub fn overrides(mut self, overrides: &[String]) -> Result<Self> {
+        for item in overrides {
+            let (key, value) = item
+                .split_once('=')
+                .with_context(|| format!("无效的配置覆盖项 \"{}\"，应为 KEY=VALUE", item))?;
+            ConfigKey::find(key.trim())?;
+            self.overrides
+                .push((key.trim().to_string(), value.to_string()));
+        }
+        Ok(self)
+    }
+
+    pub fn load(&self) -> Result<LayeredConfig> {
+        let mut values = Value::Mapping(Mapping::new());
+        let mut origins = BTreeMap::new();
+
+        merge(
+            &mut values,
+            default_values(),
+            "",
+            &ConfigSource::Default,
+            &mut origins,
+        );
+
+        if let Some(path) = self.global.as_ref().filter(|path| path.exists()) {
+            let layer = read_layer(path)?;
+            merge(
+                &mut values,
+                layer,
+                "",
+                &ConfigSource::Global(path.clone()),
+                &mut origins,
+            );
+        }
+
+        if let Some(path) = self.commitlint.as_ref().filter(|path| path.exists()) {
+            // 无法解析的 commitlint 配置不应妨碍生成消息
+            match commitlint::load(path) {
+                Ok(rules) => merge(
+                    &mut values,
+                    rules.to_layer(),
+                    "",
+                    &ConfigSource::Commitlint(path.clone()),
+                    &mut origins,
+                ),
+                Err(e) => eprintln!("警告: 已忽略 commitlint 配置: {:#}", e),
+            }
+        }
+
+        if let Some(path) = self.repo.as_ref().filter(|path| path.exists()) {
+            let layer = repo_layer(path)?;
+            merge(
+                &mut values,
+                layer,
+                "",
+                &ConfigSource::Repo(path.clone()),
+                &mut origins,
+            );
+        }
+
+        for key in CONFIG_KEYS {
+            let Some(env) = key.env else { continue };
+            if let Some((_, raw)) = self.env.iter().find(|(name, _)| name == env) {
+                let value = key
+                    .validate(raw)
+                    .with_context(|| format!("环境变量 {} 无效", env))?;
+                set_value(&mut values, key.name, value);
+                origins.insert(key.name.to_string(), ConfigSource::Env(env.to_string()));
+            }
+        }
+
+        for (name, raw) in &self.overrides {
+            let value = ConfigKey::find(name)?.validate(raw)?;
+            set_value(&mut values, name, value);
+            origins.insert(name.clone(), ConfigSource::Cli);
+        }
+
+        let config: Config = serde_yaml::from_value(values.clone()).context("合并后的配置无效")?;
+
+        Ok(LayeredConfig {
+            config,
+            values,
+            origins,
+        })
+    }
+}
+
+/// `config get/set/unset` 操作的配置文件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigScope {
+    /// 用户的全局配置文件
+    Global,
+    /// 当前仓库根目录下的 `.git-automessage.yaml`
+    Local,
+}
+
+impl ConfigScope {
+    pub fn path(&self) -> Result<PathBuf> {
+        match self {
+            ConfigScope::Global => Config::get_config_path(),
+            ConfigScope::Local => {
+                let repo = git2::Repository::discover(".").context("当前目录不在 git 仓库中")?;
+                let workdir = repo.workdir().context("裸仓库没有仓库配置文件")?;
+                Ok(workdir.join(REPO_CONFIG_FILE))
+            }
+        }
+    }
+}
+
+/// 单个配置文件的内容，只包含文件中实际写出的配置项
+pub struct ConfigFile {
+    path: PathBuf,
+    scope: ConfigScope,
+    values: Value,
+}
+
+impl ConfigFile {
+    pub fn open(scope: ConfigScope) -> Result<Self> {
+        Self::open_path(scope, scope.path()?)
+    }
+
+    pub fn open_path(scope: ConfigScope, path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let values = if path.exists() {
+            read_layer(&path)?
+        } else {
+            Value::Mapping(Mapping::new())
+        };
+        Ok(Self {
+            path,
+            scope,
+            values,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 读取配置项的显示值
+    pub fn get(&self, key: &str) -> Result<Option<String>> {
+        ConfigKey::find(key)?;
+        Ok(self
+            .entries()
+            .into_iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value))
+    }
+
+    pub fn set(&mut self, key: &str, raw: &str) -> Result<()> {
+        let value = self.writable_key(key)?.validate(raw)?;
+        set_value(&mut self.values, key, value);
+        Ok(())
+    }
+
+    fn writable_key(&self, key: &str) -> Result<&'static ConfigKey> {
+        let config_key = ConfigKey::find(key)?;
+        if self.scope == ConfigScope::Local && REPO_FORBIDDEN_KEYS.contains(&key) {
+            anyhow::bail!("{} 不能写入仓库配置文件，请使用 --global", key);
+        }
+        Ok(config_key)
+    }
+
+    /// 密钥在系统密钥环中的名称，按提供商区分
+    pub fn secret_name(&self) -> String {
+        self.values
+            .get("llm")
+            .and_then(|llm| llm.get("provider"))
+            .and_then(Value::as_str)
+            .map(str::to_lowercase)
+            .unwrap_or_else(|| "default".to_string())
+    }
+
+    /// 把密钥写入系统密钥环，配置文件中只保存 `keyring:` 引用；
+    /// 已经是引用的值直接保存
+    pub fn set_secret(&mut self, key: &str, secret: &str, store: &KeyringStore) -> Result<String> {
+        self.writable_key(key)?;
+        let reference = SecretRef::parse(secret);
+        let value = if reference.is_reference() {
+            reference
+                .check()
+                .with_context(|| format!("密钥引用无效: {}", secret))?;
+            secret.to_string()
+        } else {
+            let name = self.secret_name();
+            store.set(&name, secret)?;
+            format!("keyring:{}", name)
+        };
+        self.set(key, &value)?;
+        Ok(value)
+    }
+
+    /// 删除配置项；若是 `keyring:` 引用，同时删除密钥环中的条目
+    pub fn unset_secret(&mut self, key: &str, store: &KeyringStore) -> Result<bool> {
+        if let Some(Value::String(current)) = self.raw_value(key)
+            && let SecretRef::Keyring(name) = SecretRef::parse(current)
+        {
+            store.delete(&name)?;
+        }
+        self.unset(key)
+    }
+
+    fn raw_value(&self, key: &str) -> Option<&Value> {
+        key.split('.')
+            .try_fold(&self.values, |value, part| value.get(part))
+    }
+
+    /// 删除配置项，返回该项之前是否存在
+    pub fn unset(&mut self, key: &str) -> Result<bool> {
+        ConfigKey::find(key)?;
+        Ok(remove_value(&mut self.values, key).is_some())
+    }
+
+    /// 文件中所有配置项：(名称, 显示值)，API Key 会被遮盖
+    pub fn entries(&self) -> Vec<(String, String)> {
+        let mut leaves = Vec::new();
+        flatten(&self.values, "", &mut leaves);
+        leaves
+            .into_iter()
+            .map(|(key, value)| {
+                let value = if key == "llm.api_key" {
+                    mask_secret(&value)
+                } else {
+                    value
+                };
+                (key, value)
+            })
+            .collect()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = serde_yaml::to_string(&self.values).context("无法序列化配置到YAML")?;
+        fs::write(&self.path, content)
+            .with_context(|| format!("无法写入配置文件: {:?}", self.path))?;
         Ok(())
     }
 }
 
-fn input_info(info: &str) -> String {
+/// 内置默认值
+fn default_values() -> Value {
+    let mut llm = Mapping::new();
+    llm.insert("provider".into(), LLMProvider::OpenAI.get_name().into());
+    llm.insert("base_url".into(), "".into());
+    llm.insert("api_key".into(), "".into());
+    llm.insert("model".into(), "".into());
+
+    let mut values = Mapping::new();
+    values.insert("llm".into(), Value::Mapping(llm));
+    values.insert("language".into(), default_language().into());
+    values.insert("emoji".into(), false.into());
+    values.insert("multi_line".into(), false.into());
+    Value::Mapping(values)
+}
+
+fn read_layer(path: &Path) -> Result<Value> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("无法读取配置文件: {:?}", path))?;
+    let value: Value = serde_yaml::from_str(&content)
+        .with_context(|| format!("无法解析YAML配置文件: {:?}", path))?;
+
+    match value {
+        Value::Null => Ok(Value::Mapping(Mapping::new())),
+        Value::Mapping(_) => Ok(value),
+        _ => anyhow::bail!("配置文件的顶层必须是映射: {:?}", path),
+    }
+}
+
+/// 读取仓库配置文件：忽略不允许的配置项，模板路径相对于仓库根目录
+fn repo_layer(path: &Path) -> Result<Value> {
+    let mut layer = read_layer(path)?;
+
+    for key in REPO_FORBIDDEN_KEYS {
+        if remove_value(&mut layer, key).is_some() {
+            eprintln!("警告: 仓库配置文件 {:?} 不能设置 {}，已忽略", path, key);
+        }
+    }
+
+    let root = path.parent().unwrap_or(Path::new("."));
+    if let Some(Value::Mapping(templates)) = layer.get_mut("templates") {
+        for (_, template) in templates.iter_mut() {
+            if let Value::String(file) = template
+                && Path::new(file).is_relative()
+            {
+                *file = root.join(&*file).to_string_lossy().into_owned();
+            }
+        }
+    }
+
+    Ok(layer)
+}
+
+/// 把一层配置合并到结果中，记录被设置的叶子配置项的来源
+fn merge(
+    base: &mut Value,
+    layer: Value,
+    prefix: &str,
+    source: &ConfigSource,
+    origins: &mut BTreeMap<String, ConfigSource>,
+) {
+    let Value::Mapping(layer) = layer else { return };
+    if !base.is_mapping() {
+        *base = Value::Mapping(Mapping::new());
+    }
+    let Value::Mapping(base) = base else { return };
+
+    for (key, value) in layer {
+        let Some(name) = key.as_str() else { continue };
+        let path = join_key(prefix, name);
+
+        if value.is_mapping() {
+            let entry = base
+                .entry(key.clone())
+                .or_insert_with(|| Value::Mapping(Mapping::new()));
+            merge(entry, value, &path, source, origins);
+        } else {
+            // 子项的来源以最后一次设置为准
+            origins.retain(|existing, _| !existing.starts_with(&format!("{}.", path)));
+            origins.insert(path, source.clone());
+            base.insert(key, value);
+        }
+    }
+}
+
+fn join_key(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", prefix, name)
+    }
+}
+
+/// 按点分隔的路径设置值
+fn set_value(values: &mut Value, key: &str, value: Value) {
+    let mut current = values;
+    let mut parts = key.split('.').peekable();
+    while let Some(part) = parts.next() {
+        if !current.is_mapping() {
+            *current = Value::Mapping(Mapping::new());
+        }
+        let Value::Mapping(map) = current else { return };
+        if parts.peek().is_none() {
+            map.insert(part.into(), value);
+            return;
+        }
+        current = map
+            .entry(part.into())
+            .or_insert_with(|| Value::Mapping(Mapping::new()));
+    }
+}
+
+/// 按点分隔的路径删除值
+fn remove_value(values: &mut Value, key: &str) -> Option<Value> {
+    match key.split_once('.') {
+        Some((head, rest)) => remove_value(values.get_mut(head)?, rest),
+        None => values.as_mapping_mut()?.remove(key),
+    }
+}
+
+/// 把配置展开成 (点分隔名称, 显示值)
+fn flatten(value: &Value, prefix: &str, leaves: &mut Vec<(String, String)>) {
+    match value {
+        Value::Mapping(map) => {
+            for (key, value) in map {
+                if let Some(name) = key.as_str() {
+                    flatten(value, &join_key(prefix, name), leaves);
+                }
+            }
+        }
+        Value::Null => {}
+        Value::String(text) => leaves.push((prefix.to_string(), text.clone())),
+        Value::Sequence(items) => {
+            let items: Vec<String> = items
+                .iter()
+                .map(|item| match item {
+                    Value::String(text) => text.clone(),
+                    other => serde_yaml::to_string(other)
+                        .unwrap_or_default()
+                        .trim()
+                        .to_string(),
+                })
+                .collect();
+            leaves.push((prefix.to_string(), items.join(", ")));
+        }
+        other => leaves.push((
+            prefix.to_string(),
+            serde_yaml::to_string(other)
+                .unwrap_or_default()
+                .trim()
+                .to_string(),
+        )),
+    }
+}
+
+/// 遮盖密钥，只保留最后四个字符
+pub fn mask_secret(secret: &str) -> String {
+    // 密钥引用本身不是机密，原样显示
+    if SecretRef::parse(secret).is_reference() {
+        return secret.to_string();
+    }
+    let count = secret.chars().count();
+    match secret {
+        "" => secret.to_string(),
+        _ if count <= 8 => "*".repeat(count),
+        _ => {
+            let tail: String = secret.chars().skip(count - 4).collect();
+            format!("{}{}", "*".repeat(count - 4), tail)
+        }
+    }
+}
+
+fn input_info(info: &str) -> Result<String> {
+    ensure_terminal(info)?;
     let mut txt = String::new();
     println!("Please input {}:", info);
     std::io::stdin()
         .read_line(&mut txt)
-        .expect("failed to read line");
-    txt.trim().to_string()
+        .context("无法读取输入")?;
+    Ok(txt.trim().to_string())
 }
 
-fn get_env_var(var_name: &str) -> Option<String> {
-    match std::env::var(var_name) {
-        Ok(value) => Some(value),
-        Err(_) => None,
+/// 标准输入不是终端时无法交互，直接报错而不是阻塞
+fn ensure_terminal(info: &str) -> Result<()> {
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!(
+            "需要输入 {}，但标准输入不是终端。请设置 GAM_PROVIDER、GAM_API_KEY、GAM_MODEL（可选 GAM_BASE_URL）环境变量，\
+             或运行 git-automessage config init --non-interactive --provider <名称> --model <模型>",
+            info
+        );
     }
+    Ok(())
+}
+
+fn missing_value(info: &str) -> Result<String> {
+    let hint = match info {
+        "api key" => "--api-key 或 GAM_API_KEY",
+        "model" => "--model 或 GAM_MODEL",
+        "base url" => "--base-url 或 GAM_BASE_URL",
+        _ => "命令行参数",
+    };
+    anyhow::bail!("缺少 {}，请通过 {} 提供", info, hint)
+}
+
+fn get_env_var(var_name: &str) -> Option<String> {
+    std::env::var(var_name).ok()
 }
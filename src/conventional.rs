@@ -0,0 +1,395 @@
+//     _         _        __  __
+//    / \  _   _| |_ ___ |  \/  | ___  ___ ___  __ _  __ _  ___
+//   / _ \| | | | __/ _ \| |\/| |/ _ \/ __/ __|/ _` |/ _` |/ _ \
+//  / ___ \ |_| | || (_) | |  | |  __/\__ \__ \ (_| | (_| |  __/
+// /_/   \_\__,_|\__\___/|_|  |_|\___||___/___/\__,_|\__, |\___|
+//                                                   |___/
+//
+// Author: Sidney Zhang <zly@lyzhang.me>
+// Date: 2025-08-05
+// License: MIT
+//
+// Conventional Commits parsing (https://www.conventionalcommits.org/).
+
+use std::fmt;
+use std::ops::Range;
+
+/// A commit message following `type(scope)!: description`, an optional
+/// body and optional `Token: value` footers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConventionalCommit {
+    /// Gitmoji or other emoji in front of the type, e.g. `✨` or `:sparkles:`.
+    pub emoji: Option<String>,
+    pub kind: String,
+    pub scope: Option<String>,
+    /// `!` after the type or scope.
+    pub exclamation: bool,
+    pub description: String,
+    pub body: Option<String>,
+    pub footers: Vec<Footer>,
+}
+
+/// A trailer such as `Refs: #123`, `Closes #7` or `BREAKING CHANGE: ...`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Footer {
+    pub token: String,
+    /// `": "` or `" #"`.
+    pub separator: String,
+    pub value: String,
+}
+
+impl Footer {
+    /// Parse a single footer line.
+    pub fn parse(line: &str) -> Option<Self> {
+        parse_footer_line(line)
+    }
+
+    pub fn is_breaking(&self) -> bool {
+        self.token == "BREAKING CHANGE" || self.token == "BREAKING-CHANGE"
+    }
+}
+
+/// Why a message is not a conventional commit, and where.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    /// Byte range in the parsed message.
+    pub span: Range<usize>,
+    /// 1-based line of the span start.
+    pub line: usize,
+    /// 1-based character column of the span start.
+    pub column: usize,
+}
+
+impl ParseError {
+    fn new(source: &str, span: Range<usize>, message: impl Into<String>) -> Self {
+        let before = &source[..span.start];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let column = source[line_start..span.start].chars().count() + 1;
+        ParseError {
+            message: message.into(),
+            span,
+            line,
+            column,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl ConventionalCommit {
+    /// Parse a full commit message. Trailing whitespace is ignored; the
+    /// message should already be free of `#` comment lines.
+    pub fn parse(message: &str) -> Result<Self, ParseError> {
+        let message = message.trim_end();
+        let header_end = message.find('\n').unwrap_or(message.len());
+        let header = &message[..header_end];
+
+        let mut commit = parse_header(message, header)?;
+
+        if header_end < message.len() {
+            let rest_start = header_end + 1;
+            let second_line = message[rest_start..].lines().next().unwrap_or("");
+            if !second_line.trim().is_empty() {
+                return Err(ParseError::new(
+                    message,
+                    rest_start..rest_start + second_line.len(),
+                    "the body must be separated from the header by a blank line",
+                ));
+            }
+            let (body, footers) = split_body_and_footers(&message[rest_start..], false);
+            commit.body = body;
+            commit.footers = footers;
+        }
+
+        Ok(commit)
+    }
+
+    /// Parse the header strictly but take whatever follows it as body and
+    /// footers, also without the blank line in between, and find BREAKING
+    /// CHANGE footers that do not start a paragraph. For classifying
+    /// existing commits, where only the type and breaking changes matter.
+    pub fn parse_lenient(message: &str) -> Result<Self, ParseError> {
+        let message = message.trim();
+        let header_end = message.find('\n').unwrap_or(message.len());
+        let mut commit = parse_header(message, message[..header_end].trim_end())?;
+        if header_end < message.len() {
+            let (body, footers) = split_body_and_footers(&message[header_end + 1..], true);
+            commit.body = body;
+            commit.footers = footers;
+        }
+        Ok(commit)
+    }
+
+    /// Header `type(scope)!: description`, with the emoji if any.
+    pub fn header(&self) -> String {
+        let mut header = String::new();
+        if let Some(emoji) = &self.emoji {
+            header.push_str(emoji);
+            header.push(' ');
+        }
+        header.push_str(&self.kind);
+        if let Some(scope) = &self.scope {
+            header.push_str(&format!("({})", scope));
+        }
+        if self.exclamation {
+            header.push('!');
+        }
+        header.push_str(": ");
+        header.push_str(&self.description);
+        header
+    }
+
+    /// Whether the commit is marked breaking by `!` or a BREAKING CHANGE footer.
+    pub fn is_breaking(&self) -> bool {
+        self.exclamation || self.footers.iter().any(Footer::is_breaking)
+    }
+
+    /// What breaks: the BREAKING CHANGE footer, or the description for `!`.
+    pub fn breaking_description(&self) -> Option<&str> {
+        self.footers
+            .iter()
+            .find(|footer| footer.is_breaking())
+            .map(|footer| footer.value.as_str())
+            .or_else(|| self.exclamation.then_some(self.description.as_str()))
+    }
+
+    /// Value of the first footer with `token`, compared case-insensitively.
+    pub fn footer(&self, token: &str) -> Option<&str> {
+        self.footers
+            .iter()
+            .find(|footer| footer.token.eq_ignore_ascii_case(token))
+            .map(|footer| footer.value.as_str())
+    }
+
+    /// Whether the type is `kind`, ignoring case.
+    pub fn is(&self, kind: &str) -> bool {
+        self.kind.eq_ignore_ascii_case(kind)
+    }
+}
+
+impl fmt::Display for ConventionalCommit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.header())?;
+        if let Some(body) = &self.body {
+            write!(f, "\n\n{}", body)?;
+        }
+        if !self.footers.is_empty() {
+            writeln!(f)?;
+            for footer in &self.footers {
+                write!(f, "\n{}{}{}", footer.token, footer.separator, footer.value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse `message` and report whether it is a conventional commit.
+pub fn validate(message: &str) -> Result<(), ParseError> {
+    ConventionalCommit::parse(message).map(|_| ())
+}
+
+/// Whether `line` is a footer such as `Refs: #123` or `Closes #7`.
+pub fn is_footer(line: &str) -> bool {
+    parse_footer_line(line).is_some()
+}
+
+fn parse_header(source: &str, header: &str) -> Result<ConventionalCommit, ParseError> {
+    if header.trim().is_empty() {
+        return Err(ParseError::new(source, 0..0, "the header is empty"));
+    }
+
+    let (emoji, start) = split_emoji(header);
+    let bytes = header.as_bytes();
+    let mut pos = start;
+
+    // type
+    while pos < bytes.len()
+        && (bytes[pos].is_ascii_alphanumeric() || bytes[pos] == b'-' || bytes[pos] == b'_')
+    {
+        pos += 1;
+    }
+    if pos == start || !bytes[start].is_ascii_alphabetic() {
+        let mut end = header[start..]
+            .find([':', '(', ' '])
+            .map_or(header.len(), |i| start + i);
+        if end == start {
+            end += header[start..].chars().next().map_or(0, char::len_utf8);
+        }
+        return Err(ParseError::new(
+            source,
+            start..end,
+            "expected a type such as 'feat' or 'fix'",
+        ));
+    }
+    let kind = header[start..pos].to_string();
+
+    // (scope)
+    let mut scope = None;
+    if bytes.get(pos) == Some(&b'(') {
+        let open = pos;
+        let Some(close) = header[open..].find(')').map(|i| open + i) else {
+            return Err(ParseError::new(
+                source,
+                open..header.len(),
+                "the scope is missing its closing ')'",
+            ));
+        };
+        let text = &header[open + 1..close];
+        if text.trim().is_empty() {
+            return Err(ParseError::new(
+                source,
+                open..close + 1,
+                "the scope is empty",
+            ));
+        }
+        if text.contains('(') {
+            return Err(ParseError::new(
+                source,
+                open..close + 1,
+                "the scope may not contain '('",
+            ));
+        }
+        scope = Some(text.to_string());
+        pos = close + 1;
+    }
+
+    // !
+    let exclamation = bytes.get(pos) == Some(&b'!');
+    if exclamation {
+        pos += 1;
+    }
+
+    // ": "
+    if bytes.get(pos) != Some(&b':') {
+        let found_colon = header[pos..].find(':');
+        let message = match found_colon {
+            Some(_) if header[pos..].starts_with(char::is_whitespace) => {
+                "unexpected whitespace before ':'"
+            }
+            Some(_) => "unexpected text before ':'",
+            None => "expected ':' after the type",
+        };
+        let end = found_colon
+            .map_or(header.len(), |i| pos + i)
+            .max((pos + 1).min(header.len()));
+        return Err(ParseError::new(source, pos..end, message));
+    }
+    pos += 1;
+    if bytes.get(pos) != Some(&b' ') {
+        return Err(ParseError::new(
+            source,
+            pos - 1..pos,
+            "expected a space after ':'",
+        ));
+    }
+    let description = header[pos..].trim();
+    if description.is_empty() {
+        return Err(ParseError::new(
+            source,
+            pos..header.len(),
+            "the description is empty",
+        ));
+    }
+
+    Ok(ConventionalCommit {
+        emoji,
+        kind,
+        scope,
+        exclamation,
+        description: description.to_string(),
+        body: None,
+        footers: Vec::new(),
+    })
+}
+
+/// A leading `:shortcode:` or non-ASCII emoji followed by whitespace, and the
+/// byte offset where the type starts.
+pub fn split_emoji(header: &str) -> (Option<String>, usize) {
+    let candidate = if let Some(rest) = header.strip_prefix(':') {
+        rest.find(':').map(|end| end + 2)
+    } else if header.starts_with(|c: char| !c.is_ascii()) {
+        header.find(char::is_whitespace)
+    } else {
+        None
+    };
+
+    match candidate {
+        Some(end) if header[end..].starts_with(char::is_whitespace) => {
+            let start = end + (header[end..].len() - header[end..].trim_start().len());
+            (Some(header[..end].to_string()), start)
+        }
+        _ => (None, 0),
+    }
+}
+
+/// Split everything after the header into the body and the footers. The
+/// footers start at the first paragraph that opens with a footer line, or
+/// when `lenient`, at a BREAKING CHANGE footer anywhere.
+fn split_body_and_footers(rest: &str, lenient: bool) -> (Option<String>, Vec<Footer>) {
+    let lines: Vec<&str> = rest.lines().collect();
+    let footer_start = (0..lines.len()).find(|&i| match parse_footer_line(lines[i]) {
+        Some(footer) => {
+            i == 0 || lines[i - 1].trim().is_empty() || (lenient && footer.is_breaking())
+        }
+        None => false,
+    });
+
+    let body_lines = &lines[..footer_start.unwrap_or(lines.len())];
+    let body = body_lines.join("\n").trim().to_string();
+    let body = (!body.is_empty()).then_some(body);
+
+    let mut footers: Vec<Footer> = Vec::new();
+    if let Some(start) = footer_start {
+        for line in &lines[start..] {
+            match parse_footer_line(line) {
+                Some(footer) => footers.push(footer),
+                None => {
+                    if let Some(last) = footers.last_mut() {
+                        last.value.push('\n');
+                        last.value.push_str(line);
+                    }
+                }
+            }
+        }
+        for footer in &mut footers {
+            footer.value = footer.value.trim_end().to_string();
+        }
+    }
+
+    (body, footers)
+}
+
+fn parse_footer_line(line: &str) -> Option<Footer> {
+    for token in ["BREAKING CHANGE", "BREAKING-CHANGE"] {
+        if let Some(value) = line.strip_prefix(token).and_then(|r| r.strip_prefix(": ")) {
+            return Some(Footer {
+                token: token.to_string(),
+                separator: ": ".to_string(),
+                value: value.to_string(),
+            });
+        }
+    }
+
+    let token_end = line
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+        .unwrap_or(line.len());
+    if token_end == 0 || !line.as_bytes()[0].is_ascii_alphabetic() {
+        return None;
+    }
+    let separator = [": ", " #"]
+        .into_iter()
+        .find(|sep| line[token_end..].starts_with(sep))?;
+    Some(Footer {
+        token: line[..token_end].to_string(),
+        separator: separator.to_string(),
+        value: line[token_end + separator.len()..].to_string(),
+    })
+}
@@ -12,8 +12,25 @@
 // A git repository wrapper.
 
 use anyhow::{Context, Result};
-use git2::{Diff, DiffOptions, Repository, Signature};
-use std::path::Path;
+use git2::{Diff, DiffOptions, ErrorCode, ObjectType, Oid, Repository, Signature, Time, Tree};
+use std::path::{Path, PathBuf};
+
+use crate::sign::Signer;
+
+/// Which set of changes to describe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiffMode {
+    /// Changes recorded in the index (HEAD -> index), i.e. what `git commit` records.
+    #[default]
+    Staged,
+    /// Changes in the working tree not yet staged, including untracked files.
+    Unstaged,
+    /// Both staged and unstaged changes (HEAD -> working tree).
+    All,
+    /// HEAD's own changes together with the staged ones (HEAD^ -> index),
+    /// i.e. what `git commit --amend` records.
+    Amend,
+}
 
 #[derive(Debug)]
 pub struct StagedFile {
@@ -30,6 +47,20 @@ pub struct CommitInfo {
     pub files_changed: Vec<String>,
 }
 
+/// Result of rewording commits.
+#[derive(Debug)]
+pub struct Reworded {
+    /// The rewritten commits, in the order they were given.
+    pub commits: Vec<Oid>,
+    /// How many other commits were rebased on top of them.
+    pub rebased: usize,
+    /// Refs that still point at the original commits and their messages.
+    pub backups: Vec<String>,
+}
+
+/// Namespace of the refs that keep reworded commits.
+pub const BACKUP_REF_PREFIX: &str = "refs/git-automessage/original/";
+
 pub struct GitRepo {
     repo: Repository,
 }
@@ -40,66 +71,216 @@ impl GitRepo {
         Ok(GitRepo { repo })
     }
 
-    pub fn get_staged_files(&self) -> Result<Vec<StagedFile>> {
-        let mut diff_opts = DiffOptions::new();
-        diff_opts.include_untracked(true);
+    pub fn open_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let repo = Repository::open(path.as_ref())
+            .with_context(|| format!("could not open repository at {:?}", path.as_ref()))?;
+        Ok(GitRepo { repo })
+    }
 
-        let diff = self
+    /// Use an alternative index file, e.g. the temporary index git exports
+    /// through `GIT_INDEX_FILE` while running commit hooks.
+    pub fn use_index_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let mut index = git2::Index::open(path.as_ref())
+            .with_context(|| format!("could not open index file {:?}", path.as_ref()))?;
+        self.repo.set_index(&mut index)?;
+        Ok(())
+    }
+
+    /// Directory git runs hooks from, honoring `core.hooksPath`.
+    pub fn hooks_dir(&self) -> Result<PathBuf> {
+        let config = self.repo.config()?;
+        if let Ok(hooks_path) = config.get_path("core.hooksPath") {
+            if hooks_path.is_absolute() {
+                return Ok(hooks_path);
+            }
+            let base = self.repo.workdir().unwrap_or_else(|| self.repo.path());
+            return Ok(base.join(hooks_path));
+        }
+        Ok(self.repo.commondir().join("hooks"))
+    }
+
+    /// Comment character git uses in message files (`core.commentChar`).
+    pub fn comment_char(&self) -> char {
+        self.repo
+            .config()
+            .and_then(|c| c.get_string("core.commentChar"))
+            .ok()
+            .filter(|s| s != "auto")
+            .and_then(|s| s.chars().next())
+            .unwrap_or('#')
+    }
+
+    /// Contents of the `commit.template` file, if one is set and readable.
+    pub fn commit_template(&self) -> Option<String> {
+        let path = self
             .repo
-            .diff_index_to_workdir(None, Some(&mut diff_opts))?;
-        let mut files = Vec::new();
+            .config()
+            .and_then(|c| c.get_string("commit.template"))
+            .ok()?;
+        let path = crate::sign::expand_home(&path);
+        let path = match self.repo.workdir() {
+            Some(workdir) if path.is_relative() => workdir.join(path),
+            _ => path,
+        };
+        std::fs::read_to_string(path).ok()
+    }
 
-        diff.foreach(
-            &mut |delta, _| {
-                if let Some(path) = delta.new_file().path() {
-                    let status = match delta.status() {
-                        git2::Delta::Added => "added",
-                        git2::Delta::Modified => "modified",
-                        git2::Delta::Deleted => "deleted",
-                        git2::Delta::Renamed => "renamed",
-                        git2::Delta::Copied => "copied",
-                        _ => "unknown",
-                    };
-                    files.push(StagedFile {
-                        path: path.to_string_lossy().to_string(),
-                        status: status.to_string(),
-                    });
-                }
-                true
-            },
-            None,
-            None,
-            None,
-        )?;
+    /// Editor git would use for messages: `GIT_EDITOR`, `core.editor`,
+    /// `VISUAL`, `EDITOR`, then `vi`.
+    pub fn editor(&self) -> String {
+        let non_empty = |value: String| (!value.trim().is_empty()).then_some(value);
+
+        std::env::var("GIT_EDITOR")
+            .ok()
+            .and_then(non_empty)
+            .or_else(|| {
+                self.repo
+                    .config()
+                    .and_then(|c| c.get_string("core.editor"))
+                    .ok()
+                    .and_then(non_empty)
+            })
+            .or_else(|| std::env::var("VISUAL").ok().and_then(non_empty))
+            .or_else(|| std::env::var("EDITOR").ok().and_then(non_empty))
+            .unwrap_or_else(|| "vi".to_string())
+    }
 
-        Ok(files)
+    pub fn get_staged_files(&self) -> Result<Vec<StagedFile>> {
+        self.get_changed_files(DiffMode::Staged)
     }
 
     pub fn get_staged_diff(&self) -> Result<String> {
-        let mut diff_opts = DiffOptions::new();
-        diff_opts.include_untracked(true);
+        self.get_diff(DiffMode::Staged)
+    }
 
-        let diff = self
-            .repo
-            .diff_index_to_workdir(None, Some(&mut diff_opts))?;
-        let mut diff_text = String::new();
-
-        diff.print(git2::DiffFormat::Patch, |_delta, _hunk, line| {
-            let prefix = match line.origin() {
-                '+' => "+",
-                '-' => "-",
-                ' ' => " ",
-                _ => "",
-            };
-            diff_text.push_str(&format!(
-                "{}{}",
-                prefix,
-                std::str::from_utf8(line.content()).unwrap_or("")
-            ));
-            true
-        })?;
+    pub fn get_changed_files(&self, mode: DiffMode) -> Result<Vec<StagedFile>> {
+        let diff = self.diff_for_mode(mode)?;
+        diff_files(&diff)
+    }
+
+    pub fn get_diff(&self, mode: DiffMode) -> Result<String> {
+        let diff = self.diff_for_mode(mode)?;
+        diff_to_text(&diff)
+    }
+
+    /// Stage every change in the working tree, including untracked files,
+    /// so that the index matches what `DiffMode::All` describes.
+    pub fn stage_all(&self) -> Result<()> {
+        let mut index = self.repo.index()?;
+        index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
+        index.update_all(["*"].iter(), None)?;
+        index.write()?;
+        Ok(())
+    }
+
+    /// Stage the given files, relative to the working tree. Ignored files
+    /// that are not tracked yet are skipped.
+    pub fn stage_paths(&self, paths: &[PathBuf]) -> Result<()> {
+        let mut index = self.repo.index()?;
+        for path in paths {
+            if index.get_path(path, 0).is_none() && self.repo.is_path_ignored(path)? {
+                continue;
+            }
+            index.add_path(path)?;
+        }
+        index.write()?;
+        Ok(())
+    }
+
+    /// Root of the working tree.
+    pub fn workdir(&self) -> Result<&Path> {
+        self.repo
+            .workdir()
+            .context("the repository has no working tree")
+    }
+
+    /// The commit HEAD points at, if any.
+    pub fn head_id(&self) -> Result<Option<Oid>> {
+        match self.repo.head() {
+            Ok(head) => Ok(Some(head.peel_to_commit()?.id())),
+            Err(e) if e.code() == ErrorCode::UnbornBranch || e.code() == ErrorCode::NotFound => {
+                Ok(None)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Move HEAD and the index back to `oid`, leaving the working tree
+    /// alone like `git reset --mixed`.
+    pub fn reset_to(&self, oid: Oid) -> Result<()> {
+        let commit = self.repo.find_commit(oid)?;
+        self.repo
+            .reset(commit.as_object(), git2::ResetType::Mixed, None)?;
+        Ok(())
+    }
 
-        Ok(diff_text)
+    pub fn tag_exists(&self, name: &str) -> bool {
+        self.repo
+            .find_reference(&format!("refs/tags/{}", name))
+            .is_ok()
+    }
+
+    fn head_tree(&self) -> Result<Option<Tree<'_>>> {
+        match self.repo.head() {
+            Ok(head) => Ok(Some(head.peel_to_tree()?)),
+            Err(e) if e.code() == ErrorCode::UnbornBranch || e.code() == ErrorCode::NotFound => {
+                Ok(None)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn diff_for_mode(&self, mode: DiffMode) -> Result<Diff<'_>> {
+        let mut diff_opts = DiffOptions::new();
+
+        let mut diff = match mode {
+            DiffMode::Staged => {
+                let head_tree = self.head_tree()?;
+                let index = self.repo.index()?;
+                self.repo.diff_tree_to_index(
+                    head_tree.as_ref(),
+                    Some(&index),
+                    Some(&mut diff_opts),
+                )?
+            }
+            DiffMode::Unstaged => {
+                diff_opts
+                    .include_untracked(true)
+                    .recurse_untracked_dirs(true)
+                    .show_untracked_content(true);
+                self.repo
+                    .diff_index_to_workdir(None, Some(&mut diff_opts))?
+            }
+            DiffMode::All => {
+                diff_opts
+                    .include_untracked(true)
+                    .recurse_untracked_dirs(true)
+                    .show_untracked_content(true);
+                let head_tree = self.head_tree()?;
+                self.repo
+                    .diff_tree_to_workdir_with_index(head_tree.as_ref(), Some(&mut diff_opts))?
+            }
+            DiffMode::Amend => {
+                let head = self
+                    .repo
+                    .head()
+                    .and_then(|head| head.peel_to_commit())
+                    .context("there is no commit to amend")?;
+                let parent_tree = match head.parent(0) {
+                    Ok(parent) => Some(parent.tree()?),
+                    Err(_) => None,
+                };
+                let index = self.repo.index()?;
+                self.repo.diff_tree_to_index(
+                    parent_tree.as_ref(),
+                    Some(&index),
+                    Some(&mut diff_opts),
+                )?
+            }
+        };
+
+        diff.find_similar(None)?;
+        Ok(diff)
     }
 
     pub fn get_commit_info(&self, reference: &str) -> Result<CommitInfo> {
@@ -113,7 +294,7 @@ impl GitRepo {
 
         let tree = commit.tree()?;
         let parent = commit.parent(0).ok();
-        let parent_tree = parent.as_ref().map(|p| p.tree().ok()).flatten();
+        let parent_tree = parent.as_ref().and_then(|p| p.tree().ok());
 
         let diff = self
             .repo
@@ -141,6 +322,31 @@ impl GitRepo {
         })
     }
 
+    /// Files and diff a commit introduced relative to its first parent.
+    pub fn get_commit_changes(&self, reference: &str) -> Result<(Vec<StagedFile>, String)> {
+        let commit = self.repo.revparse_single(reference)?.peel_to_commit()?;
+        let tree = commit.tree()?;
+        let parent_tree = match commit.parent(0) {
+            Ok(parent) => Some(parent.tree()?),
+            Err(_) => None,
+        };
+
+        let mut diff = self
+            .repo
+            .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+        diff.find_similar(None)?;
+        Ok((diff_files(&diff)?, diff_to_text(&diff)?))
+    }
+
+    /// Name of the checked-out branch, also for a branch with no commits
+    /// yet. `None` when HEAD is detached.
+    pub fn current_branch(&self) -> Option<String> {
+        let head = self.repo.find_reference("HEAD").ok()?;
+        head.symbolic_target()
+            .and_then(|target| target.strip_prefix("refs/heads/"))
+            .map(str::to_string)
+    }
+
     pub fn get_recent_commits(&self, count: usize) -> Result<Vec<CommitInfo>> {
         let mut revwalk = self.repo.revwalk()?;
         revwalk.push_head()?;
@@ -180,33 +386,537 @@ impl GitRepo {
         Ok(commits)
     }
 
+    /// Every commit reachable from `reference`, newest first.
+    pub fn get_commits_reachable(&self, reference: &str) -> Result<Vec<CommitInfo>> {
+        let tip = self.repo.revparse_single(reference)?.peel_to_commit()?;
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.push(tip.id())?;
+
+        let mut commits = Vec::new();
+        for oid in revwalk {
+            commits.push(self.get_commit_info(&oid?.to_string())?);
+        }
+        Ok(commits)
+    }
+
+    /// Tag names with the commits they point at; tags of other objects are
+    /// left out.
+    fn tag_targets(&self) -> Result<Vec<(String, Oid)>> {
+        let mut targets = Vec::new();
+        for name in self.repo.tag_names(None)?.iter().flatten() {
+            if let Ok(target) = self
+                .repo
+                .revparse_single(&format!("refs/tags/{}", name))
+                .and_then(|object| object.peel_to_commit())
+            {
+                targets.push((name.to_string(), target.id()));
+            }
+        }
+        Ok(targets)
+    }
+
+    /// Names of the tags pointing at `reference` or one of its ancestors.
+    pub fn tags_reachable_from(&self, reference: &str) -> Result<Vec<String>> {
+        let tip = self.repo.revparse_single(reference)?.peel_to_commit()?.id();
+        let mut tags = Vec::new();
+        for (name, target) in self.tag_targets()? {
+            if target == tip || self.repo.graph_descendant_of(tip, target)? {
+                tags.push(name);
+            }
+        }
+        Ok(tags)
+    }
+
+    /// The tag on the nearest ancestor of `reference`, not counting tags on
+    /// `reference` itself. Of several tags on one commit, the highest
+    /// version wins.
+    pub fn previous_tag(&self, reference: &str) -> Result<Option<String>> {
+        let tip = self.repo.revparse_single(reference)?.peel_to_commit()?.id();
+        let targets = self.tag_targets()?;
+
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+        revwalk.push(tip)?;
+        for oid in revwalk {
+            let oid = oid?;
+            if oid == tip {
+                continue;
+            }
+            let version = |name: &str| {
+                crate::version::Version::parse(
+                    name.trim_start_matches(|c: char| !c.is_ascii_digit()),
+                )
+            };
+            let newest = targets
+                .iter()
+                .filter(|(_, target)| *target == oid)
+                .max_by(|(a, _), (b, _)| version(a).cmp(&version(b)).then_with(|| a.cmp(b)));
+            if let Some((name, _)) = newest {
+                return Ok(Some(name.clone()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Whether `commit` exists and is `reference` or one of its ancestors.
+    pub fn is_ancestor(&self, commit: &str, reference: &str) -> Result<bool> {
+        let Ok(commit) = self
+            .repo
+            .revparse_single(commit)
+            .and_then(|object| object.peel_to_commit())
+        else {
+            return Ok(false);
+        };
+        let tip = self.repo.revparse_single(reference)?.peel_to_commit()?.id();
+        Ok(commit.id() == tip || self.repo.graph_descendant_of(tip, commit.id())?)
+    }
+
+    /// Author identity from `GIT_AUTHOR_*` or `user.name`/`user.email`.
+    pub fn author_signature(&self) -> Result<Signature<'static>> {
+        self.identity("AUTHOR")
+    }
+
+    /// Committer identity from `GIT_COMMITTER_*` or `user.name`/`user.email`.
+    pub fn committer_signature(&self) -> Result<Signature<'static>> {
+        self.identity("COMMITTER")
+    }
+
+    fn identity(&self, role: &str) -> Result<Signature<'static>> {
+        let env = |field: &str| {
+            std::env::var(format!("GIT_{}_{}", role, field))
+                .ok()
+                .filter(|v| !v.is_empty())
+        };
+        let name = env("NAME");
+        let email = env("EMAIL");
+
+        let (name, email) = match (name, email) {
+            (Some(name), Some(email)) => (name, email),
+            (name, email) => {
+                let configured = self.repo.signature().context(
+                    "could not determine your identity; set user.name and user.email in git config",
+                )?;
+                (
+                    name.unwrap_or_else(|| configured.name().unwrap_or_default().to_string()),
+                    email.unwrap_or_else(|| configured.email().unwrap_or_default().to_string()),
+                )
+            }
+        };
+
+        match env("DATE") {
+            Some(date) => {
+                let time = parse_git_date(&date)
+                    .with_context(|| format!("invalid GIT_{}_DATE: {}", role, date))?;
+                Ok(Signature::new(&name, &email, &time)?)
+            }
+            None => Ok(Signature::now(&name, &email)?),
+        }
+    }
+
     pub fn create_commit(&self, message: &str) -> Result<()> {
-        let signature = Signature::now("Git AutoMessage", "automessage@git")?;
+        let author = self.author_signature()?;
+        let committer = self.committer_signature()?;
         let tree = self.repo.find_tree(self.repo.index()?.write_tree()?)?;
 
-        let parent_commit = self.repo.head()?.peel_to_commit()?;
+        let parent_commit = match self.head_tree()? {
+            Some(_) => Some(self.repo.head()?.peel_to_commit()?),
+            None => None,
+        };
+        let parents: Vec<&git2::Commit> = parent_commit.iter().collect();
 
-        self.repo.commit(
-            Some("HEAD"),
-            &signature,
-            &signature,
-            message,
-            &tree,
-            &[&parent_commit],
-        )?;
+        let oid = self.write_commit(&author, &committer, message, &tree, &parents)?;
+
+        let summary = message.lines().next().unwrap_or("");
+        let reflog = if parents.is_empty() {
+            format!("commit (initial): {}", summary)
+        } else {
+            format!("commit: {}", summary)
+        };
+        self.update_head(oid, &reflog)?;
+
+        Ok(())
+    }
+
+    /// Replace HEAD with a commit of the index and `message`, keeping HEAD's
+    /// parents and author like `git commit --amend`.
+    pub fn amend_commit(&self, message: &str) -> Result<()> {
+        let head = self
+            .repo
+            .head()
+            .and_then(|head| head.peel_to_commit())
+            .context("there is no commit to amend")?;
+        let committer = self.committer_signature()?;
+        let tree = self.repo.find_tree(self.repo.index()?.write_tree()?)?;
+        let parents: Vec<git2::Commit> = head.parents().collect();
+        let parents: Vec<&git2::Commit> = parents.iter().collect();
+
+        let oid = self.write_commit(&head.author(), &committer, message, &tree, &parents)?;
+
+        let summary = message.lines().next().unwrap_or("");
+        self.update_head(oid, &format!("commit (amend): {}", summary))?;
 
         Ok(())
     }
 
+    /// Give the commit at `reference` a new message; see [`Self::reword_commits`].
+    pub fn reword_commit(&self, reference: &str, message: &str, force: bool) -> Result<Reworded> {
+        let target = self.repo.revparse_single(reference)?.peel_to_commit()?;
+        self.reword_commits(&[(target.id(), message.to_string())], force)
+    }
+
+    /// Give each commit a new message and cherry-pick the rest of the branch
+    /// on top, in one pass. Authors, committers and their dates are kept.
+    /// The original commits are kept under [`BACKUP_REF_PREFIX`], and the
+    /// branch is only moved if nothing else moved it meanwhile. History
+    /// already on a remote-tracking branch is only rewritten with `force`.
+    pub fn reword_commits(&self, messages: &[(Oid, String)], force: bool) -> Result<Reworded> {
+        let head = self
+            .repo
+            .head()
+            .and_then(|head| head.peel_to_commit())
+            .context("HEAD does not point at a commit")?;
+
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)?;
+        revwalk.push(head.id())?;
+        for (oid, _) in messages {
+            let short = &oid.to_string()[..8];
+            if *oid != head.id() && !self.repo.graph_descendant_of(head.id(), *oid)? {
+                anyhow::bail!("commit {} is not an ancestor of HEAD", short);
+            }
+            if !force && let Some(remote) = self.published_on(*oid)? {
+                anyhow::bail!(
+                    "commit {} is already on {}; pass --force to rewrite published history",
+                    short,
+                    remote
+                );
+            }
+            // Commits below another target are rewritten anyway.
+            let mut lowest = true;
+            for (other, _) in messages {
+                if self.repo.graph_descendant_of(*oid, *other)? {
+                    lowest = false;
+                    break;
+                }
+            }
+            if lowest {
+                for parent in self.repo.find_commit(*oid)?.parent_ids() {
+                    revwalk.hide(parent)?;
+                }
+            }
+        }
+
+        // Oldest first: the first reworded commit and everything after it.
+        let commits = revwalk
+            .map(|oid| Ok(self.repo.find_commit(oid?)?))
+            .collect::<Result<Vec<_>>>()?;
+        if commits
+            .iter()
+            .skip(1)
+            .any(|commit| commit.parent_count() > 1)
+            || messages
+                .iter()
+                .any(|(oid, _)| !commits.iter().any(|commit| commit.id() == *oid))
+        {
+            anyhow::bail!("cannot reword a commit below a merge commit");
+        }
+
+        let mut backups = Vec::new();
+        for (oid, _) in messages {
+            let backup = format!("{}{}", BACKUP_REF_PREFIX, oid);
+            self.repo
+                .reference(&backup, *oid, true, &format!("reword: original of {}", oid))?;
+            backups.push(backup);
+        }
+
+        let mut rewritten: std::collections::HashMap<Oid, Oid> = Default::default();
+        for commit in &commits {
+            let message = messages
+                .iter()
+                .find(|(oid, _)| *oid == commit.id())
+                .map(|(_, message)| message.as_str())
+                .unwrap_or_else(|| commit.message_raw().unwrap_or(""));
+            let parents = commit
+                .parent_ids()
+                .map(|parent| {
+                    let parent = rewritten.get(&parent).copied().unwrap_or(parent);
+                    self.repo.find_commit(parent)
+                })
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+
+            // Only commits whose parent was rewritten need to be picked.
+            let moved = commit
+                .parent_ids()
+                .zip(&parents)
+                .any(|(old, new)| old != new.id());
+            let tree = if moved {
+                let mut index = self.repo.cherrypick_commit(commit, &parents[0], 0, None)?;
+                if index.has_conflicts() {
+                    anyhow::bail!(
+                        "cherry-picking {} caused conflicts",
+                        &commit.id().to_string()[..8]
+                    );
+                }
+                self.repo.find_tree(index.write_tree_to(&self.repo)?)?
+            } else {
+                commit.tree()?
+            };
+
+            let oid = self.write_commit(
+                &commit.author(),
+                &commit.committer(),
+                message,
+                &tree,
+                &parent_refs,
+            )?;
+            rewritten.insert(commit.id(), oid);
+        }
+
+        let tip = rewritten[&head.id()];
+        let summary = match messages {
+            [(_, message)] => message.lines().next().unwrap_or("").to_string(),
+            _ => format!("{} commits", messages.len()),
+        };
+        self.move_head(head.id(), tip, &format!("reword: {}", summary))?;
+
+        Ok(Reworded {
+            commits: messages.iter().map(|(oid, _)| rewritten[oid]).collect(),
+            rebased: commits.len() - messages.len(),
+            backups,
+        })
+    }
+
+    /// First remote-tracking branch that already contains `oid`.
+    fn published_on(&self, oid: Oid) -> Result<Option<String>> {
+        for reference in self.repo.references_glob("refs/remotes/*")? {
+            let reference = reference?;
+            let Ok(commit) = reference.peel_to_commit() else {
+                continue;
+            };
+            if commit.id() == oid || self.repo.graph_descendant_of(commit.id(), oid)? {
+                let name = reference.shorthand().unwrap_or("a remote branch");
+                return Ok(Some(name.to_string()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Write a commit object, signed when `commit.gpgsign` is set. No ref is
+    /// updated.
+    fn write_commit(
+        &self,
+        author: &Signature,
+        committer: &Signature,
+        message: &str,
+        tree: &Tree,
+        parents: &[&git2::Commit],
+    ) -> Result<Oid> {
+        let config = self.repo.config()?;
+        if !config.get_bool("commit.gpgsign").unwrap_or(false) {
+            return Ok(self
+                .repo
+                .commit(None, author, committer, message, tree, parents)?);
+        }
+
+        let buffer = self
+            .repo
+            .commit_create_buffer(author, committer, message, tree, parents)?;
+        let content = buffer
+            .as_str()
+            .context("commit buffer is not valid UTF-8")?;
+        let signature = Signer::from_config(&config)?.sign(content, committer)?;
+        Ok(self.repo.commit_signed(content, &signature, None)?)
+    }
+
+    /// Move HEAD (or the branch it refers to) from `old` to `new`, failing
+    /// if it no longer points at `old`.
+    fn move_head(&self, old: Oid, new: Oid, reflog: &str) -> Result<()> {
+        let head = self.repo.find_reference("HEAD")?;
+        match head.symbolic_target() {
+            Some(target) => {
+                self.repo
+                    .reference_matching(target, new, true, old, reflog)
+                    .with_context(|| format!("{} was updated by someone else", target))?;
+            }
+            None => self.repo.set_head_detached(new)?,
+        }
+        Ok(())
+    }
+
+    /// Point HEAD (or the branch it refers to) at `oid`.
+    fn update_head(&self, oid: Oid, reflog: &str) -> Result<()> {
+        let head = self.repo.find_reference("HEAD")?;
+        match head.symbolic_target() {
+            Some(target) => {
+                self.repo.reference(target, oid, true, reflog)?;
+            }
+            None => self.repo.set_head_detached(oid)?,
+        }
+        Ok(())
+    }
+
     pub fn create_annotated_tag(&self, name: &str, message: &str, reference: &str) -> Result<()> {
         let obj = self.repo.revparse_single(reference)?;
         let commit = obj.peel_to_commit()?;
 
-        let signature = Signature::now("Git AutoMessage", "automessage@git")?;
+        let tagger = self.committer_signature()?;
 
-        let object = commit.as_object();
-        self.repo.tag(name, object, &signature, message, false)?;
+        let config = self.repo.config()?;
+        let sign = config.get_bool("tag.gpgsign").unwrap_or(false)
+            || config.get_bool("tag.forceSignAnnotated").unwrap_or(false);
+        if !sign {
+            let object = commit.as_object();
+            self.repo.tag(name, object, &tagger, message, false)?;
+            return Ok(());
+        }
+
+        let ref_name = format!("refs/tags/{}", name);
+        if self.repo.find_reference(&ref_name).is_ok() {
+            anyhow::bail!("tag '{}' already exists", name);
+        }
+
+        let mut message = message.to_string();
+        if !message.ends_with('\n') {
+            message.push('\n');
+        }
+        let payload = format!(
+            "object {}\ntype commit\ntag {}\ntagger {}\n\n{}",
+            commit.id(),
+            name,
+            format_signature(&tagger),
+            message
+        );
+        let signature = Signer::from_config(&config)?.sign(&payload, &tagger)?;
+
+        let content = format!("{}{}", payload, signature);
+        let oid = self
+            .repo
+            .odb()?
+            .write(ObjectType::Tag, content.as_bytes())?;
+        self.repo
+            .reference(&ref_name, oid, false, &format!("tag: {}", name))?;
 
         Ok(())
     }
 }
+
+/// Format a signature the way it appears in commit and tag headers.
+fn format_signature(signature: &Signature) -> String {
+    let when = signature.when();
+    let offset = when.offset_minutes().abs();
+    format!(
+        "{} <{}> {} {}{:02}{:02}",
+        signature.name().unwrap_or_default(),
+        signature.email().unwrap_or_default(),
+        when.seconds(),
+        when.sign(),
+        offset / 60,
+        offset % 60
+    )
+}
+
+/// Parse the date formats git accepts in `GIT_AUTHOR_DATE` and
+/// `GIT_COMMITTER_DATE`: git's internal `[@]<seconds> <offset>`, RFC 2822
+/// and ISO 8601.
+pub fn parse_git_date(date: &str) -> Option<Time> {
+    let date = date.trim();
+
+    let raw = date.strip_prefix('@').unwrap_or(date);
+    let mut parts = raw.split_whitespace();
+    if let Some(Ok(seconds)) = parts.next().map(str::parse::<i64>) {
+        let offset = match parts.next() {
+            Some(tz) => parse_offset(tz)?,
+            None => 0,
+        };
+        if parts.next().is_none() {
+            return Some(Time::new(seconds, offset));
+        }
+    }
+
+    let parsed = chrono::DateTime::parse_from_rfc2822(date)
+        .or_else(|_| chrono::DateTime::parse_from_rfc3339(date))
+        .or_else(|_| chrono::DateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S %z"))
+        .or_else(|_| chrono::DateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S%z"));
+    if let Ok(parsed) = parsed {
+        return Some(Time::new(
+            parsed.timestamp(),
+            parsed.offset().local_minus_utc() / 60,
+        ));
+    }
+
+    let naive = chrono::NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| chrono::NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S"))
+        .ok()?;
+    let local = naive.and_local_timezone(chrono::Local).single()?;
+    Some(Time::new(
+        local.timestamp(),
+        local.offset().local_minus_utc() / 60,
+    ))
+}
+
+fn parse_offset(tz: &str) -> Option<i32> {
+    let (sign, digits) = match tz.as_bytes().first()? {
+        b'+' => (1, &tz[1..]),
+        b'-' => (-1, &tz[1..]),
+        _ => return None,
+    };
+    if digits.len() != 4 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let hours: i32 = digits[..2].parse().ok()?;
+    let minutes: i32 = digits[2..].parse().ok()?;
+    Some(sign * (hours * 60 + minutes))
+}
+
+fn diff_files(diff: &Diff) -> Result<Vec<StagedFile>> {
+    let mut files = Vec::new();
+
+    diff.foreach(
+        &mut |delta, _| {
+            let path = delta.new_file().path().or_else(|| delta.old_file().path());
+            if let Some(path) = path {
+                let status = match delta.status() {
+                    git2::Delta::Added | git2::Delta::Untracked => "added",
+                    git2::Delta::Modified => "modified",
+                    git2::Delta::Deleted => "deleted",
+                    git2::Delta::Renamed => "renamed",
+                    git2::Delta::Copied => "copied",
+                    git2::Delta::Typechange => "typechange",
+                    _ => "unknown",
+                };
+                files.push(StagedFile {
+                    path: path.to_string_lossy().to_string(),
+                    status: status.to_string(),
+                });
+            }
+            true
+        },
+        None,
+        None,
+        None,
+    )?;
+
+    Ok(files)
+}
+
+fn diff_to_text(diff: &Diff) -> Result<String> {
+    let mut diff_text = String::new();
+
+    diff.print(git2::DiffFormat::Patch, |_delta, _hunk, line| {
+        let prefix = match line.origin() {
+            '+' => "+",
+            '-' => "-",
+            ' ' => " ",
+            _ => "",
+        };
+        diff_text.push_str(&format!(
+            "{}{}",
+            prefix,
+            std::str::from_utf8(line.content()).unwrap_or("")
+        ));
+        true
+    })?;
+
+    Ok(diff_text)
+}
@@ -0,0 +1,233 @@
+//     _         _        __  __
+//    / \  _   _| |_ ___ |  \/  | ___  ___ ___  __ _  __ _  ___
+//   / _ \| | | | __/ _ \| |\/| |/ _ \/ __/ __|/ _` |/ _` |/ _ \
+//  / ___ \ |_| | || (_) | |  | |  __/\__ \__ \ (_| | (_| |  __/
+// /_/   \_\__,_|\__\___/|_|  |_|\___||___/___/\__,_|\__, |\___|
+//                                                   |___/
+//
+// Author: Sidney Zhang <zly@lyzhang.me>
+// Date: 2025-08-05
+// License: MIT
+//
+// prepare-commit-msg hook management.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::git::GitRepo;
+
+pub const HOOK_NAME: &str = "prepare-commit-msg";
+
+/// Marker line identifying a hook written by git-automessage.
+const HOOK_MARKER: &str = "# git-automessage prepare-commit-msg hook";
+
+/// Suffix given to a pre-existing hook that we chain to.
+const CHAINED_SUFFIX: &str = ".pre-automessage";
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum HookStatus {
+    /// No prepare-commit-msg hook is present.
+    NotInstalled,
+    /// Our hook is installed, optionally chaining a previous hook.
+    Installed { chained: bool },
+    /// Another prepare-commit-msg hook is present.
+    Foreign,
+}
+
+pub struct HookManager {
+    hooks_dir: PathBuf,
+}
+
+impl HookManager {
+    pub fn new(repo: &GitRepo) -> Result<Self> {
+        Ok(HookManager {
+            hooks_dir: repo.hooks_dir()?,
+        })
+    }
+
+    pub fn hook_path(&self) -> PathBuf {
+        self.hooks_dir.join(HOOK_NAME)
+    }
+
+    pub fn chained_path(&self) -> PathBuf {
+        self.hooks_dir
+            .join(format!("{}{}", HOOK_NAME, CHAINED_SUFFIX))
+    }
+
+    pub fn status(&self) -> Result<HookStatus> {
+        let hook_path = self.hook_path();
+        if !hook_path.exists() {
+            return Ok(HookStatus::NotInstalled);
+        }
+        if is_our_hook(&hook_path)? {
+            Ok(HookStatus::Installed {
+                chained: self.chained_path().exists(),
+            })
+        } else {
+            Ok(HookStatus::Foreign)
+        }
+    }
+
+    /// Write the hook, moving any existing foreign hook aside so it keeps
+    /// running before ours.
+    pub fn install(&self) -> Result<HookStatus> {
+        fs::create_dir_all(&self.hooks_dir)
+            .with_context(|| format!("could not create hooks directory {:?}", self.hooks_dir))?;
+
+        let hook_path = self.hook_path();
+        if self.status()? == HookStatus::Foreign {
+            let chained_path = self.chained_path();
+            if chained_path.exists() {
+                anyhow::bail!(
+                    "both {:?} and {:?} exist; remove one of them before installing",
+                    hook_path,
+                    chained_path
+                );
+            }
+            fs::rename(&hook_path, &chained_path)
+                .with_context(|| format!("could not move existing hook {:?}", hook_path))?;
+        }
+
+        fs::write(&hook_path, hook_script())
+            .with_context(|| format!("could not write hook {:?}", hook_path))?;
+        make_executable(&hook_path)?;
+
+        self.status()
+    }
+
+    /// Remove our hook and restore the chained one, if any.
+    pub fn uninstall(&self) -> Result<HookStatus> {
+        match self.status()? {
+            HookStatus::NotInstalled => return Ok(HookStatus::NotInstalled),
+            HookStatus::Foreign => {
+                anyhow::bail!(
+                    "{:?} was not installed by git-automessage; leaving it untouched",
+                    self.hook_path()
+                )
+            }
+            HookStatus::Installed { .. } => {}
+        }
+
+        let hook_path = self.hook_path();
+        fs::remove_file(&hook_path)
+            .with_context(|| format!("could not remove hook {:?}", hook_path))?;
+
+        let chained_path = self.chained_path();
+        if chained_path.exists() {
+            fs::rename(&chained_path, &hook_path)
+                .with_context(|| format!("could not restore hook {:?}", chained_path))?;
+        }
+
+        self.status()
+    }
+}
+
+/// Whether the hook should fill in a message for the given `source`
+/// argument. Git passes no source for a plain `git commit`, and `template`
+/// when `commit.template` is set; merges, squashes, `-m`/`-F` messages and
+/// amends (`commit`) keep the message git prepared.
+pub fn should_generate(source: Option<&str>) -> bool {
+    matches!(source, None | Some("") | Some("template"))
+}
+
+/// Whether the message file already holds a message, ignoring comments and
+/// the text of an unchanged `template`.
+pub fn has_message(content: &str, template: Option<&str>, comment_char: char) -> bool {
+    let text = message_text(content, comment_char);
+    !text.is_empty() && template.is_none_or(|template| text != message_text(template, comment_char))
+}
+
+/// Put `message` at the top of the message file, keeping git's comments
+/// and dropping any other text, such as an unchanged template.
+pub fn write_message_file(path: &Path, message: &str, comment_char: char) -> Result<()> {
+    let existing = fs::read_to_string(path).unwrap_or_default();
+    let (head, diff) = split_scissors(&existing, comment_char);
+    let mut comments: Vec<&str> = head
+        .lines()
+        .filter(|line| line.starts_with(comment_char))
+        .collect();
+    comments.extend(diff.lines());
+    let content = if comments.is_empty() {
+        format!("{}\n", message.trim_end())
+    } else {
+        format!("{}\n\n{}\n", message.trim_end(), comments.join("\n"))
+    };
+    fs::write(path, content).with_context(|| format!("could not write message file {:?}", path))?;
+    Ok(())
+}
+
+/// The lines that are not comments, without surrounding blank lines.
+fn message_text(content: &str, comment_char: char) -> String {
+    split_scissors(content, comment_char)
+        .0
+        .lines()
+        .filter(|line| !line.starts_with(comment_char))
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// Split at the scissors line `git commit -v` puts above the diff; git
+/// ignores everything from there on.
+fn split_scissors(content: &str, comment_char: char) -> (&str, &str) {
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        if line.starts_with(comment_char) && line.contains(" >8 ") {
+            return content.split_at(offset);
+        }
+        offset += line.len();
+    }
+    (content, "")
+}
+
+fn is_our_hook(path: &Path) -> Result<bool> {
+    let content = fs::read(path).with_context(|| format!("could not read hook {:?}", path))?;
+    Ok(String::from_utf8_lossy(&content).contains(HOOK_MARKER))
+}
+
+fn hook_script() -> String {
+    let binary = std::env::current_exe()
+        .ok()
+        .map(|p| p.to_string_lossy().replace('\\', "/"))
+        .unwrap_or_default();
+
+    format!(
+        r#"#!/bin/sh
+{marker}
+# Installed by `git-automessage hook install`; remove with `git-automessage hook uninstall`.
+
+hook_dir=$(dirname "$0")
+if [ -x "$hook_dir/{name}{suffix}" ]; then
+    "$hook_dir/{name}{suffix}" "$@" || exit $?
+fi
+
+gam='{binary}'
+if [ ! -x "$gam" ]; then
+    gam=git-automessage
+fi
+exec "$gam" hook run "$@"
+"#,
+        marker = HOOK_MARKER,
+        name = HOOK_NAME,
+        suffix = CHAINED_SUFFIX,
+        binary = binary.replace('\'', "'\\''"),
+    )
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_mode(0o755);
+    fs::set_permissions(path, permissions)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
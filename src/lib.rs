@@ -11,8 +11,21 @@
 //
 // Library interface for git-automessage
 
+pub mod candidates;
 pub mod changelog;
+pub mod cli;
+pub mod commitlint;
+pub mod compact;
 pub mod config;
+pub mod conventional;
 pub mod git;
+pub mod hook;
+pub mod lint;
 pub mod llm;
+pub mod postprocess;
 pub mod prompts;
+pub mod release;
+pub mod review;
+pub mod secret;
+pub mod sign;
+pub mod version;
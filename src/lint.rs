@@ -0,0 +1,310 @@
+//     _         _        __  __
+//    / \  _   _| |_ ___ |  \/  | ___  ___ ___  __ _  __ _  ___
+//   / _ \| | | | __/ _ \| |\/| |/ _ \/ __/ __|/ _` |/ _` |/ _ \
+//  / ___ \ |_| | || (_) | |  | |  __/\__ \__ \ (_| | (_| |  __/
+// /_/   \_\__,_|\__\___/|_|  |_|\___||___/___/\__,_|\__, |\___|
+//                                                   |___/
+//
+// Author: Sidney Zhang <zly@lyzhang.me>
+// Date: 2025-08-05
+// License: MIT
+//
+// Checking human-written commit messages against the team's rules.
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::conventional::{ConventionalCommit, Footer};
+use crate::postprocess::BODY_WIDTH;
+
+/// Header length allowed when `lint.max_header_length` is not set.
+pub const DEFAULT_HEADER_LENGTH: usize = 72;
+
+/// How diagnostics are printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LintFormat {
+    Text,
+    Json,
+}
+
+/// What a message is checked against.
+#[derive(Debug, Clone)]
+pub struct LintRules {
+    /// Allowed commit types; empty allows any.
+    pub types: Vec<String>,
+    /// Allowed scopes; empty allows any.
+    pub scopes: Vec<String>,
+    /// Maximum header length in characters; 0 disables the check.
+    pub max_header_length: usize,
+    /// Maximum body line length in characters; 0 disables the check.
+    pub body_width: usize,
+    /// Trailers every message must carry, e.g. `Signed-off-by`.
+    pub required_trailers: Vec<String>,
+    pub allow_wip: bool,
+}
+
+impl LintRules {
+    pub fn from_config(config: &Config) -> Self {
+        LintRules {
+            types: config.types.clone(),
+            scopes: config.scopes.clone(),
+            max_header_length: config
+                .lint
+                .max_header_length
+                .unwrap_or(DEFAULT_HEADER_LENGTH),
+            body_width: config.lint.body_width.unwrap_or(BODY_WIDTH),
+            required_trailers: config.lint.required_trailers.clone(),
+            allow_wip: config.lint.allow_wip,
+        }
+    }
+}
+
+impl Default for LintRules {
+    fn default() -> Self {
+        Self::from_config(&Config::default())
+    }
+}
+
+/// One broken rule, at a 1-based line and character column.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub rule: &'static str,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn new(rule: &'static str, line: usize, column: usize, message: impl Into<String>) -> Self {
+        Diagnostic {
+            rule,
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+}
+
+/// The diagnostics of one message.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    /// Where the message came from: a file path or an abbreviated commit SHA.
+    pub source: String,
+    pub subject: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Report {
+    pub fn new(source: impl Into<String>, message: &str, rules: &LintRules) -> Self {
+        Report {
+            source: source.into(),
+            subject: message.trim().lines().next().unwrap_or("").to_string(),
+            diagnostics: lint(message, rules),
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+}
+
+/// Check `message`. Git's default merge messages are not checked.
+pub fn lint(message: &str, rules: &LintRules) -> Vec<Diagnostic> {
+    let message = message.trim_end();
+    let lines: Vec<&str> = message.lines().collect();
+    let header = lines.first().copied().unwrap_or("");
+    let mut diagnostics = Vec::new();
+
+    if header.trim().is_empty() {
+        diagnostics.push(Diagnostic::new(
+            "header-empty",
+            1,
+            1,
+            "the message is empty",
+        ));
+        return diagnostics;
+    }
+    if header.starts_with("Merge ") {
+        return diagnostics;
+    }
+
+    if !rules.allow_wip && is_wip(header) {
+        diagnostics.push(Diagnostic::new(
+            "no-wip",
+            1,
+            1,
+            "work-in-progress commits are not allowed",
+        ));
+    }
+
+    let length = header.chars().count();
+    if rules.max_header_length > 0 && length > rules.max_header_length {
+        diagnostics.push(Diagnostic::new(
+            "header-max-length",
+            1,
+            rules.max_header_length + 1,
+            format!(
+                "the header is {} characters, over the limit of {}",
+                length, rules.max_header_length
+            ),
+        ));
+    }
+
+    match ConventionalCommit::parse(message) {
+        Ok(commit) => {
+            if !rules.types.is_empty() && !rules.types.contains(&commit.kind) {
+                diagnostics.push(Diagnostic::new(
+                    "type-enum",
+                    1,
+                    column_of(header, &commit.kind),
+                    format!(
+                        "type '{}' is not one of {}",
+                        commit.kind,
+                        rules.types.join(", ")
+                    ),
+                ));
+            }
+            if let Some(scope) = &commit.scope
+                && !rules.scopes.is_empty()
+                && !rules.scopes.contains(scope)
+            {
+                diagnostics.push(Diagnostic::new(
+                    "scope-enum",
+                    1,
+                    column_of(header, &format!("({})", scope)) + 1,
+                    format!(
+                        "scope '{}' is not one of {}",
+                        scope,
+                        rules.scopes.join(", ")
+                    ),
+                ));
+            }
+        }
+        Err(e) => diagnostics.push(Diagnostic::new(
+            "header-format",
+            e.line,
+            e.column,
+            e.message,
+        )),
+    }
+
+    if rules.body_width > 0 {
+        for (index, line) in lines.iter().enumerate().skip(1) {
+            // Links and trailers cannot be broken up.
+            if line.chars().count() > rules.body_width
+                && !line.contains("://")
+                && Footer::parse(line).is_none()
+            {
+                diagnostics.push(Diagnostic::new(
+                    "body-max-line-length",
+                    index + 1,
+                    rules.body_width + 1,
+                    format!(
+                        "the line is {} characters, over the limit of {}",
+                        line.chars().count(),
+                        rules.body_width
+                    ),
+                ));
+            }
+        }
+    }
+
+    let trailers = trailers(&lines);
+    for required in &rules.required_trailers {
+        if !trailers
+            .iter()
+            .any(|footer| footer.token.eq_ignore_ascii_case(required))
+        {
+            diagnostics.push(Diagnostic::new(
+                "trailer-required",
+                lines.len() + 1,
+                1,
+                format!("the '{}' trailer is missing", required),
+            ));
+        }
+    }
+
+    diagnostics
+}
+
+/// Whether `header` marks a temporary commit: `WIP`, `[WIP]`, `fixup!` or
+/// `squash!`.
+fn is_wip(header: &str) -> bool {
+    let lower = header.trim().to_lowercase();
+    lower.contains("[wip]")
+        || lower.starts_with("fixup!")
+        || lower.starts_with("squash!")
+        || lower
+            .strip_prefix("wip")
+            .is_some_and(|rest| !rest.starts_with(|c: char| c.is_alphanumeric()))
+}
+
+/// Trailers in the last paragraph, after the header.
+fn trailers(lines: &[&str]) -> Vec<Footer> {
+    let start = lines
+        .iter()
+        .rposition(|line| line.trim().is_empty())
+        .map_or(lines.len(), |blank| blank + 1);
+    lines[start..]
+        .iter()
+        .filter_map(|line| Footer::parse(line))
+        .collect()
+}
+
+/// 1-based character column of `needle` in `header`.
+fn column_of(header: &str, needle: &str) -> usize {
+    header
+        .find(needle)
+        .map_or(1, |start| header[..start].chars().count() + 1)
+}
+
+/// The message git would record from a commit message file: everything
+/// below the scissors line and all comment lines are dropped.
+pub fn clean_message(text: &str, comment_char: char) -> String {
+    let scissors = format!(
+        "{} ------------------------ >8 ------------------------",
+        comment_char
+    );
+    let text = match text.find(&scissors) {
+        Some(end) => &text[..end],
+        None => text,
+    };
+    crate::review::strip_comments(text, comment_char)
+}
+
+/// Human-readable diagnostics, one block per message with problems.
+pub fn format_text(reports: &[Report]) -> String {
+    let mut output = String::new();
+    for report in reports.iter().filter(|report| !report.is_ok()) {
+        output.push_str(&format!("{}: {}\n", report.source, report.subject));
+        for diagnostic in &report.diagnostics {
+            output.push_str(&format!(
+                "  {}:{}  {}  [{}]\n",
+                diagnostic.line, diagnostic.column, diagnostic.message, diagnostic.rule
+            ));
+        }
+    }
+
+    let problems: usize = reports.iter().map(|report| report.diagnostics.len()).sum();
+    if problems == 0 {
+        output.push_str(&format!(
+            "No problems found in {} message(s).\n",
+            reports.len()
+        ));
+    } else {
+        output.push_str(&format!(
+            "{} problem(s) in {} of {} message(s).\n",
+            problems,
+            reports.iter().filter(|report| !report.is_ok()).count(),
+            reports.len()
+        ));
+    }
+    output
+}
+
+/// All reports as a JSON array.
+pub fn format_json(reports: &[Report]) -> Result<String> {
+    Ok(serde_json::to_string_pretty(reports)?)
+}
@@ -11,38 +11,360 @@
 //
 // Message generator using llm
 
-use rig::prelude::*;
-
+use crate::candidates::{self, Convention, MessageKind};
+use crate::compact::{self, Compaction};
 use crate::config::Config;
-use crate::config::LLMConfig;
+use crate::config::{LLMConfig, LLMProvider};
+use crate::conventional::ConventionalCommit;
+use crate::postprocess;
+use crate::prompts::{self, INFORMATION_VARIABLE, Prompt, Variables};
 use anyhow::{Context, Result};
+use futures::future::join_all;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use rig::agent::AgentBuilder;
 use rig::client::CompletionClient;
-use rig::completion::Prompt;
-use rig::{agent::AgentBuilder, providers::openai};
+use rig::completion::{Chat as _, CompletionModel, Message};
+use rig::message::UserContent;
+use rig::providers::{anthropic, deepseek, moonshot, ollama, openai};
 use std::time::Duration;
+use tokio::sync::OnceCell;
 use tokio::time::timeout;
 
+/// Anthropic 要求显式设置最大输出 token 数
+const ANTHROPIC_MAX_TOKENS: u64 = 4096;
+
+/// 按提供商构建的 LLM 客户端
+enum Backend {
+    OpenAI(openai::Client),
+    DeepSeek(deepseek::Client),
+    Kimi(moonshot::Client),
+    Anthropic(anthropic::Client),
+    Ollama(ollama::Client),
+}
+
+impl Backend {
+    fn new(config: &LLMConfig, api_key: &str) -> Result<Self> {
+        let base_url = config.resolved_base_url();
+
+        let backend = match config.provider {
+            LLMProvider::OpenAI => Backend::OpenAI(
+                openai::ClientBuilder::new(api_key)
+                    .base_url(&base_url)
+                    .build()
+                    .context("创建LLM客户端失败")?,
+            ),
+            LLMProvider::DeepSeek => Backend::DeepSeek(
+                deepseek::ClientBuilder::new(api_key)
+                    .base_url(&base_url)
+                    .build()
+                    .context("创建LLM客户端失败")?,
+            ),
+            LLMProvider::Kimi => Backend::Kimi(
+                moonshot::ClientBuilder::new(api_key)
+                    .base_url(&base_url)
+                    .build()
+                    .context("创建LLM客户端失败")?,
+            ),
+            LLMProvider::Anthropic => Backend::Anthropic(
+                anthropic::ClientBuilder::new(api_key)
+                    .base_url(&base_url)
+                    .build()
+                    .context("创建LLM客户端失败")?,
+            ),
+            LLMProvider::Ollama => Backend::Ollama(
+                ollama::ClientBuilder::new()
+                    .base_url(&base_url)
+                    .build()
+                    .context("创建LLM客户端失败")?,
+            ),
+        };
+
+        Ok(backend)
+    }
+
+    async fn chat(&self, model: &str, prompt: &str, history: &[Message]) -> Result<String> {
+        match self {
+            // OpenAI 兼容服务普遍只实现了 chat completions 接口
+            Backend::OpenAI(client) => {
+                send_prompt(
+                    client.completion_model(model).completions_api(),
+                    prompt,
+                    history,
+                    None,
+                )
+                .await
+            }
+            Backend::DeepSeek(client) => {
+                send_prompt(client.completion_model(model), prompt, history, None).await
+            }
+            Backend::Kimi(client) => {
+                send_prompt(client.completion_model(model), prompt, history, None).await
+            }
+            Backend::Anthropic(client) => {
+                send_prompt(
+                    client.completion_model(model),
+                    prompt,
+                    history,
+                    Some(ANTHROPIC_MAX_TOKENS),
+                )
+                .await
+            }
+            Backend::Ollama(client) => {
+                send_prompt(client.completion_model(model), prompt, history, None).await
+            }
+        }
+    }
+}
+
+async fn send_prompt<M: CompletionModel>(
+    model: M,
+    prompt: &str,
+    history: &[Message],
+    max_tokens: Option<u64>,
+) -> Result<String> {
+    let mut builder = AgentBuilder::new(model);
+    if let Some(max_tokens) = max_tokens {
+        builder = builder.max_tokens(max_tokens);
+    }
+    let agent = builder.build();
+
+    agent
+        .chat(prompt, history.to_vec())
+        .await
+        .context("获取LLM响应失败")
+}
+
+/// 一次多轮对话的历史，用于根据反馈修改已生成的消息
+#[derive(Debug, Clone, Default)]
+pub struct Conversation {
+    history: Vec<Message>,
+}
+
+impl Conversation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn history(&self) -> &[Message] {
+        &self.history
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.history.is_empty()
+    }
+
+    /// 对话的第一条提示词，即生成原始消息的请求
+    pub fn first_prompt(&self) -> Option<String> {
+        match self.history.first()? {
+            Message::User { content } => match content.first() {
+                UserContent::Text(text) => Some(text.text),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// 用户手动编辑后，用编辑后的内容替换模型的上一条回复
+    pub fn replace_last_reply(&mut self, reply: &str) {
+        if matches!(self.history.last(), Some(Message::Assistant { .. })) {
+            self.history.pop();
+        }
+        self.history.push(Message::assistant(reply));
+    }
+
+    /// 记录一轮问答，例如从候选消息中选定的回复
+    pub fn record(&mut self, prompt: &str, reply: &str) {
+        self.history.push(Message::user(prompt));
+        self.history.push(Message::assistant(reply));
+    }
+}
+
 pub struct MessageGenerator {
-    model: LLMConfig,
-    prompt: String,
+    config: Config,
+    /// 第一次请求时才创建，API Key 引用（密钥环、命令等）也在那时解析
+    backend: OnceCell<Backend>,
+    variables: Variables,
 }
 
 impl MessageGenerator {
     pub fn new() -> Result<Self> {
         let config = Config::load()?;
-        
+        Self::from_config(&config)
+    }
+
+    pub fn from_config(config: &Config) -> Result<Self> {
+        config.validate().context("配置验证失败")?;
+
         Ok(MessageGenerator {
-            model: config.llm.clone(),
+            config: config.clone(),
+            backend: OnceCell::new(),
+            variables: base_variables(config),
         })
     }
 
+    pub fn provider(&self) -> LLMProvider {
+        self.config.llm.provider
+    }
+
+    /// 实际请求的 API 地址
+    pub fn base_url(&self) -> String {
+        self.config.llm.resolved_base_url()
+    }
+
+    /// 实际使用的模型名称
+    pub fn model_name(&self) -> String {
+        self.config.llm.resolved_model().unwrap_or_default()
+    }
+
+    /// 设置模板变量（如 `branch`、`recent_commits`、`max_length`）
+    pub fn set_variable(&mut self, name: &str, value: impl Into<String>) -> &mut Self {
+        self.variables.set(name, value);
+        self
+    }
+
+    /// 选择提示词：带变量的自定义提示词作为完整模板，否则作为额外要求
+    fn resolve_prompt(&self, prompt_type: &str, custom_prompt: Option<&str>) -> Result<Prompt> {
+        match custom_prompt {
+            Some(template) if prompts::is_template(template) => {
+                prompts::check_template(template).context("自定义提示词模板无效")?;
+                Ok(Prompt::user_template(template, &self.config))
+            }
+            custom_prompt => {
+                Ok(Prompt::for_config(prompt_type, &self.config)?.with_instructions(custom_prompt))
+            }
+        }
+    }
+
+    /// 用通用变量和本次请求的变量渲染提示词
+    fn render(&self, prompt: &Prompt, variables: &Variables) -> Result<String> {
+        let mut all = self.variables.clone();
+        all.extend(variables);
+        prompt.render(&all)
+    }
+
     pub async fn generate_message(&self, prompt: &str) -> Result<String> {
+        self.send_with_retries(prompt, &[]).await
+    }
+
+    /// 并发请求多个候选消息，部分失败时只返回成功的结果
+    pub async fn generate_candidates(&self, prompt: &str, count: usize) -> Result<Vec<String>> {
+        let results = join_all((0..count.max(1)).map(|_| self.generate_message(prompt))).await;
+
+        let mut candidates = Vec::new();
+        let mut last_error = None;
+        for result in results {
+            match result {
+                Ok(message) => candidates.push(message),
+                Err(e) => {
+                    eprintln!("生成候选消息失败: {}", e);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        match last_error {
+            Some(e) if candidates.is_empty() => Err(e),
+            _ => Ok(candidates),
+        }
+    }
+
+    /// 在对话中发送提示词，并把提示词和回复记入历史
+    pub async fn converse(&self, conversation: &mut Conversation, prompt: &str) -> Result<String> {
+        let reply = self
+            .send_with_retries(prompt, conversation.history())
+            .await?;
+        conversation.record(prompt, &reply);
+        Ok(reply)
+    }
+
+    /// 根据反馈修改对话中的上一条消息，而不是从头生成
+    pub async fn refine(&self, conversation: &mut Conversation, feedback: &str) -> Result<String> {
+        let prompt = if prompts::is_chinese(&self.config.language) {
+            format!(
+                "请根据以下反馈修改上面的提交消息，保持原有格式要求，只输出修改后的完整提交消息：\n{}",
+                feedback.trim()
+            )
+        } else {
+            format!(
+                "Revise the commit message above according to the following feedback. \
+                 Keep the same format requirements and reply with the complete revised \
+                 commit message only:\n{}",
+                feedback.trim()
+            )
+        };
+        self.converse(conversation, &prompt).await
+    }
+
+    /// 清理生成的消息并按提交规范校验；不合规时请模型按具体问题修正一次，
+    /// 仍不合规再在本地修正
+    pub async fn conform(&self, message: &str, convention: &Convention) -> Result<String> {
+        let message = postprocess::clean(message);
+        let problems = candidates::check(&message, convention);
+        if problems.is_empty() {
+            return Ok(message);
+        }
+
+        let repaired = match self
+            .generate_message(&self.repair_prompt(&message, &problems, convention.kind))
+            .await
+        {
+            Ok(repaired) => postprocess::clean(&repaired),
+            Err(e) => {
+                eprintln!("修正提交消息失败: {}", e);
+                message
+            }
+        };
+        if candidates::check(&repaired, convention).is_empty() {
+            return Ok(repaired);
+        }
+
+        let fixed = postprocess::fix_up(&repaired, convention);
+        let remaining = candidates::check(&fixed, convention);
+        if !remaining.is_empty() {
+            eprintln!(
+                "warning: the message still breaks the convention: {}",
+                remaining.join("; ")
+            );
+        }
+        Ok(fixed)
+    }
+
+    /// 列出具体问题，请模型修正消息
+    fn repair_prompt(&self, message: &str, problems: &[String], kind: MessageKind) -> String {
+        let problems: Vec<String> = problems.iter().map(|p| format!("- {}", p)).collect();
+        if prompts::is_chinese(&self.config.language) {
+            let kind = match kind {
+                MessageKind::Commit => "提交消息",
+                MessageKind::Tag => "标签消息",
+            };
+            format!(
+                "下面的{kind}不符合规范：\n{}\n\n{kind}：\n{}\n\n\
+                 请只修正这些问题，保持原意，只输出修正后的完整{kind}。",
+                problems.join("\n"),
+                message
+            )
+        } else {
+            let (kind, title) = match kind {
+                MessageKind::Commit => ("commit message", "Commit message"),
+                MessageKind::Tag => ("tag message", "Tag message"),
+            };
+            format!(
+                "The {kind} below breaks these rules:\n{}\n\n{title}:\n{}\n\n\
+                 Fix only these problems, keep the meaning, and reply with the complete \
+                 corrected {kind} only.",
+                problems.join("\n"),
+                message
+            )
+        }
+    }
+
+    async fn send_with_retries(&self, prompt: &str, history: &[Message]) -> Result<String> {
         const MAX_RETRIES: u32 = 3;
 
         let mut retries = 0;
 
         loop {
-            match self.try_generate_message(prompt).await {
+            match self.try_generate_message(prompt, history).await {
                 Ok(response) => return Ok(response),
                 Err(e) if retries < MAX_RETRIES => {
                     retries += 1;
@@ -56,26 +378,32 @@ impl MessageGenerator {
         }
     }
 
-    async fn try_generate_message(&self, prompt: &str) -> Result<String> {
+    async fn try_generate_message(&self, prompt: &str, history: &[Message]) -> Result<String> {
         timeout(
             Duration::from_secs(30),
-            self.create_and_send_request(prompt),
+            self.create_and_send_request(prompt, history),
         )
         .await
         .context("请求超时")?
         .context("生成消息失败")
     }
 
-    async fn create_and_send_request(&self, prompt: &str) -> Result<String> {
-        let client = openai::ClientBuilder::new(&self.api_key)
-            .base_url(&self.base_url)
-            .build()
-            .context("创建LLM客户端失败")?;
-
-        let model = client.completion_model(&self.model);
-        let agent = AgentBuilder::new(model).build();
+    async fn backend(&self) -> Result<&Backend> {
+        self.backend
+            .get_or_try_init(|| async {
+                // 读取密钥环或执行命令可能阻塞
+                let llm = self.config.llm.clone();
+                let api_key = tokio::task::spawn_blocking(move || llm.resolved_api_key())
+                    .await
+                    .context("解析 API Key 失败")??;
+                Backend::new(&self.config.llm, &api_key)
+            })
+            .await
+    }
 
-        let response = agent.prompt(prompt).await.context("获取LLM响应失败")?;
+    async fn create_and_send_request(&self, prompt: &str, history: &[Message]) -> Result<String> {
+        let model = self.config.llm.resolved_model()?;
+        let response = self.backend().await?.chat(&model, prompt, history).await?;
 
         // 清理响应内容，移除可能的markdown代码块标记
         let cleaned_response = response
@@ -92,31 +420,40 @@ impl MessageGenerator {
         &self,
         commits: &[super::git::CommitInfo],
     ) -> Result<String> {
-        let commit_descriptions: Vec<String> = commits
-            .iter()
-            .map(|c| format!("- {}: {} (by {})", &c.sha[..8], c.message, c.author))
-            .collect();
-
-        let prompt = format!(
-            "基于以下git提交记录，生成一个简洁的变更日志摘要。请按以下格式组织内容：
-
-### 新增功能
-- 新增的功能描述
+        let prompt = Prompt::for_config("changelog", &self.config)?;
+        let (commits_list, summarized) = self.commit_list(commits).await?;
+        let heading = match (prompt.is_chinese(), summarized) {
+            (true, false) => "提交记录：",
+            (true, true) => "提交记录过多，以下是分组整理的摘要：",
+            (false, false) => "Commits:",
+            (false, true) => "There are too many commits to list; summaries of groups of them:",
+        };
 
-### 修复
-- 修复的问题描述
-
-### 改进
-- 其他改进描述
+        let mut variables = Variables::new();
+        variables.set("commits", commits_list.as_str()).set(
+            INFORMATION_VARIABLE,
+            format!("{}\n{}", heading, commits_list),
+        );
 
-请确保描述简洁明了，避免技术细节。
+        self.generate_message(&self.render(&prompt, &variables)?)
+            .await
+    }
 
-提交记录：
-{}",
-            commit_descriptions.join("\n")
-        );
+    /// 每个提交一行的列表；超出 token 上限时先分组摘要，此时第二个值为 true
+    async fn commit_list(&self, commits: &[super::git::CommitInfo]) -> Result<(String, bool)> {
+        let commit_descriptions: Vec<String> = commits
+            .iter()
+            .map(|c| format!("- {}: {} (by {})", &c.sha[..8], c.message.trim(), c.author))
+            .collect();
+        let commits_list = commit_descriptions.join("\n");
 
-        self.generate_message(&prompt).await
+        let budget = self.config.token_budget();
+        if compact::estimate_tokens(&commits_list) > budget {
+            let groups = compact::split_by_budget(&commit_descriptions, budget);
+            let summary = self.summarize(&groups, SummaryKind::Commits).await?;
+            return Ok((summary, true));
+        }
+        Ok((commits_list, false))
     }
 
     /// 为暂存的更改生成提交消息
@@ -126,46 +463,376 @@ impl MessageGenerator {
         staged_files: &[super::git::StagedFile],
         custom_prompt: Option<&str>,
     ) -> Result<String> {
+        let prompt = self
+            .commit_prompt(diff, staged_files, custom_prompt)
+            .await?;
+        self.generate_message(&prompt).await
+    }
+
+    /// 渲染提交消息的提示词，可作为对话的第一条消息
+    pub async fn commit_prompt(
+        &self,
+        diff: &str,
+        staged_files: &[super::git::StagedFile],
+        custom_prompt: Option<&str>,
+    ) -> Result<String> {
+        let prompt = self.resolve_prompt("commit", custom_prompt)?;
+        let chinese = prompt.is_chinese();
+
         let files_list: Vec<String> = staged_files
             .iter()
             .map(|f| format!("{} ({})", f.path, f.status))
             .collect();
+        let files_list = files_list.join("\n");
 
-        let base_prompt = custom_prompt.unwrap_or(
-            "基于以下代码更改生成一个简洁的git提交消息。请遵循常规提交规范（Conventional Commits）。
+        // 差异过大时按 token 上限精简，并告诉模型省略了什么
+        let budget = self.config.token_budget();
+        let compaction = compact::compact_diff(diff, budget);
+        let parts = if compaction.exceeds(budget) {
+            compact::split_diff(diff, budget)
+        } else {
+            Vec::new()
+        };
 
-格式：<type>: <description>
+        let (files_heading, diff_heading) = if chinese {
+            ("文件更改：", "代码差异：")
+        } else {
+            ("Changed files:", "Diff:")
+        };
+        let mut information = format!("{files_heading}\n{files_list}\n\n{diff_heading}\n");
 
-类型包括：feat, fix, docs, style, refactor, test, chore
+        let (diff, elided) = if parts.is_empty() {
+            let elided = elision_note(&compaction, chinese);
+            let diff = compaction.diff.trim_end().to_string();
+            if !elided.is_empty() {
+                information.push_str(&elided);
+                information.push('\n');
+            }
+            information.push_str(&format!("```diff\n{}\n```", diff));
+            (diff, elided)
+        } else {
+            // 精简后仍然超出上限：先分段摘要，再用摘要生成提交消息
+            let summary = self.summarize(&parts, SummaryKind::Diff).await?;
+            let elided = if chinese {
+                format!(
+                    "注意：代码差异过大（约 {} tokens），以下不是原始差异，而是分 {} 段生成的变更摘要。",
+                    compaction.original_tokens,
+                    parts.len()
+                )
+            } else {
+                format!(
+                    "Note: the diff is too large (about {} tokens); below are summaries of its {} parts instead of the diff itself.",
+                    compaction.original_tokens,
+                    parts.len()
+                )
+            };
+            information.push_str(&format!("{}\n{}", elided, summary));
+            (summary, elided)
+        };
 
-更改内容："
-        );
+        let mut variables = Variables::new();
+        variables
+            .set("files", files_list)
+            .set("diff", diff)
+            .set("elided", elided)
+            .set(INFORMATION_VARIABLE, information);
 
-        let prompt = format!(
-            "{base_prompt}\n\n文件更改：\n{}\n\n代码差异：\n{diff}",
-            files_list.join("\n")
-        );
+        self.render(&prompt, &variables)
+    }
 
-        self.generate_message(&prompt).await
+    /// 分层摘要：并发（受 `parallelism` 限制）为每一段生成摘要，
+    /// 合并后仍超出 token 上限时继续对摘要分组再摘要
+    pub async fn summarize(&self, parts: &[String], kind: SummaryKind) -> Result<String> {
+        let budget = self.config.token_budget();
+        let mut summaries = self.summarize_parts(parts, kind).await?;
+
+        loop {
+            let joined = summaries.join("\n\n");
+            if summaries.len() <= 1 || compact::estimate_tokens(&joined) <= budget {
+                return Ok(joined);
+            }
+            let groups = compact::split_by_budget(&summaries, budget);
+            if groups.len() >= summaries.len() {
+                // 每段摘要本身已接近上限，无法再合并
+                return Ok(joined);
+            }
+            summaries = self
+                .summarize_parts(&groups, SummaryKind::Summaries)
+                .await?;
+        }
+    }
+
+    /// 按输入顺序返回每一段的摘要
+    async fn summarize_parts(&self, parts: &[String], kind: SummaryKind) -> Result<Vec<String>> {
+        let chinese = prompts::is_chinese(&self.config.language);
+        let prompts: Vec<String> = parts
+            .iter()
+            .map(|part| kind.prompt(part, chinese, &self.config.language))
+            .collect();
+
+        stream::iter(prompts.iter().map(|prompt| self.generate_message(prompt)))
+            .buffered(self.config.parallelism())
+            .try_collect()
+            .await
+            .context("分段摘要失败")
     }
 
     /// 为标签生成消息
     pub async fn generate_tag_message(
         &self,
         tag_name: &str,
-        commit_info: &super::git::CommitInfo,
+        release: &TagRelease<'_>,
         custom_prompt: Option<&str>,
     ) -> Result<String> {
-        let base_prompt = custom_prompt
-            .unwrap_or("为git标签生成一个有意义的消息。消息应该简洁地描述这个标签代表的内容。");
-
-        let prompt = format!(
-            "{base_prompt}\n\n标签名称：{tag_name}\n提交信息：{}\n作者：{}\n提交SHA：{}",
-            commit_info.message,
-            commit_info.author,
-            &commit_info.sha[..8]
-        );
-
+        let prompt = self.tag_prompt(tag_name, release, custom_prompt).await?;
         self.generate_message(&prompt).await
     }
+
+    /// 渲染标签消息的提示词：概括上一个标签以来的全部提交、破坏性变更和贡献者
+    pub async fn tag_prompt(
+        &self,
+        tag_name: &str,
+        release: &TagRelease<'_>,
+        custom_prompt: Option<&str>,
+    ) -> Result<String> {
+        let prompt = self.resolve_prompt("tag", custom_prompt)?;
+        let chinese = prompt.is_chinese();
+        let (commits_list, summarized) = self.commit_list(release.commits).await?;
+        let breaking = release.breaking_changes();
+        let contributors = release
+            .contributors()
+            .iter()
+            .map(|(name, count)| format!("{} ({})", name, count))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let mut information = if chinese {
+            format!(
+                "标签名称：{tag_name}\n上一个标签：{}\n",
+                release.previous.unwrap_or("无（首次发布）")
+            )
+        } else {
+            format!(
+                "Tag name: {tag_name}\nPrevious tag: {}\n",
+                release.previous.unwrap_or("none (first release)")
+            )
+        };
+        let since = match (chinese, release.previous.is_some()) {
+            (true, true) => "自上一个标签以来",
+            (true, false) => "首次发布",
+            (false, true) => "since the previous tag",
+            (false, false) => "in this first release",
+        };
+        let heading = match (chinese, summarized) {
+            (true, false) => format!("{}的 {} 个提交：", since, release.commits.len()),
+            (true, true) => format!(
+                "{}有 {} 个提交，以下是分组整理的摘要：",
+                since,
+                release.commits.len()
+            ),
+            (false, false) => format!("{} commit(s) {}:", release.commits.len(), since),
+            (false, true) => format!(
+                "{} commits {}; summaries of groups of them:",
+                release.commits.len(),
+                since
+            ),
+        };
+        information.push_str(&format!("\n{}\n{}\n", heading, commits_list));
+        // 破坏性变更需要在标签消息中特别说明
+        if !breaking.is_empty() {
+            let heading = if chinese {
+                "破坏性变更："
+            } else {
+                "Breaking changes:"
+            };
+            information.push_str(&format!("\n{}\n- {}\n", heading, breaking.join("\n- ")));
+        }
+        if !contributors.is_empty() {
+            let heading = if chinese {
+                "贡献者："
+            } else {
+                "Contributors:"
+            };
+            information.push_str(&format!("\n{} {}\n", heading, contributors));
+        }
+
+        let mut variables = Variables::new();
+        variables
+            .set("tag_name", tag_name)
+            .set("previous_tag", release.previous.unwrap_or(""))
+            .set("commits", commits_list.as_str())
+            .set("breaking_changes", breaking.join("\n"))
+            .set("contributors", contributors)
+            .set(INFORMATION_VARIABLE, information.trim_end());
+
+        self.render(&prompt, &variables)
+    }
+}
+
+/// 一个标签所发布的内容：上一个标签以及两者之间的提交
+#[derive(Debug, Clone, Copy)]
+pub struct TagRelease<'a> {
+    /// 上一个标签；首次发布时为 None
+    pub previous: Option<&'a str>,
+    /// 自上一个标签以来的提交，新提交在前
+    pub commits: &'a [super::git::CommitInfo],
+}
+
+impl TagRelease<'_> {
+    /// 各提交中的破坏性变更说明
+    pub fn breaking_changes(&self) -> Vec<String> {
+        self.commits
+            .iter()
+            .filter_map(|commit| {
+                let parsed = ConventionalCommit::parse_lenient(&commit.message).ok()?;
+                parsed
+                    .breaking_description()
+                    .map(|description| format!("{} ({})", description, &commit.sha[..8]))
+            })
+            .collect()
+    }
+
+    /// 作者及 Co-authored-by 中的贡献者和各自的提交数，按提交数从多到少排列
+    pub fn contributors(&self) -> Vec<(String, usize)> {
+        let mut counts: Vec<(String, usize)> = Vec::new();
+        for commit in self.commits {
+            let co_authors = commit.message.lines().filter_map(|line| {
+                let (token, value) = line.split_once(':')?;
+                token
+                    .trim()
+                    .eq_ignore_ascii_case("Co-authored-by")
+                    .then(|| value.split('<').next().unwrap_or("").trim())
+            });
+            let mut names: Vec<&str> = std::iter::once(commit.author.as_str())
+                .chain(co_authors)
+                .filter(|name| !name.is_empty())
+                .collect();
+            names.sort_unstable();
+            names.dedup();
+            for name in names {
+                match counts.iter_mut().find(|(known, _)| known == name) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((name.to_string(), 1)),
+                }
+            }
+        }
+        counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        counts
+    }
+}
+
+/// 分段摘要的内容类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SummaryKind {
+    /// 一部分文件的代码差异
+    Diff,
+    /// 一组提交记录
+    Commits,
+    /// 上一层生成的摘要
+    Summaries,
+}
+
+impl SummaryKind {
+    fn prompt(&self, part: &str, chinese: bool, language: &str) -> String {
+        let instruction = match (self, chinese) {
+            (SummaryKind::Diff, true) => {
+                "下面是一次提交中部分文件的代码差异。请用几条简短的要点概括这些变更做了什么，\
+                 能看出原因时也写上。不要写成提交消息。"
+            }
+            (SummaryKind::Commits, true) => {
+                "下面是一部分提交记录。请按新增、修复、变更等类别，用简短的要点概括其中值得写入变更日志的内容，\
+                 并在每个要点末尾用括号保留对应提交的短哈希。"
+            }
+            (SummaryKind::Summaries, true) => {
+                "下面是同一批变更中几部分的摘要。请把它们合并成更精简的要点，保留重要信息。"
+            }
+            (SummaryKind::Diff, false) => {
+                "Below is the diff of some of the files in one commit. Summarize what these \
+                 changes do, and why if apparent, in a few short bullet points. Do not write a \
+                 commit message."
+            }
+            (SummaryKind::Commits, false) => {
+                "Below are some of the commits of a release. Summarize what is worth a changelog \
+                 entry in short bullet points grouped as added, fixed, changed and so on. End \
+                 each point with the short hashes of its commits in parentheses."
+            }
+            (SummaryKind::Summaries, false) => {
+                "Below are summaries of several parts of the same changes. Merge them into \
+                 fewer, shorter bullet points and keep the important details."
+            }
+        };
+
+        let language_note = if chinese {
+            String::new()
+        } else {
+            format!(" Answer in \"{}\".", language)
+        };
+        match self {
+            SummaryKind::Diff => format!("{instruction}{language_note}\n\n```diff\n{part}\n```"),
+            _ => format!("{instruction}{language_note}\n\n{part}"),
+        }
+    }
+}
+
+/// 说明精简差异时省略了哪些内容；差异完整时为空
+fn elision_note(compaction: &Compaction, chinese: bool) -> String {
+    if !compaction.is_partial() {
+        return String::new();
+    }
+
+    let sections = if chinese {
+        [
+            ("未包含的锁文件、生成文件或第三方代码", &compaction.dropped),
+            ("只保留代码块标题的文件", &compaction.collapsed),
+            ("只保留增删行数的文件", &compaction.stats_only),
+        ]
+    } else {
+        [
+            (
+                "Lockfiles, generated or vendored files left out",
+                &compaction.dropped,
+            ),
+            ("Files reduced to hunk headers", &compaction.collapsed),
+            ("Files reduced to line counts", &compaction.stats_only),
+        ]
+    };
+
+    let mut note = if chinese {
+        format!(
+            "注意：代码差异过大（约 {} tokens），以下差异经过精简，并不完整。",
+            compaction.original_tokens
+        )
+    } else {
+        format!(
+            "Note: the diff is too large (about {} tokens), so it was shortened and is incomplete.",
+            compaction.original_tokens
+        )
+    };
+    let separator = if chinese { "：" } else { ": " };
+    for (heading, paths) in sections {
+        if !paths.is_empty() {
+            note.push_str(&format!("\n- {}{}{}", heading, separator, paths.join(", ")));
+        }
+    }
+    note
+}
+
+/// 来自配置的通用模板变量
+fn base_variables(config: &Config) -> Variables {
+    let mut variables = Variables::new();
+    variables
+        .set("language", config.language.as_str())
+        .set("emoji", config.emoji.to_string())
+        .set("multi_line", config.multi_line.to_string())
+        .set("types", config.types.join(", "))
+        .set("scopes", config.scopes.join(", "))
+        .set(
+            "max_length",
+            config
+                .lint
+                .max_header_length
+                .unwrap_or(crate::lint::DEFAULT_HEADER_LENGTH)
+                .to_string(),
+        );
+    variables
 }
@@ -14,13 +14,7 @@
 use anyhow::Result;
 use clap::Parser;
 
-mod changelog;
-mod cli;
-mod config;
-mod git;
-mod llm;
-
-use cli::{Cli, Commands};
+use git_automessage::cli::{self, Cli, Commands};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -28,16 +22,28 @@ async fn main() -> Result<()> {
 
     match cli.command {
         Commands::Commit(args) => {
-            cli::handle_commit(args).await?;
+            cli::handle_commit(args, &cli.overrides).await?;
         }
         Commands::Tag(args) => {
-            cli::handle_tag(args).await?;
+            cli::handle_tag(args, &cli.overrides).await?;
         }
         Commands::Changelog(args) => {
-            cli::handle_changelog(args).await?;
+            cli::handle_changelog(args, &cli.overrides).await?;
         }
         Commands::Config(args) => {
-            cli::handle_config(args).await?;
+            cli::handle_config(args, &cli.overrides).await?;
+        }
+        Commands::Hook(args) => {
+            cli::handle_hook(args, &cli.overrides).await?;
+        }
+        Commands::Lint(args) => {
+            cli::handle_lint(args, &cli.overrides).await?;
+        }
+        Commands::Reword(args) => {
+            cli::handle_reword(args, &cli.overrides).await?;
+        }
+        Commands::Release(args) => {
+            cli::handle_release(args, &cli.overrides).await?;
         }
     }
 
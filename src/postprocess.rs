@@ -0,0 +1,232 @@
+//     _         _        __  __
+//    / \  _   _| |_ ___ |  \/  | ___  ___ ___  __ _  __ _  ___
+//   / _ \| | | | __/ _ \| |\/| |/ _ \/ __/ __|/ _` |/ _` |/ _ \
+//  / ___ \ |_| | || (_) | |  | |  __/\__ \__ \ (_| | (_| |  __/
+// /_/   \_\__,_|\__\___/|_|  |_|\___||___/___/\__,_|\__, |\___|
+//                                                   |___/
+//
+// Author: Sidney Zhang <zly@lyzhang.me>
+// Date: 2025-08-05
+// License: MIT
+//
+// Cleaning up generated messages and fixing them up to the convention.
+
+use crate::candidates::Convention;
+use crate::conventional::{self, ConventionalCommit};
+
+/// Column the body is wrapped at, as git recommends.
+pub const BODY_WIDTH: usize = 72;
+
+/// Strip what models put around the message and wrap its body.
+pub fn clean(message: &str) -> String {
+    wrap_body(&strip_preamble(message), BODY_WIDTH)
+}
+
+/// Remove markdown fences, surrounding quotes and introductions such as
+/// "Here is your commit message:".
+pub fn strip_preamble(message: &str) -> String {
+    let mut lines: Vec<&str> = message.trim().lines().collect();
+
+    while let Some(first) = lines.first() {
+        let line = first.trim();
+        if line.is_empty() || line.starts_with("```") || is_preamble(line) {
+            lines.remove(0);
+        } else {
+            break;
+        }
+    }
+    while let Some(last) = lines.last() {
+        let line = last.trim();
+        if line.is_empty() || line.starts_with("```") {
+            lines.pop();
+        } else {
+            break;
+        }
+    }
+
+    let message = lines.join("\n");
+    let message = message.trim();
+    for (open, close) in [("\"", "\""), ("'", "'"), ("`", "`"), ("“", "”")] {
+        if let Some(inner) = message
+            .strip_prefix(open)
+            .and_then(|m| m.strip_suffix(close))
+            && !inner.contains(open)
+        {
+            return inner.trim().to_string();
+        }
+    }
+    message.to_string()
+}
+
+/// Whether `line` only introduces the message, e.g. "Commit message:" or
+/// "以下是提交消息：".
+fn is_preamble(line: &str) -> bool {
+    let Some(intro) = line.strip_suffix(':').or_else(|| line.strip_suffix('：')) else {
+        return false;
+    };
+    // A conventional header such as `feat:` with nothing after it is still
+    // not an introduction.
+    if !intro.contains(char::is_whitespace) && intro.is_ascii() {
+        return false;
+    }
+
+    let intro = intro.to_lowercase();
+    [
+        "commit message",
+        "tag message",
+        "提交消息",
+        "提交信息",
+        "标签消息",
+    ]
+    .iter()
+    .any(|phrase| intro.contains(phrase))
+        || ["here is", "here's", "sure", "以下是", "好的"]
+            .iter()
+            .any(|start| intro.starts_with(start))
+}
+
+/// Wrap body lines longer than `width`. The subject, footers, indented
+/// lines and fenced blocks are left alone; list items get a hanging indent.
+pub fn wrap_body(message: &str, width: usize) -> String {
+    let mut lines = message.lines();
+    let Some(subject) = lines.next() else {
+        return String::new();
+    };
+
+    let mut wrapped = vec![subject.to_string()];
+    let mut in_fence = false;
+    for line in lines {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        if in_fence
+            || line.chars().count() <= width
+            || line.starts_with(char::is_whitespace)
+            || conventional::is_footer(line)
+        {
+            wrapped.push(line.to_string());
+            continue;
+        }
+        let indent = " ".repeat(list_marker_width(line));
+        wrapped.extend(wrap_line(line, width, &indent));
+    }
+    wrapped.join("\n")
+}
+
+/// Width of a leading `- `, `* ` or `1. ` list marker, or 0.
+fn list_marker_width(line: &str) -> usize {
+    if line.starts_with("- ") || line.starts_with("* ") {
+        return 2;
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 && line[digits..].starts_with(". ") {
+        digits + 2
+    } else {
+        0
+    }
+}
+
+/// Greedy word wrap; words longer than `width`, such as URLs, are kept whole.
+fn wrap_line(line: &str, width: usize, indent: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in line.split_whitespace() {
+        let length = current.chars().count();
+        if !current.trim().is_empty() && length + 1 + word.chars().count() > width {
+            lines.push(std::mem::replace(&mut current, indent.to_string()));
+        } else if !current.trim().is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    if !current.trim().is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+/// Fix `message` up to `convention` without asking the model again: repair
+/// or add the type, drop disallowed scopes, shorten the subject and
+/// separate the body.
+pub fn fix_up(message: &str, convention: &Convention) -> String {
+    let message = strip_preamble(message);
+    let (subject, body) = match message.split_once('\n') {
+        Some((subject, body)) => (subject.trim(), Some(body.trim())),
+        None => (message.trim(), None),
+    };
+    let subject = subject.trim_end_matches(['.', '。']);
+
+    let subject = if convention.conventional {
+        fix_header(subject, convention)
+    } else {
+        subject.to_string()
+    };
+    let subject = shorten(&subject, convention.max_length);
+
+    let mut fixed = subject;
+    if let Some(body) = body.filter(|body| !body.is_empty()) {
+        fixed.push_str("\n\n");
+        fixed.push_str(body);
+    }
+    wrap_body(&fixed, BODY_WIDTH)
+}
+
+fn fix_header(subject: &str, convention: &Convention) -> String {
+    let allowed =
+        |kind: &str| convention.types.is_empty() || convention.types.iter().any(|t| t == kind);
+
+    let mut commit = match ConventionalCommit::parse(subject) {
+        Ok(commit) => commit,
+        // Models sometimes capitalize the type or drop the space after ':'.
+        Err(_) => match subject.split_once(':') {
+            Some((head, description)) => {
+                let retry = format!("{}: {}", head.trim().to_lowercase(), description.trim());
+                match ConventionalCommit::parse(&retry) {
+                    Ok(commit) => commit,
+                    Err(_) => return format!("{}: {}", fallback_type(convention), subject),
+                }
+            }
+            None => return format!("{}: {}", fallback_type(convention), subject),
+        },
+    };
+
+    if !allowed(&commit.kind) {
+        let lower = commit.kind.to_lowercase();
+        commit.kind = if allowed(&lower) {
+            lower
+        } else {
+            fallback_type(convention).to_string()
+        };
+    }
+    if let Some(scope) = &commit.scope
+        && !convention.scopes.is_empty()
+        && !convention.scopes.contains(scope)
+    {
+        commit.scope = None;
+    }
+    commit.header()
+}
+
+/// `chore` when allowed, otherwise the first allowed type.
+fn fallback_type(convention: &Convention) -> &str {
+    if convention.types.is_empty() || convention.types.iter().any(|t| t == "chore") {
+        "chore"
+    } else {
+        &convention.types[0]
+    }
+}
+
+/// Cut `subject` to `max_length` characters, at a word boundary if the
+/// description has one. 0 means no limit.
+fn shorten(subject: &str, max_length: usize) -> String {
+    if max_length == 0 || subject.chars().count() <= max_length {
+        return subject.to_string();
+    }
+    let cut: String = subject.chars().take(max_length).collect();
+    let head_end = subject.find(": ").map_or(0, |i| i + 2);
+    let cut = match cut.rfind(' ') {
+        Some(space) if space > head_end => &cut[..space],
+        _ => cut.as_str(),
+    };
+    cut.trim_end_matches([',', ';', ':', ' ']).to_string()
+}
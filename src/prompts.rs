@@ -11,59 +11,533 @@
 //
 // generate llm prompt
 
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
 use crate::config::Config;
 
-fn get_prompt_config() -> Config {
-    let config = Config::load().map_err(|e| {
-        eprintln!("Error loading config: {:?}", e);
-    }).unwrap();
-    config
-}
+/// 内置模板中代码变更信息的变量名
+pub const INFORMATION_VARIABLE: &str = "Code_Change_Informations";
+
+/// 模板中代码变更信息的占位符
+pub const PLACEHOLDER: &str = "{Code_Change_Informations}";
+
+/// 模板可用的变量及说明
+pub const KNOWN_VARIABLES: &[(&str, &str)] = &[
+    ("diff", "代码差异"),
+    ("files", "变更的文件列表"),
+    ("branch", "当前分支名称"),
+    ("recent_commits", "最近的提交标题"),
+    ("language", "输出语言"),
+    ("max_length", "提交标题的最大长度"),
+    ("emoji", "是否使用 gitmoji（可用于条件判断）"),
+    ("multi_line", "是否生成多行消息（可用于条件判断）"),
+    ("tag_name", "标签名称"),
+    ("commits", "提交记录列表"),
+    ("previous_tag", "上一个标签名称，首次发布时为空"),
+    ("breaking_changes", "破坏性变更说明，每行一条"),
+    ("contributors", "贡献者及其提交数"),
+    ("types", "允许的提交类型，逗号分隔"),
+    ("scopes", "允许的提交范围，逗号分隔"),
+    ("elided", "代码差异过大时被省略内容的说明"),
+    (INFORMATION_VARIABLE, "整理好的代码变更信息（内置模板使用）"),
+];
+
+/// 代表代码变更内容的变量，模板中一个都没有引用时会自动附加变更信息
+const CONTENT_VARIABLES: &[&str] = &["diff", "files", "commits", INFORMATION_VARIABLE];
+
+const EN_COMMIT: &str = include_str!("../prompts/en/COMMIT");
+const EN_GITMOJI: &str = include_str!("../prompts/en/GITMOJI");
+const EN_TAG: &str = include_str!("../prompts/en/TAG");
+const EN_CHANGELOG: &str = include_str!("../prompts/en/CHANGELOG");
+const ZH_COMMIT: &str = include_str!("../prompts/zh-CN/COMMIT");
+const ZH_GITMOJI: &str = include_str!("../prompts/zh-CN/GITMOJI");
+const ZH_TAG: &str = include_str!("../prompts/zh-CN/TAG");
+const ZH_CHANGELOG: &str = include_str!("../prompts/zh-CN/CHANGELOG");
 
 pub struct Prompt {
-    prompt: String
+    prompt: String,
+    chinese: bool,
 }
 
 impl Prompt {
-    pub fn from_str(prompt: &str) -> Self {
-        Self { prompt: prompt.to_string() }
-    }
-    pub fn new(prompt_type: &str) -> Self {
-        match prompt_type {
-            "tag" => Self::tag_prompt(),
-            "commit" => Self::commit_prompt(),
-            "changelog" => Self::changelog_prompt(),
-            _ => {
-                Self::default_prompt()
-            },
+    pub fn from_text(prompt: &str) -> Self {
+        Self {
+            prompt: prompt.to_string(),
+            chinese: false,
+        }
+    }
+    pub fn new(prompt_type: &str, config: &Config) -> Self {
+        let chinese = is_chinese(&config.language);
+        let mut prompt = match prompt_type {
+            "tag" => Self::tag_prompt(chinese),
+            "commit" => Self::commit_prompt(chinese, config.emoji),
+            "changelog" => Self::changelog_prompt(chinese),
+            _ => Self::default_prompt(),
+        };
+        prompt.chinese = chinese;
+        if prompt_type == "commit" {
+            prompt.add_allowed_values(config);
+        }
+        // 内置模板只有中英文两种，其他语言要求模型用该语言输出
+        if !chinese && !is_english(&config.language) && !prompt.prompt.is_empty() {
+            prompt.prompt.push_str(&format!(
+                "\nWrite the response in the language identified by \"{}\".\n",
+                config.language
+            ));
+        }
+        prompt
+    }
+    /// 按配置选择模板：用户模板文件或 `prompt` 字段优先，否则使用内置模板
+    pub fn for_config(prompt_type: &str, config: &Config) -> Result<Self> {
+        let user_template = match config.templates.path_for(prompt_type) {
+            Some(path) => Some(load_template_file(path)?),
+            None if prompt_type == "commit" => config.prompt.clone(),
+            None => None,
+        };
+
+        match user_template {
+            Some(template) => Ok(Self::user_template(&template, config)),
+            None => Ok(Self::new(prompt_type, config)),
+        }
+    }
+    /// 使用用户提供的模板文本
+    pub fn user_template(template: &str, config: &Config) -> Self {
+        Self {
+            prompt: template.to_string(),
+            chinese: is_chinese(&config.language),
         }
     }
     pub fn get_prompt(&self) -> &str {
         &self.prompt
     }
+    /// 渲染模板；模板没有引用任何变更内容时在末尾附加代码变更信息
+    pub fn render(&self, variables: &Variables) -> Result<String> {
+        let template = Template::parse(&self.prompt)?;
+        let mut rendered = template.render(variables);
+
+        let used = template.variables();
+        if !CONTENT_VARIABLES.iter().any(|name| used.contains(*name))
+            && let Some(information) = variables.get(INFORMATION_VARIABLE)
+        {
+            rendered = format!("{}\n\n{}", rendered.trim_end(), information.trim_end());
+        }
+
+        Ok(rendered)
+    }
+    /// 模板是否为中文（用于选择代码变更信息中的标签文字）
+    pub fn is_chinese(&self) -> bool {
+        self.chinese
+    }
+    /// 在代码变更信息之前加入用户的额外要求
+    pub fn with_instructions(mut self, instructions: Option<&str>) -> Self {
+        let Some(instructions) = instructions.map(str::trim).filter(|s| !s.is_empty()) else {
+            return self;
+        };
+        let heading = if self.chinese {
+            "## 额外要求"
+        } else {
+            "## Additional Requirements"
+        };
+        let section = format!("{}\n{}\n\n", heading, instructions);
+        self.prompt = insert_before_information(&self.prompt, &section, self.chinese);
+        self
+    }
+    // 私有方法 : 限定可用的提交类型和范围
+    fn add_allowed_values(&mut self, config: &Config) {
+        if config.types.is_empty() && config.scopes.is_empty() {
+            return;
+        }
+        let mut section = String::from(if self.chinese {
+            "## 允许的类型与范围\n"
+        } else {
+            "## Allowed Types and Scopes\n"
+        });
+        if !config.types.is_empty() {
+            section.push_str(&if self.chinese {
+                format!("- 类型只能是以下之一：{}\n", config.types.join(", "))
+            } else {
+                format!(
+                    "- Use only one of these types: {}\n",
+                    config.types.join(", ")
+                )
+            });
+        }
+        if !config.scopes.is_empty() {
+            section.push_str(&if self.chinese {
+                format!(
+                    "- 范围只能是以下之一，无法匹配时省略范围：{}\n",
+                    config.scopes.join(", ")
+                )
+            } else {
+                format!(
+                    "- Use only one of these scopes, or omit the scope: {}\n",
+                    config.scopes.join(", ")
+                )
+            });
+        }
+        section.push('\n');
+        self.prompt = insert_before_information(&self.prompt, &section, self.chinese);
+    }
     // 私有方法 ： default prompt
     fn default_prompt() -> Self {
-        Self::from_str("")
+        Self::from_text("")
     }
     // 私有方法 : tag massage prompt
-    fn tag_prompt() -> Self {
-        let config = get_prompt_config();
-        let emoji = config.emoji;
-        let mut prompt = "";
-        Self::from_str("")
+    fn tag_prompt(chinese: bool) -> Self {
+        Self::from_text(if chinese { ZH_TAG } else { EN_TAG })
     }
     // 私有方法 : commit massage prompt
-    fn commit_prompt() -> Self {
-        let config = get_prompt_config();
-        let emoji = config.emoji;
-        let mut prompt = "";
-        Self::from_str("")
+    fn commit_prompt(chinese: bool, emoji: bool) -> Self {
+        match (chinese, emoji) {
+            (true, true) => Self::from_text(ZH_GITMOJI),
+            (true, false) => Self::from_text(ZH_COMMIT),
+            // 英文 GITMOJI 只是 emoji 列表，需要并入提交规范模板
+            (false, true) => Self::from_text(&insert_before_information(
+                EN_COMMIT,
+                &format!("## Gitmoji\n{}\n\n", EN_GITMOJI.trim()),
+                false,
+            )),
+            (false, false) => Self::from_text(EN_COMMIT),
+        }
     }
     // 私有方法 : changelog massage prompt
-    fn changelog_prompt() -> Self {
-        let config = get_prompt_config();
-        let emoji = config.emoji;
-        let mut prompt = "";
-        Self::from_str("")
+    fn changelog_prompt(chinese: bool) -> Self {
+        Self::from_text(if chinese { ZH_CHANGELOG } else { EN_CHANGELOG })
+    }
+}
+
+pub(crate) fn is_chinese(language: &str) -> bool {
+    language.to_lowercase().starts_with("zh")
+}
+
+fn is_english(language: &str) -> bool {
+    language.to_lowercase().starts_with("en")
+}
+
+/// 把一段内容插入到模板的代码变更信息小节之前
+fn insert_before_information(template: &str, section: &str, chinese: bool) -> String {
+    let heading = if chinese {
+        "## 代码变更信息"
+    } else {
+        "## Code Change Information"
+    };
+    match template.find(heading) {
+        Some(pos) => format!("{}{}{}", &template[..pos], section, &template[pos..]),
+        None => format!("{}\n\n{}", template.trim_end(), section),
+    }
+}
+
+/// 读取模板文件并检查语法
+pub fn load_template_file(path: &Path) -> Result<String> {
+    let template =
+        fs::read_to_string(path).with_context(|| format!("无法读取模板文件: {:?}", path))?;
+    check_template(&template).with_context(|| format!("模板文件无效: {:?}", path))?;
+    Ok(template)
+}
+
+/// 检查模板语法以及是否使用了未知变量
+pub fn check_template(template: &str) -> Result<()> {
+    let unknown: Vec<String> = Template::parse(template)?
+        .variables()
+        .into_iter()
+        .filter(|name| !KNOWN_VARIABLES.iter().any(|(known, _)| known == name))
+        .map(|name| format!("{{{}}}", name))
+        .collect();
+
+    if !unknown.is_empty() {
+        let known: Vec<String> = KNOWN_VARIABLES
+            .iter()
+            .map(|(name, _)| format!("{{{}}}", name))
+            .collect();
+        anyhow::bail!(
+            "未知的模板变量: {}（可用变量: {}）",
+            unknown.join(", "),
+            known.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+/// 文本是否使用了模板语法（变量或条件）
+pub fn is_template(text: &str) -> bool {
+    Template::parse(text)
+        .map(|template| !template.variables().is_empty())
+        .unwrap_or(false)
+}
+
+/// 模板变量的取值
+#[derive(Debug, Clone, Default)]
+pub struct Variables {
+    values: BTreeMap<String, String>,
+}
+
+impl Variables {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, name: &str, value: impl Into<String>) -> &mut Self {
+        self.values.insert(name.to_string(), value.into());
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
     }
+
+    /// 合并另一组变量，已有的同名变量会被覆盖
+    pub fn extend(&mut self, other: &Variables) -> &mut Self {
+        for (name, value) in &other.values {
+            self.values.insert(name.clone(), value.clone());
+        }
+        self
+    }
+
+    /// 条件判断的真假：空值、`false`、`0`、`no`、`off` 为假
+    fn is_truthy(&self, name: &str) -> bool {
+        match self.get(name) {
+            None => false,
+            Some(value) => {
+                let value = value.trim();
+                !(value.is_empty()
+                    || ["false", "0", "no", "off"]
+                        .iter()
+                        .any(|f| value.eq_ignore_ascii_case(f)))
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Node {
+    Text(String),
+    Variable(String),
+    If {
+        name: String,
+        negate: bool,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+}
+
+/// 解析中尚未闭合的条件块
+struct Frame {
+    outer: Vec<Node>,
+    name: String,
+    negate: bool,
+    then: Option<Vec<Node>>,
+    line: usize,
+}
+
+enum Token {
+    Text(String),
+    Variable(String),
+    If { name: String, negate: bool },
+    Else,
+    EndIf,
+}
+
+/// 提示词模板
+///
+/// 语法：`{name}` 插入变量，`{#if name}...{#else}...{/if}` 按变量真假选择内容，
+/// `{#if !name}` 取反，`{{` 与 `}}` 输出字面量花括号。
+/// 其他花括号内容（如代码片段）按原样保留。
+#[derive(Debug)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+impl Template {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut stack: Vec<Frame> = Vec::new();
+        let mut nodes = Vec::new();
+
+        for (token, line) in tokenize(text)? {
+            match token {
+                Token::Text(text) => nodes.push(Node::Text(text)),
+                Token::Variable(name) => nodes.push(Node::Variable(name)),
+                Token::If { name, negate } => stack.push(Frame {
+                    outer: std::mem::take(&mut nodes),
+                    name,
+                    negate,
+                    then: None,
+                    line,
+                }),
+                Token::Else => {
+                    let Some(frame) = stack.last_mut() else {
+                        anyhow::bail!("第 {} 行: {{#else}} 没有对应的 {{#if}}", line);
+                    };
+                    if frame.then.is_some() {
+                        anyhow::bail!("第 {} 行: 重复的 {{#else}}", line);
+                    }
+                    frame.then = Some(std::mem::take(&mut nodes));
+                }
+                Token::EndIf => {
+                    let Some(frame) = stack.pop() else {
+                        anyhow::bail!("第 {} 行: {{/if}} 没有对应的 {{#if}}", line);
+                    };
+                    let branch = std::mem::replace(&mut nodes, frame.outer);
+                    let (then, otherwise) = match frame.then {
+                        Some(then) => (then, branch),
+                        None => (branch, Vec::new()),
+                    };
+                    nodes.push(Node::If {
+                        name: frame.name,
+                        negate: frame.negate,
+                        then,
+                        otherwise,
+                    });
+                }
+            }
+        }
+
+        if let Some(frame) = stack.last() {
+            anyhow::bail!("第 {} 行: {{#if {}}} 缺少 {{/if}}", frame.line, frame.name);
+        }
+
+        Ok(Template { nodes })
+    }
+
+    /// 模板中引用的所有变量（包括条件中的变量）
+    pub fn variables(&self) -> BTreeSet<String> {
+        let mut names = BTreeSet::new();
+        collect_variables(&self.nodes, &mut names);
+        names
+    }
+
+    pub fn render(&self, variables: &Variables) -> String {
+        let mut output = String::new();
+        render_nodes(&self.nodes, variables, &mut output);
+        output
+    }
+}
+
+fn collect_variables(nodes: &[Node], names: &mut BTreeSet<String>) {
+    for node in nodes {
+        match node {
+            Node::Text(_) => {}
+            Node::Variable(name) => {
+                names.insert(name.clone());
+            }
+            Node::If {
+                name,
+                then,
+                otherwise,
+                ..
+            } => {
+                names.insert(name.clone());
+                collect_variables(then, names);
+                collect_variables(otherwise, names);
+            }
+        }
+    }
+}
+
+fn render_nodes(nodes: &[Node], variables: &Variables, output: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Variable(name) => match variables.get(name) {
+                Some(value) => output.push_str(value),
+                None => {
+                    // 未知变量原样输出，便于发现问题；已知变量缺省为空
+                    if !KNOWN_VARIABLES.iter().any(|(known, _)| known == name) {
+                        output.push_str(&format!("{{{}}}", name));
+                    }
+                }
+            },
+            Node::If {
+                name,
+                negate,
+                then,
+                otherwise,
+            } => {
+                if variables.is_truthy(name) != *negate {
+                    render_nodes(then, variables, output);
+                } else {
+                    render_nodes(otherwise, variables, output);
+                }
+            }
+        }
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>> {
+    let mut tokens = Vec::new();
+    let mut literal = String::new();
+    let mut line = 1;
+    let mut rest = text;
+
+    let flush = |literal: &mut String, tokens: &mut Vec<(Token, usize)>, line: usize| {
+        if !literal.is_empty() {
+            tokens.push((Token::Text(std::mem::take(literal)), line));
+        }
+    };
+
+    while let Some(c) = rest.chars().next() {
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            literal.push(c);
+            rest = &rest[2..];
+            continue;
+        }
+
+        if c == '{' {
+            // 标签不跨行
+            let tag = rest[1..]
+                .find(['}', '\n', '{'])
+                .filter(|&end| rest.as_bytes()[1 + end] == b'}')
+                .map(|end| &rest[1..1 + end]);
+
+            if let Some(tag) = tag {
+                let token = if is_identifier(tag) {
+                    Some(Token::Variable(tag.to_string()))
+                } else if let Some(condition) = tag.strip_prefix("#if ") {
+                    let condition = condition.trim();
+                    let (negate, name) = match condition.strip_prefix('!') {
+                        Some(name) => (true, name.trim()),
+                        None => (false, condition),
+                    };
+                    if !is_identifier(name) {
+                        anyhow::bail!("第 {} 行: 无效的条件 {{{}}}", line, tag);
+                    }
+                    Some(Token::If {
+                        name: name.to_string(),
+                        negate,
+                    })
+                } else if tag == "#else" {
+                    Some(Token::Else)
+                } else if tag == "/if" {
+                    Some(Token::EndIf)
+                } else {
+                    None
+                };
+
+                if let Some(token) = token {
+                    flush(&mut literal, &mut tokens, line);
+                    tokens.push((token, line));
+                    rest = &rest[tag.len() + 2..];
+                    continue;
+                }
+            }
+        }
+
+        if c == '\n' {
+            line += 1;
+        }
+        literal.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+
+    flush(&mut literal, &mut tokens, line);
+    Ok(tokens)
 }
@@ -0,0 +1,387 @@
+//     _         _        __  __
+//    / \  _   _| |_ ___ |  \/  | ___  ___ ___  __ _  __ _  ___
+//   / _ \| | | | __/ _ \| |\/| |/ _ \/ __/ __|/ _` |/ _` |/ _ \
+//  / ___ \ |_| | || (_) | |  | |  __/\__ \__ \ (_| | (_| |  __/
+// /_/   \_\__,_|\__\___/|_|  |_|\___||___/___/\__,_|\__, |\___|
+//                                                   |___/
+//
+// Author: Sidney Zhang <zly@lyzhang.me>
+// Date: 2025-08-05
+// License: MIT
+//
+// Planning a release and applying it: version files, changelog, commit and tag.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::VersionFile;
+use crate::git::{DiffMode, GitRepo};
+use crate::version::{Bump, TaggedVersion, Version};
+
+/// Changelog updated when `release.changelog` is not set.
+pub const DEFAULT_CHANGELOG: &str = "CHANGELOG.md";
+
+/// A planned change to one file.
+#[derive(Debug, Clone)]
+pub struct FileChange {
+    /// Relative to the working tree.
+    pub path: PathBuf,
+    /// `None` when the file is created.
+    pub before: Option<String>,
+    pub after: String,
+    /// One line describing the change, e.g. `version 1.2.3 -> 1.3.0`.
+    pub summary: String,
+}
+
+/// Everything a release changes, worked out before anything is written.
+#[derive(Debug, Clone)]
+pub struct ReleasePlan {
+    pub previous: Option<TaggedVersion>,
+    pub version: Version,
+    pub bump: Bump,
+    /// Number of commits since `previous`.
+    pub commits: usize,
+    pub changes: Vec<FileChange>,
+    pub commit_message: String,
+    pub tag: String,
+    pub tag_message: String,
+}
+
+impl ReleasePlan {
+    /// A readable account of every planned change, for `--dry-run`.
+    pub fn describe(&self) -> String {
+        let mut text = match &self.previous {
+            Some(previous) => format!(
+                "Release {} -> {} ({} commit(s) since {}, {} bump)\n",
+                previous.version, self.version, self.commits, previous.tag, self.bump
+            ),
+            None => format!(
+                "Release {} ({} commit(s), first release)\n",
+                self.version, self.commits
+            ),
+        };
+
+        text.push_str("\nFiles:\n");
+        for change in &self.changes {
+            text.push_str(&format!(
+                "  {}: {}\n",
+                change.path.display(),
+                change.summary
+            ));
+        }
+        for change in &self.changes {
+            if let Some(section) = added_text(change.before.as_deref(), &change.after) {
+                text.push_str(&format!("\n{}:\n{}\n", change.path.display(), section));
+            }
+        }
+
+        text.push_str(&format!("\nCommit:\n{}\n", self.commit_message));
+        text.push_str(&format!(
+            "\nTag {}:\n{}\n",
+            self.tag,
+            self.tag_message.trim_end()
+        ));
+        text
+    }
+
+    /// Write the files, commit them and tag the commit. When a step fails,
+    /// the files and HEAD are put back the way they were.
+    pub fn apply(&self, repo: &GitRepo) -> Result<()> {
+        let workdir = repo.workdir()?.to_path_buf();
+        let head = repo.head_id()?;
+
+        let mut written = Vec::new();
+        let result = (|| -> Result<()> {
+            for change in &self.changes {
+                let path = workdir.join(&change.path);
+                fs::write(&path, &change.after)
+                    .with_context(|| format!("could not write {:?}", path))?;
+                written.push(change);
+            }
+            let paths: Vec<PathBuf> = self
+                .changes
+                .iter()
+                .map(|change| change.path.clone())
+                .collect();
+            repo.stage_paths(&paths)?;
+            repo.create_commit(&self.commit_message)?;
+            repo.create_annotated_tag(&self.tag, &self.tag_message, "HEAD")
+        })();
+
+        if let Err(error) = result {
+            for change in written {
+                let path = workdir.join(&change.path);
+                let _ = match &change.before {
+                    Some(before) => fs::write(&path, before),
+                    None => fs::remove_file(&path),
+                };
+            }
+            // Also when HEAD did not move: the release files may be staged.
+            if let Some(head) = head {
+                repo.reset_to(head)?;
+            }
+            return Err(error.context("release rolled back"));
+        }
+        Ok(())
+    }
+}
+
+/// Lines `after` has on top of `before`, when it only adds a block, such as
+/// a new changelog section.
+fn added_text(before: Option<&str>, after: &str) -> Option<String> {
+    let before: Vec<&str> = before.unwrap_or("").lines().collect();
+    let after: Vec<&str> = after.lines().collect();
+    if after.len() <= before.len() {
+        return None;
+    }
+    let prefix = before
+        .iter()
+        .zip(&after)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = before[prefix..]
+        .iter()
+        .rev()
+        .zip(after[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    if prefix + suffix != before.len() {
+        return None;
+    }
+    Some(after[prefix..after.len() - suffix].join("\n"))
+}
+
+/// Refuse to release on top of staged changes or with local edits to the
+/// files the release writes.
+pub fn check_clean(repo: &GitRepo, paths: &[PathBuf]) -> Result<()> {
+    if !repo.get_changed_files(DiffMode::Staged)?.is_empty() {
+        anyhow::bail!("there are staged changes; commit or unstage them before releasing");
+    }
+    let modified = repo.get_changed_files(DiffMode::Unstaged)?;
+    if let Some(file) = modified
+        .iter()
+        .find(|file| paths.iter().any(|path| Path::new(&file.path) == path))
+    {
+        anyhow::bail!(
+            "{} has uncommitted changes; commit or stash them before releasing",
+            file.path
+        );
+    }
+    Ok(())
+}
+
+/// Sets the version in a file's content, returning the old version and the
+/// new content.
+type BumpFn = fn(&str, &str) -> Option<(String, String)>;
+
+/// Manifests whose version is kept in step with the release.
+const MANIFESTS: [(&str, BumpFn); 3] = [
+    ("Cargo.toml", bump_cargo_toml),
+    ("package.json", bump_package_json),
+    ("pyproject.toml", bump_pyproject),
+];
+
+/// The highest version among the manifests in `workdir`, if any has one.
+pub fn manifest_version(workdir: &Path) -> Result<Option<Version>> {
+    let mut highest: Option<Version> = None;
+    for (name, bump) in MANIFESTS {
+        let Some(content) = read_optional(&workdir.join(name))? else {
+            continue;
+        };
+        if let Some(version) = bump(&content, "").and_then(|(old, _)| Version::parse(&old))
+            && highest.as_ref().is_none_or(|highest| version > *highest)
+        {
+            highest = Some(version);
+        }
+    }
+    Ok(highest)
+}
+
+/// Version changes for the manifests in `workdir` and the configured
+/// `files`. Manifests without a version of their own are skipped; a
+/// configured file whose pattern does not match is an error.
+pub fn version_changes(
+    workdir: &Path,
+    version: &Version,
+    files: &[VersionFile],
+) -> Result<Vec<FileChange>> {
+    let version = version.to_string();
+    let mut changes = Vec::new();
+
+    for (name, bump) in MANIFESTS {
+        let Some(content) = read_optional(&workdir.join(name))? else {
+            continue;
+        };
+        let Some((old, updated)) = bump(&content, &version) else {
+            continue;
+        };
+        let package = toml_value(&content, &["package"], "name");
+        changes.push(change(name, content, updated, &old, &version));
+
+        // Keep Cargo.lock in step, or the next build changes it.
+        if name == "Cargo.toml"
+            && let Some(package) = package
+            && let Some(lock) = read_optional(&workdir.join("Cargo.lock"))?
+            && let Some(updated_lock) = bump_cargo_lock(&lock, &package, &version)
+        {
+            changes.push(change("Cargo.lock", lock, updated_lock, &old, &version));
+        }
+    }
+
+    for file in files {
+        let pattern = Regex::new(&file.pattern)
+            .with_context(|| format!("invalid release.files pattern for {:?}", file.path))?;
+        let path = workdir.join(&file.path);
+        let content =
+            fs::read_to_string(&path).with_context(|| format!("could not read {:?}", path))?;
+        let (old, updated) = bump_pattern(&content, &pattern, &version).with_context(|| {
+            format!("{:?} has no version matching '{}'", file.path, file.pattern)
+        })?;
+        changes.push(FileChange {
+            path: file.path.clone(),
+            before: Some(content),
+            after: updated,
+            summary: format!("version {} -> {}", old, version),
+        });
+    }
+
+    Ok(changes)
+}
+
+fn change(name: &str, before: String, after: String, old: &str, new: &str) -> FileChange {
+    FileChange {
+        path: PathBuf::from(name),
+        before: Some(before),
+        after,
+        summary: format!("version {} -> {}", old, new),
+    }
+}
+
+fn read_optional(path: &Path) -> Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("could not read {:?}", path)),
+    }
+}
+
+/// Set the version of `[package]` or `[workspace.package]`. Returns the old
+/// version and the new content.
+pub fn bump_cargo_toml(content: &str, version: &str) -> Option<(String, String)> {
+    set_toml_value(
+        content,
+        &["package", "workspace.package"],
+        "version",
+        version,
+    )
+}
+
+/// Set the version of `[project]` or `[tool.poetry]`.
+pub fn bump_pyproject(content: &str, version: &str) -> Option<(String, String)> {
+    set_toml_value(content, &["project", "tool.poetry"], "version", version)
+}
+
+/// Set the top-level `"version"` of a package.json, keeping its formatting.
+pub fn bump_package_json(content: &str, version: &str) -> Option<(String, String)> {
+    let json: serde_json::Value = serde_json::from_str(content).ok()?;
+    let old = json.get("version")?.as_str()?.to_string();
+    let pattern = Regex::new(&format!(
+        r#"("version"\s*:\s*")({})(")"#,
+        regex::escape(&old)
+    ))
+    .ok()?;
+    let updated = pattern.replacen(content, 1, format!("${{1}}{}${{3}}", version));
+    Some((old, updated.into_owned()))
+}
+
+/// Set the `version` of the `[[package]]` entry named `package`.
+pub fn bump_cargo_lock(content: &str, package: &str, version: &str) -> Option<String> {
+    let name_line = format!("name = \"{}\"", package);
+    let mut in_package = false;
+    let mut found = false;
+    let mut output = String::with_capacity(content.len());
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            in_package = false;
+        }
+        if trimmed == name_line {
+            in_package = true;
+        }
+        if in_package
+            && !found
+            && let Some((_, updated)) = replace_toml_line(line, "version", version)
+        {
+            output.push_str(&updated);
+            found = true;
+            continue;
+        }
+        output.push_str(line);
+    }
+    found.then_some(output)
+}
+
+/// Replace the version captured by the first group of `pattern`.
+pub fn bump_pattern(content: &str, pattern: &Regex, version: &str) -> Option<(String, String)> {
+    let captures = pattern.captures(content)?;
+    let old = captures.get(1)?;
+    let updated = format!(
+        "{}{}{}",
+        &content[..old.start()],
+        version,
+        &content[old.end()..]
+    );
+    Some((old.as_str().to_string(), updated))
+}
+
+/// Value of a quoted `key` in the first of `sections` that has it.
+fn toml_value(content: &str, sections: &[&str], key: &str) -> Option<String> {
+    set_toml_value(content, sections, key, "").map(|(old, _)| old)
+}
+
+/// Replace the quoted `key = "..."` in one of `sections`, line by line, so
+/// comments and formatting survive.
+fn set_toml_value(
+    content: &str,
+    sections: &[&str],
+    key: &str,
+    value: &str,
+) -> Option<(String, String)> {
+    let mut section = String::new();
+    let mut old = None;
+    let mut output = String::with_capacity(content.len());
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if let Some(header) = trimmed
+            .strip_prefix('[')
+            .and_then(|rest| rest.split(']').next())
+        {
+            section = header.trim().to_string();
+        }
+        if old.is_none()
+            && sections.contains(&section.as_str())
+            && let Some((previous, updated)) = replace_toml_line(line, key, value)
+        {
+            old = Some(previous);
+            output.push_str(&updated);
+            continue;
+        }
+        output.push_str(line);
+    }
+    old.map(|old| (old, output))
+}
+
+/// `line` with the string value of `key` replaced, and the old value.
+fn replace_toml_line(line: &str, key: &str, value: &str) -> Option<(String, String)> {
+    let rest = line.trim_start().strip_prefix(key)?;
+    let rest = rest.trim_start().strip_prefix('=')?.trim_start();
+    let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let inner = &rest[1..];
+    let end = inner.find(quote)?;
+
+    let start = line.len() - inner.len();
+    let updated = format!("{}{}{}", &line[..start], value, &line[start + end..]);
+    Some((inner[..end].to_string(), updated))
+}
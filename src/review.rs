@@ -0,0 +1,206 @@
+//     _         _        __  __
+//    / \  _   _| |_ ___ |  \/  | ___  ___ ___  __ _  __ _  ___
+//   / _ \| | | | __/ _ \| |\/| |/ _ \/ __/ __|/ _` |/ _` |/ _ \
+//  / ___ \ |_| | || (_) | |  | |  __/\__ \__ \ (_| | (_| |  __/
+// /_/   \_\__,_|\__\___/|_|  |_|\___||___/___/\__,_|\__, |\___|
+//                                                   |___/
+//
+// Author: Sidney Zhang <zly@lyzhang.me>
+// Date: 2025-08-05
+// License: MIT
+//
+// Interactive review of generated messages.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::Write;
+
+use crate::conventional::{self, ConventionalCommit};
+use crate::sign::shell;
+
+/// What the user chose to do with a candidate message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReviewAction {
+    Accept,
+    Edit,
+    Regenerate,
+    /// Regenerate taking the given feedback into account; empty means the
+    /// feedback still has to be asked for.
+    Feedback(String),
+    /// Switch to the given type and optional scope.
+    TypeScope(String),
+    Abort,
+}
+
+/// Menu line shown under each candidate.
+pub const MENU: &str = "[a]ccept  [e]dit  [r]egenerate  [f]eedback  [t]ype/scope  [q]uit";
+
+/// Parse a menu answer. The feedback and type/scope choices may carry their
+/// argument inline, e.g. `f mention the migration` or `t fix(api)`.
+pub fn parse_action(input: &str) -> Option<ReviewAction> {
+    let input = input.trim();
+    let (choice, rest) = match input.split_once(char::is_whitespace) {
+        Some((choice, rest)) => (choice, rest.trim()),
+        None => (input, ""),
+    };
+
+    match choice.to_lowercase().as_str() {
+        "" | "a" | "accept" | "y" | "yes" => Some(ReviewAction::Accept),
+        "e" | "edit" => Some(ReviewAction::Edit),
+        "r" | "regenerate" => Some(ReviewAction::Regenerate),
+        "f" | "feedback" => Some(ReviewAction::Feedback(rest.to_string())),
+        "t" | "type" => Some(ReviewAction::TypeScope(rest.to_string())),
+        "q" | "quit" | "abort" | "n" | "no" => Some(ReviewAction::Abort),
+        _ => None,
+    }
+}
+
+/// Open `message` in `editor` and return the edited text with comment lines
+/// removed, the way git cleans up COMMIT_EDITMSG.
+pub fn edit_message(editor: &str, message: &str, comment_char: char) -> Result<String> {
+    let mut file = tempfile::Builder::new()
+        .prefix("COMMIT_EDITMSG")
+        .tempfile()?;
+    write!(
+        file,
+        "{}\n\n{c} Edit the commit message. Lines starting with '{c}' are ignored,\n\
+         {c} and an empty message aborts.\n",
+        message.trim_end(),
+        c = comment_char
+    )?;
+    file.flush()?;
+
+    // Like git, run the editor through the shell so it may carry arguments.
+    let path = file.path().to_string_lossy().replace('\'', "'\\''");
+    let status = shell(&format!("{} '{}'", editor, path))
+        .status()
+        .with_context(|| format!("could not run editor '{}'", editor))?;
+    if !status.success() {
+        anyhow::bail!("editor '{}' exited with {}", editor, status);
+    }
+
+    let edited = fs::read_to_string(file.path())
+        .with_context(|| format!("could not read edited message {:?}", file.path()))?;
+    Ok(strip_comments(&edited, comment_char))
+}
+
+/// Drop comment lines and surrounding blank lines.
+pub fn strip_comments(message: &str, comment_char: char) -> String {
+    let lines: Vec<&str> = message
+        .lines()
+        .filter(|line| !line.starts_with(comment_char))
+        .map(str::trim_end)
+        .collect();
+    lines.join("\n").trim().to_string()
+}
+
+/// Replace the `type(scope)` of a conventional subject line. `spec` is
+/// `type`, `type(scope)` or `(scope)`; an empty scope such as `fix()`
+/// removes it. A leading gitmoji and the `!` breaking marker are kept.
+pub fn replace_type_scope(message: &str, spec: &str) -> Result<String> {
+    let spec = spec.trim();
+    let (new_type, new_scope) = match spec.split_once('(') {
+        Some((kind, scope)) => {
+            let scope = scope
+                .strip_suffix(')')
+                .with_context(|| format!("missing ')' in '{}'", spec))?;
+            (kind.trim(), Some(scope.trim()))
+        }
+        None => (spec, None),
+    };
+    if new_type.is_empty() && new_scope.is_none() {
+        anyhow::bail!("expected a type and/or scope, e.g. fix(api)");
+    }
+    if new_type.contains(char::is_whitespace) {
+        anyhow::bail!("invalid type '{}'", new_type);
+    }
+
+    let (subject, body) = match message.split_once('\n') {
+        Some((subject, body)) => (subject, Some(body)),
+        None => (message, None),
+    };
+
+    let mut commit = match ConventionalCommit::parse(subject) {
+        Ok(commit) => commit,
+        Err(_) => {
+            // Not conventional yet: the whole subject becomes the
+            // description, after a leading emoji or :shortcode:.
+            let (emoji, start) = conventional::split_emoji(subject);
+            ConventionalCommit {
+                emoji,
+                kind: String::new(),
+                scope: None,
+                exclamation: false,
+                description: subject[start..].to_string(),
+                body: None,
+                footers: Vec::new(),
+            }
+        }
+    };
+
+    if !new_type.is_empty() {
+        commit.kind = new_type.to_string();
+    }
+    if commit.kind.is_empty() {
+        anyhow::bail!("the message has no type to keep; give one, e.g. feat(api)");
+    }
+    if let Some(scope) = new_scope {
+        commit.scope = (!scope.is_empty()).then(|| scope.to_string());
+    }
+
+    let mut replaced = commit.header();
+    if let Some(body) = body {
+        replaced.push('\n');
+        replaced.push_str(body);
+    }
+    Ok(replaced)
+}
+
+/// A table of old and new subject lines, one row per `(commit, before,
+/// after)`, with subjects cut to `width` characters.
+pub fn before_after_table(rows: &[(String, String, String)], width: usize) -> String {
+    let cut = |text: &str| -> String {
+        let subject = text.lines().next().unwrap_or("").trim();
+        if subject.chars().count() <= width {
+            subject.to_string()
+        } else {
+            let mut cut: String = subject.chars().take(width.saturating_sub(1)).collect();
+            cut.push('…');
+            cut
+        }
+    };
+    let rows: Vec<(&str, String, String)> = rows
+        .iter()
+        .map(|(commit, before, after)| (commit.as_str(), cut(before), cut(after)))
+        .collect();
+
+    let commit_width = rows
+        .iter()
+        .map(|row| row.0.chars().count())
+        .chain(["commit".len()])
+        .max()
+        .unwrap_or(0);
+    let before_width = rows
+        .iter()
+        .map(|row| row.1.chars().count())
+        .chain(["before".len()])
+        .max()
+        .unwrap_or(0);
+    let pad =
+        |text: &str, width: usize| format!("{}{}", text, " ".repeat(width - text.chars().count()));
+
+    let mut table = format!(
+        "{}  {}  after\n",
+        pad("commit", commit_width),
+        pad("before", before_width)
+    );
+    for (commit, before, after) in &rows {
+        table.push_str(&format!(
+            "{}  {}  {}\n",
+            pad(commit, commit_width),
+            pad(before, before_width),
+            after
+        ));
+    }
+    table
+}
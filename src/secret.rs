@@ -0,0 +1,177 @@
+//     _         _        __  __
+//    / \  _   _| |_ ___ |  \/  | ___  ___ ___  __ _  __ _  ___
+//   / _ \| | | | __/ _ \| |\/| |/ _ \/ __/ __|/ _` |/ _` |/ _ \
+//  / ___ \ |_| | || (_) | |  | |  __/\__ \__ \ (_| | (_| |  __/
+// /_/   \_\__,_|\__\___/|_|  |_|\___||___/___/\__,_|\__, |\___|
+//                                                   |___/
+//
+// Author: Sidney Zhang <zly@lyzhang.me>
+// Date: 2025-08-05
+// License: MIT
+//
+// Secret references for API keys: keyring, environment, command and file.
+
+use anyhow::{Context, Result};
+use keyring::{CredentialBuilder, Entry};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::sign::{expand_home, shell};
+
+/// Service name used for entries in the OS keyring.
+pub const KEYRING_SERVICE: &str = "git-automessage";
+
+/// Where the actual value of a secret setting lives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecretRef {
+    /// The value itself, stored in the config file.
+    Plain(String),
+    /// `keyring:<name>`: an entry in the OS keyring.
+    Keyring(String),
+    /// `env:<VAR>`: an environment variable.
+    Env(String),
+    /// `cmd:<command>`: the first line printed by a shell command.
+    Command(String),
+    /// `file:<path>`: the first line of a file.
+    File(PathBuf),
+}
+
+impl SecretRef {
+    pub fn parse(value: &str) -> Self {
+        // The legacy `ENV` placeholder means GAM_API_KEY.
+        if value == "ENV" {
+            return SecretRef::Env("GAM_API_KEY".to_string());
+        }
+        match value.split_once(':') {
+            Some(("keyring", name)) => SecretRef::Keyring(name.trim().to_string()),
+            Some(("env", var)) => SecretRef::Env(var.trim().to_string()),
+            Some(("cmd", command)) => SecretRef::Command(command.trim().to_string()),
+            Some(("file", path)) => SecretRef::File(expand_home(path.trim())),
+            _ => SecretRef::Plain(value.to_string()),
+        }
+    }
+
+    pub fn is_reference(&self) -> bool {
+        !matches!(self, SecretRef::Plain(_))
+    }
+
+    /// Check the reference is well formed without resolving it.
+    pub fn check(&self) -> Result<()> {
+        let empty = match self {
+            SecretRef::Plain(_) => false,
+            SecretRef::Keyring(name) => name.is_empty(),
+            SecretRef::Env(var) => var.is_empty(),
+            SecretRef::Command(command) => command.is_empty(),
+            SecretRef::File(path) => path.as_os_str().is_empty(),
+        };
+        if empty {
+            anyhow::bail!("secret reference is missing its target");
+        }
+        Ok(())
+    }
+
+    /// Look up the secret. Keyring references are read from `store`.
+    pub fn resolve(&self, store: &KeyringStore) -> Result<String> {
+        let secret = match self {
+            SecretRef::Plain(value) => return Ok(value.clone()),
+            SecretRef::Keyring(name) => store.get(name)?,
+            SecretRef::Env(var) => std::env::var(var)
+                .with_context(|| format!("environment variable {} is not set", var))?,
+            SecretRef::Command(command) => {
+                let output = shell(command)
+                    .stdin(std::process::Stdio::null())
+                    .output()
+                    .with_context(|| format!("could not run secret command '{}'", command))?;
+                if !output.status.success() {
+                    anyhow::bail!(
+                        "secret command '{}' failed:\n{}",
+                        command,
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    );
+                }
+                String::from_utf8(output.stdout).context("secret command output is not UTF-8")?
+            }
+            SecretRef::File(path) => fs::read_to_string(path)
+                .with_context(|| format!("could not read secret file {:?}", path))?,
+        };
+
+        let secret = secret.lines().next().unwrap_or("").trim().to_string();
+        if secret.is_empty() {
+            anyhow::bail!("secret {} is empty", self);
+        }
+        Ok(secret)
+    }
+}
+
+impl std::fmt::Display for SecretRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SecretRef::Plain(_) => write!(f, "(plain value)"),
+            SecretRef::Keyring(name) => write!(f, "keyring:{}", name),
+            SecretRef::Env(var) => write!(f, "env:{}", var),
+            SecretRef::Command(command) => write!(f, "cmd:{}", command),
+            SecretRef::File(path) => write!(f, "file:{}", path.display()),
+        }
+    }
+}
+
+/// The platform credential store used for `keyring:` secrets: Secret
+/// Service on Linux, Keychain on macOS and Credential Manager on Windows.
+pub struct KeyringStore {
+    service: String,
+    builder: Option<Box<CredentialBuilder>>,
+}
+
+impl KeyringStore {
+    pub fn new() -> Self {
+        Self {
+            service: KEYRING_SERVICE.to_string(),
+            builder: None,
+        }
+    }
+
+    /// Use another credential backend, such as a mock store in tests.
+    pub fn with_credential_builder(builder: Box<CredentialBuilder>) -> Self {
+        Self {
+            service: KEYRING_SERVICE.to_string(),
+            builder: Some(builder),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<String> {
+        self.entry(name)?
+            .get_password()
+            .with_context(|| format!("could not read '{}' from the keyring", name))
+    }
+
+    pub fn set(&self, name: &str, secret: &str) -> Result<()> {
+        self.entry(name)?
+            .set_password(secret)
+            .with_context(|| format!("could not store '{}' in the keyring", name))
+    }
+
+    pub fn delete(&self, name: &str) -> Result<()> {
+        match self.entry(name)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => {
+                Err(e).with_context(|| format!("could not delete '{}' from the keyring", name))
+            }
+        }
+    }
+
+    fn entry(&self, name: &str) -> Result<Entry> {
+        let entry = match &self.builder {
+            Some(builder) => builder
+                .build(None, &self.service, name)
+                .map(Entry::new_with_credential),
+            None => Entry::new(&self.service, name),
+        };
+        entry.with_context(|| format!("could not open keyring entry '{}'", name))
+    }
+}
+
+impl Default for KeyringStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,233 @@
+//     _         _        __  __
+//    / \  _   _| |_ ___ |  \/  | ___  ___ ___  __ _  __ _  ___
+//   / _ \| | | | __/ _ \| |\/| |/ _ \/ __/ __|/ _` |/ _` |/ _ \
+//  / ___ \ |_| | || (_) | |  | |  __/\__ \__ \ (_| | (_| |  __/
+// /_/   \_\__,_|\__\___/|_|  |_|\___||___/___/\__,_|\__, |\___|
+//                                                   |___/
+//
+// Author: Sidney Zhang <zly@lyzhang.me>
+// Date: 2025-08-05
+// License: MIT
+//
+// Commit and tag signing through external programs, the same way git does.
+
+use anyhow::{Context, Result};
+use git2::{Config, Signature};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SigningFormat {
+    OpenPgp,
+    X509,
+    Ssh,
+}
+
+impl SigningFormat {
+    fn from_config(config: &Config) -> Result<Self> {
+        match config.get_string("gpg.format") {
+            Ok(format) => match format.as_str() {
+                "openpgp" => Ok(SigningFormat::OpenPgp),
+                "x509" => Ok(SigningFormat::X509),
+                "ssh" => Ok(SigningFormat::Ssh),
+                other => anyhow::bail!("unsupported gpg.format: {}", other),
+            },
+            Err(_) => Ok(SigningFormat::OpenPgp),
+        }
+    }
+
+    fn default_program(&self) -> &'static str {
+        match self {
+            SigningFormat::OpenPgp => "gpg",
+            SigningFormat::X509 => "gpgsm",
+            SigningFormat::Ssh => "ssh-keygen",
+        }
+    }
+
+    fn program_key(&self) -> &'static str {
+        match self {
+            SigningFormat::OpenPgp => "gpg.openpgp.program",
+            SigningFormat::X509 => "gpg.x509.program",
+            SigningFormat::Ssh => "gpg.ssh.program",
+        }
+    }
+}
+
+pub struct Signer {
+    format: SigningFormat,
+    program: String,
+    key: Option<String>,
+    default_key_command: Option<String>,
+}
+
+impl Signer {
+    /// Build a signer from `gpg.format`, the matching `gpg.*.program` and
+    /// `user.signingkey`.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let format = SigningFormat::from_config(config)?;
+
+        let mut program = config.get_string(format.program_key()).ok();
+        if program.is_none() && format == SigningFormat::OpenPgp {
+            program = config.get_string("gpg.program").ok();
+        }
+        let program = program.unwrap_or_else(|| format.default_program().to_string());
+
+        Ok(Signer {
+            format,
+            program,
+            key: config.get_string("user.signingkey").ok(),
+            default_key_command: config.get_string("gpg.ssh.defaultKeyCommand").ok(),
+        })
+    }
+
+    pub fn format(&self) -> SigningFormat {
+        self.format
+    }
+
+    /// Sign `payload`, returning an armored detached signature.
+    /// `committer` supplies the default key id for OpenPGP and X.509.
+    pub fn sign(&self, payload: &str, committer: &Signature) -> Result<String> {
+        match self.format {
+            SigningFormat::OpenPgp | SigningFormat::X509 => {
+                let key = match &self.key {
+                    Some(key) => key.clone(),
+                    None => format!(
+                        "{} <{}>",
+                        committer.name().unwrap_or_default(),
+                        committer.email().unwrap_or_default()
+                    ),
+                };
+                self.sign_gpg(payload, &key)
+            }
+            SigningFormat::Ssh => self.sign_ssh(payload),
+        }
+    }
+
+    fn sign_gpg(&self, payload: &str, key: &str) -> Result<String> {
+        let mut child = Command::new(&self.program)
+            .args(["--status-fd=2", "-bsau", key])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("could not run signing program '{}'", self.program))?;
+
+        child
+            .stdin
+            .take()
+            .context("could not write to signing program")?
+            .write_all(payload.as_bytes())?;
+        let output = child.wait_with_output()?;
+
+        let status = String::from_utf8_lossy(&output.stderr);
+        if !output.status.success() || !status.contains("[GNUPG:] SIG_CREATED ") {
+            anyhow::bail!(
+                "'{}' failed to sign the data:\n{}",
+                self.program,
+                status.trim()
+            );
+        }
+
+        String::from_utf8(output.stdout).context("signature is not valid UTF-8")
+    }
+
+    fn sign_ssh(&self, payload: &str) -> Result<String> {
+        let key = match &self.key {
+            Some(key) => key.clone(),
+            None => self.default_ssh_key()?,
+        };
+
+        // A literal public key is looked up in ssh-agent; anything else is a key file.
+        let literal = key
+            .strip_prefix("key::")
+            .map(str::to_string)
+            .or_else(|| key.starts_with("ssh-").then(|| key.clone()));
+
+        let mut key_file = None;
+        let key_path = match literal {
+            Some(public_key) => {
+                let mut file = tempfile::Builder::new()
+                    .prefix(".git_signing_key_tmp")
+                    .tempfile()?;
+                writeln!(file, "{}", public_key)?;
+                let path = file.path().to_path_buf();
+                key_file = Some(file);
+                path
+            }
+            None => expand_home(&key),
+        };
+
+        let mut buffer = tempfile::Builder::new()
+            .prefix(".git_signing_buffer_tmp")
+            .tempfile()?;
+        buffer.write_all(payload.as_bytes())?;
+        buffer.flush()?;
+
+        let mut command = Command::new(&self.program);
+        command
+            .args(["-Y", "sign", "-n", "git", "-f"])
+            .arg(&key_path);
+        if key_file.is_some() {
+            command.arg("-U");
+        }
+        let output = command
+            .arg(buffer.path())
+            .stdin(Stdio::null())
+            .output()
+            .with_context(|| format!("could not run signing program '{}'", self.program))?;
+
+        let sig_path = PathBuf::from(format!("{}.sig", buffer.path().display()));
+        let signature = fs::read_to_string(&sig_path);
+        let _ = fs::remove_file(&sig_path);
+
+        if !output.status.success() {
+            anyhow::bail!(
+                "'{}' failed to sign the data:\n{}",
+                self.program,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        signature.with_context(|| format!("could not read ssh signature {:?}", sig_path))
+    }
+
+    fn default_ssh_key(&self) -> Result<String> {
+        let command = self
+            .default_key_command
+            .as_deref()
+            .context("user.signingkey needs to be set for ssh signing")?;
+
+        let output = shell(command)
+            .output()
+            .with_context(|| format!("could not run gpg.ssh.defaultKeyCommand '{}'", command))?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let key = stdout.lines().next().unwrap_or("").trim();
+        if !output.status.success() || key.is_empty() {
+            anyhow::bail!("gpg.ssh.defaultKeyCommand '{}' returned no key", command);
+        }
+        Ok(format!("key::{}", key))
+    }
+}
+
+pub(crate) fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir()
+            .map(|home| home.join(rest))
+            .unwrap_or_else(|| PathBuf::from(path)),
+        None => PathBuf::from(path),
+    }
+}
+
+pub(crate) fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    }
+}
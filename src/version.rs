@@ -0,0 +1,378 @@
+//     _         _        __  __
+//    / \  _   _| |_ ___ |  \/  | ___  ___ ___  __ _  __ _  ___
+//   / _ \| | | | __/ _ \| |\/| |/ _ \/ __/ __|/ _` |/ _` |/ _ \
+//  / ___ \ |_| | || (_) | |  | |  __/\__ \__ \ (_| | (_| |  __/
+// /_/   \_\__,_|\__\___/|_|  |_|\___||___/___/\__,_|\__, |\___|
+//                                                   |___/
+//
+// Author: Sidney Zhang <zly@lyzhang.me>
+// Date: 2025-08-05
+// License: MIT
+//
+// Semantic versions and the next version implied by the commit history.
+
+use anyhow::Result;
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::conventional::ConventionalCommit;
+use crate::git::{CommitInfo, GitRepo};
+
+/// Tag prefix used when `release.tag_prefix` is not set.
+pub const DEFAULT_TAG_PREFIX: &str = "v";
+
+/// Version released first when no version tag exists yet.
+pub const INITIAL_VERSION: Version = Version {
+    major: 0,
+    minor: 1,
+    patch: 0,
+    pre: None,
+};
+
+/// A semantic version. Build metadata is dropped when parsing, since it
+/// does not take part in precedence.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    /// Pre-release identifiers such as `rc.1`.
+    pub pre: Option<String>,
+}
+
+impl Version {
+    pub fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Version {
+            major,
+            minor,
+            patch,
+            pre: None,
+        }
+    }
+
+    /// Parse `1.2.3`, `1.2.3-rc.1` or `1.2.3+build.5`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let text = text.split_once('+').map_or(text, |(version, _)| version);
+        let (core, pre) = match text.split_once('-') {
+            Some((core, pre)) => (core, Some(pre)),
+            None => (text, None),
+        };
+
+        let mut numbers = core.split('.').map(|part| {
+            // Leading zeros are not allowed by the specification.
+            if part.is_empty() || (part.len() > 1 && part.starts_with('0')) {
+                return None;
+            }
+            part.parse::<u64>().ok()
+        });
+        let (major, minor, patch) = (numbers.next()??, numbers.next()??, numbers.next()??);
+        if numbers.next().is_some() {
+            return None;
+        }
+
+        if let Some(pre) = pre
+            && pre.split('.').any(|identifier| {
+                identifier.is_empty()
+                    || !identifier
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-')
+            })
+        {
+            return None;
+        }
+
+        Some(Version {
+            major,
+            minor,
+            patch,
+            pre: pre.map(str::to_string),
+        })
+    }
+
+    /// Parse a tag such as `v1.2.3` or `pkg@1.2.3` carrying `prefix`.
+    pub fn from_tag(tag: &str, prefix: &str) -> Option<Self> {
+        Self::parse(tag.strip_prefix(prefix)?)
+    }
+
+    pub fn is_prerelease(&self) -> bool {
+        self.pre.is_some()
+    }
+
+    /// The release this version leads up to, without pre-release identifiers.
+    pub fn release(&self) -> Version {
+        Version::new(self.major, self.minor, self.patch)
+    }
+
+    /// The pre-release channel and its number, e.g. `("rc", 2)` for `rc.2`.
+    /// A channel without a number counts as 0.
+    pub fn channel(&self) -> Option<(&str, u64)> {
+        let pre = self.pre.as_deref()?;
+        match pre.rsplit_once('.') {
+            Some((channel, number)) if number.chars().all(|c| c.is_ascii_digit()) => {
+                Some((channel, number.parse().ok()?))
+            }
+            _ => Some((pre, 0)),
+        }
+    }
+
+    /// Increase the version by `bump`, ignoring pre-release identifiers.
+    pub fn bumped(&self, bump: Bump) -> Version {
+        match bump {
+            Bump::None => self.release(),
+            Bump::Patch => Version::new(self.major, self.minor, self.patch + 1),
+            Bump::Minor => Version::new(self.major, self.minor + 1, 0),
+            Bump::Major => Version::new(self.major + 1, 0, 0),
+        }
+    }
+
+    /// The largest bump this release already stands for: `2.0.0` is a
+    /// major release and `1.3.0` a minor one.
+    fn level(&self) -> Bump {
+        if self.major > 0 && self.minor == 0 && self.patch == 0 {
+            Bump::Major
+        } else if self.patch == 0 {
+            Bump::Minor
+        } else {
+            Bump::Patch
+        }
+    }
+
+    /// The version after this one, for changes of the kind `bump`, on the
+    /// pre-release `channel` (e.g. `rc`) or as a release.
+    ///
+    /// Before 1.0.0, breaking changes bump the minor version and features
+    /// the patch version. A pre-release moves on to the next number of its
+    /// channel as long as its release already covers `bump`; otherwise the
+    /// release itself is bumped. `None` means there is nothing to release.
+    pub fn next(&self, bump: Bump, channel: Option<&str>) -> Option<Version> {
+        let bump = if self.major == 0 {
+            bump.pre_major()
+        } else {
+            bump
+        };
+        self.advance(bump, channel)
+    }
+
+    /// Like [`Self::next`], but `bump` is taken as is, also before 1.0.0;
+    /// for bumps the user asks for explicitly.
+    pub fn advance(&self, bump: Bump, channel: Option<&str>) -> Option<Version> {
+        let release = self.release();
+        let target = if self.is_prerelease() {
+            if bump <= release.level() {
+                release.clone()
+            } else {
+                release.bumped(bump)
+            }
+        } else if bump == Bump::None {
+            return None;
+        } else {
+            self.bumped(bump)
+        };
+
+        let Some(channel) = channel else {
+            return Some(target);
+        };
+        let number = match self.channel() {
+            Some((current, number)) if target == release && current == channel => {
+                if bump == Bump::None {
+                    return None;
+                }
+                number + 1
+            }
+            _ => 1,
+        };
+        Some(Version {
+            pre: Some(format!("{}.{}", channel, number)),
+            ..target
+        })
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if let Some(pre) = &self.pre {
+            write!(f, "-{}", pre)?;
+        }
+        Ok(())
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (&self.pre, &other.pre) {
+                (None, None) => Ordering::Equal,
+                // A pre-release comes before its release.
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(a), Some(b)) => compare_pre(a, b),
+            })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Pre-release precedence: identifier by identifier, numbers numerically
+/// and below words, and a shorter list first when all else is equal.
+fn compare_pre(a: &str, b: &str) -> Ordering {
+    let mut left = a.split('.');
+    let mut right = b.split('.');
+    loop {
+        let ordering = match (left.next(), right.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a), Some(b)) => match (a.parse::<u64>(), b.parse::<u64>()) {
+                (Ok(a), Ok(b)) => a.cmp(&b),
+                (Ok(_), Err(_)) => Ordering::Less,
+                (Err(_), Ok(_)) => Ordering::Greater,
+                (Err(_), Err(_)) => a.cmp(b),
+            },
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+/// How much a set of changes moves the version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Bump {
+    None,
+    Patch,
+    Minor,
+    Major,
+}
+
+impl Bump {
+    /// Breaking changes are major, `feat` minor and `fix` or `perf` patch;
+    /// anything else, including non-conventional messages, does not count.
+    pub fn of(message: &str) -> Bump {
+        let Ok(commit) = ConventionalCommit::parse_lenient(message) else {
+            return Bump::None;
+        };
+        if commit.is_breaking() {
+            Bump::Major
+        } else if commit.is("feat") {
+            Bump::Minor
+        } else if commit.is("fix") || commit.is("perf") {
+            Bump::Patch
+        } else {
+            Bump::None
+        }
+    }
+
+    /// The largest bump among `commits`.
+    pub fn for_commits(commits: &[CommitInfo]) -> Bump {
+        commits
+            .iter()
+            .map(|commit| Bump::of(&commit.message))
+            .max()
+            .unwrap_or(Bump::None)
+    }
+
+    /// The bump under the 0.x rules, one level lower.
+    fn pre_major(self) -> Bump {
+        match self {
+            Bump::Major => Bump::Minor,
+            Bump::Minor | Bump::Patch => Bump::Patch,
+            Bump::None => Bump::None,
+        }
+    }
+}
+
+impl fmt::Display for Bump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Bump::None => "none",
+            Bump::Patch => "patch",
+            Bump::Minor => "minor",
+            Bump::Major => "major",
+        })
+    }
+}
+
+/// A version tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaggedVersion {
+    pub tag: String,
+    pub version: Version,
+}
+
+/// The highest version among `tags` carrying `prefix`; other tags are ignored.
+pub fn latest_tag(tags: &[String], prefix: &str) -> Option<TaggedVersion> {
+    tags.iter()
+        .filter_map(|tag| {
+            Version::from_tag(tag, prefix).map(|version| TaggedVersion {
+                tag: tag.clone(),
+                version,
+            })
+        })
+        .max_by(|a, b| a.version.cmp(&b.version))
+}
+
+/// The changes since the last version tag and the version they lead to.
+#[derive(Debug)]
+pub struct Release {
+    /// The latest version tag reachable from HEAD.
+    pub previous: Option<TaggedVersion>,
+    /// Commits since `previous`, newest first.
+    pub commits: Vec<CommitInfo>,
+    pub bump: Bump,
+    /// The version `next` follows: that of `previous`, or without a version
+    /// tag the manifest version when it is past [`INITIAL_VERSION`].
+    pub released: Option<Version>,
+    /// `None` when no commit calls for a release.
+    pub next: Option<Version>,
+}
+
+/// Work out the next version from the tags reachable from HEAD. Without a
+/// version tag, the next version follows `current`, the version in the
+/// manifests, once that is past [`INITIAL_VERSION`]; otherwise any commit
+/// leads to [`INITIAL_VERSION`].
+pub fn next_release(
+    repo: &GitRepo,
+    prefix: &str,
+    channel: Option<&str>,
+    current: Option<&Version>,
+) -> Result<Release> {
+    let previous = latest_tag(&repo.tags_reachable_from("HEAD")?, prefix);
+    let commits = match &previous {
+        Some(previous) => repo.get_commits_in_range(&format!("{}..HEAD", previous.tag))?,
+        None => repo.get_recent_commits(usize::MAX)?,
+    };
+    let bump = Bump::for_commits(&commits);
+
+    let released = match &previous {
+        Some(previous) => Some(previous.version.clone()),
+        None => current
+            .filter(|current| **current > INITIAL_VERSION)
+            .cloned(),
+    };
+    let next = match &released {
+        Some(released) => released.next(bump, channel),
+        None if commits.is_empty() => None,
+        None => Some(match channel {
+            Some(channel) => Version {
+                pre: Some(format!("{}.1", channel)),
+                ..INITIAL_VERSION
+            },
+            None => INITIAL_VERSION,
+        }),
+    };
+
+    Ok(Release {
+        previous,
+        commits,
+        bump,
+        released,
+        next,
+    })
+}
@@ -0,0 +1,84 @@
+//     _         _        __  __
+//    / \  _   _| |_ ___ |  \/  | ___  ___ ___  __ _  __ _  ___
+//   / _ \| | | | __/ _ \| |\/| |/ _ \/ __/ __|/ _` |/ _` |/ _ \
+//  / ___ \ |_| | || (_) | |  | |  __/\__ \__ \ (_| | (_| |  __/
+// /_/   \_\__,_|\__\___/|_|  |_|\___||___/___/\__,_|\__, |\___|
+//                                                   |___/
+//
+// Author: Sidney Zhang <zly@lyzhang.me>
+// Date: 2025-08-05
+// License: MIT
+//
+// A test for candidate ranking
+
+use git_automessage::candidates::{self, Convention, MessageKind, Pick};
+
+fn commit_convention() -> Convention {
+    Convention {
+        kind: MessageKind::Commit,
+        max_length: 50,
+        types: vec!["feat".to_string(), "fix".to_string()],
+        scopes: vec!["api".to_string()],
+        conventional: true,
+    }
+}
+
+fn messages(list: &[&str]) -> Vec<String> {
+    list.iter().map(|m| m.to_string()).collect()
+}
+
+// 测试去重和空消息过滤
+#[test]
+fn test_collect_removes_duplicates() {
+    let list = messages(&[
+        "feat(api): add login",
+        "  Feat(api):  add login\n",
+        "",
+        "fix: handle empty diff",
+    ]);
+    let collected = candidates::collect(&list, &commit_convention());
+    assert_eq!(collected.len(), 2);
+    assert_eq!(collected[0].message, "feat(api): add login");
+    assert_eq!(collected[1].message, "fix: handle empty diff");
+}
+
+// 测试提交规范检查
+#[test]
+fn test_check_convention() {
+    let convention = commit_convention();
+    assert!(candidates::check("feat(api): add login", &convention).is_empty());
+    assert!(candidates::check("✨ feat!: drop v1", &convention).is_empty());
+
+    let problems = candidates::check("docs(web): update readme", &convention);
+    assert_eq!(problems.len(), 2);
+    assert!(problems[0].contains("'docs'"));
+    assert!(problems[1].contains("'web'"));
+
+    assert!(!candidates::check("Add login", &convention).is_empty());
+    assert!(!candidates::check(&format!("fix: {}", "x".repeat(60)), &convention).is_empty());
+    assert!(!candidates::check("fix: typo\nbody without blank line", &convention).is_empty());
+
+    // 标签消息不要求约定式格式
+    let tag = Convention::default();
+    assert!(candidates::check("Release 1.2.0 with the new login flow", &tag).is_empty());
+}
+
+// 测试按启发式分数选择最佳候选
+#[test]
+fn test_choose_best() {
+    let list = messages(&[
+        "Added login",
+        "feat(api): added login page.",
+        "feat(api): add login with OAuth providers",
+        "feat(api): add login with OAuth providers\n\nAlso add tests.",
+    ]);
+    let collected = candidates::collect(&list, &commit_convention());
+    assert!(collected[0].score < collected[1].score);
+    assert!(collected[1].score < collected[2].score);
+
+    // 分数相同时选择先生成的
+    assert_eq!(collected[2].score, collected[3].score);
+    assert_eq!(candidates::choose(&collected, Pick::Best), Some(2));
+    assert_eq!(candidates::choose(&collected, Pick::First), Some(0));
+    assert_eq!(candidates::choose(&[], Pick::Best), None);
+}
@@ -0,0 +1,255 @@
+//     _         _        __  __
+//    / \  _   _| |_ ___ |  \/  | ___  ___ ___  __ _  __ _  ___
+//   / _ \| | | | __/ _ \| |\/| |/ _ \/ __/ __|/ _` |/ _` |/ _ \
+//  / ___ \ |_| | || (_) | |  | |  __/\__ \__ \ (_| | (_| |  __/
+// /_/   \_\__,_|\__\___/|_|  |_|\___||___/___/\__,_|\__, |\___|
+//                                                   |___/
+//
+// Author: Sidney Zhang <zly@lyzhang.me>
+// Date: 2025-08-05
+// License: MIT
+//
+// A test for reading and editing Keep a Changelog documents
+
+use git_automessage::changelog::{Block, Changelog, UpdateMode};
+
+const EXISTING: &str = "# Changelog
+
+All notable changes to this project will be documented in this file.
+
+## Conventions
+
+We group changes by type. A second paragraph
+explains the rest of the intro.
+
+## [Unreleased]
+
+### Added
+
+- Export to CSV
+
+## [1.1.0] - 2025-02-01
+
+Maintenance release.
+
+### Fixed
+
+- Crash on empty input
+  when the file is missing
+
+```text
+## not a heading
+```
+
+## 1.0.0 (2025-01-01)
+
+- First release
+
+[Unreleased]: https://github.com/owner/repo/compare/v1.1.0...HEAD
+[1.1.0]: https://github.com/owner/repo/compare/v1.0.0...v1.1.0
+[1.0.0]: https://github.com/owner/repo/releases/tag/v1.0.0
+[docs]: https://example.com/docs
+";
+
+fn generated(version: &str, body: &str) -> Changelog {
+    Changelog::parse(&format!("## [{}] - 2025-03-01\n\n{}", version, body))
+}
+
+// 测试解析更新日志并原样输出
+#[test]
+fn test_parse_changelog() {
+    let changelog = Changelog::parse(EXISTING);
+    assert_eq!(changelog.to_string(), EXISTING);
+
+    assert!(changelog.preamble.contains(&"## Conventions".to_string()));
+    let versions: Vec<&str> = changelog
+        .releases
+        .iter()
+        .map(|release| release.version.as_str())
+        .collect();
+    assert_eq!(versions, vec!["Unreleased", "1.1.0", "1.0.0"]);
+    assert_eq!(changelog.releases[2].date.as_deref(), Some("2025-01-01"));
+    assert_eq!(changelog.links.len(), 4);
+    assert_eq!(
+        changelog.link_base().as_deref(),
+        Some("https://github.com/owner/repo")
+    );
+
+    let fixed = &changelog.release("v1.1.0").unwrap().sections[0];
+    assert_eq!(fixed.title, "Fixed");
+    assert!(fixed.blocks.contains(&Block::Entry(
+        "- Crash on empty input\n  when the file is missing".to_string()
+    )));
+    assert!(!changelog.unreleased().unwrap().sections.is_empty());
+}
+
+// 测试按字节原样往返，编辑后保留换行符和未变化的链接
+#[test]
+fn test_round_trip_bytes() {
+    let text = "# Changelog\r\n\r\n## [Unreleased]\r\n\r\n- Export to CSV\r\n\r\n## [1.0.0] - 2025-01-01\r\n\r\n- First release\r\n\r\n[Unreleased]: https://github.com/owner/repo/compare/v1.0.0...HEAD\r\n\r\n[1.0.0]: https://github.com/owner/repo/releases/tag/v1.0.0 \"First release\"\r\n[docs]: https://example.com/docs";
+    let mut changelog = Changelog::parse(text);
+    assert_eq!(changelog.to_string(), text);
+    changelog.regenerate_links("https://github.com/owner/repo", "v");
+    assert_eq!(changelog.to_string(), text);
+
+    changelog.promote_unreleased("1.1.0", "2025-03-01").unwrap();
+    changelog.regenerate_links("https://github.com/owner/repo", "v");
+    let edited = changelog.to_string();
+    assert!(!edited.replace("\r\n", "").contains('\n'));
+    assert!(edited.contains(
+        "## [Unreleased]\r\n\r\n## [1.1.0] - 2025-03-01\r\n\r\n- Export to CSV\r\n\r\n## [1.0.0]"
+    ));
+    assert!(edited.ends_with(
+        "- First release\r\n\r\n[Unreleased]: https://github.com/owner/repo/compare/v1.1.0...HEAD\r\n[1.1.0]: https://github.com/owner/repo/compare/v1.0.0...v1.1.0\r\n\r\n[1.0.0]: https://github.com/owner/repo/releases/tag/v1.0.0 \"First release\"\r\n[docs]: https://example.com/docs"
+    ));
+}
+
+// 测试按版本顺序插入新版本
+#[test]
+fn test_insert_release() {
+    let mut changelog = Changelog::parse(EXISTING);
+    let release = generated("1.0.1", "### Fixed\n\n- Typo in help\n")
+        .releases
+        .remove(0);
+    changelog.add_release(release);
+    let text = changelog.to_string();
+    let position = |needle: &str| text.find(needle).unwrap();
+    assert!(position("## [1.1.0]") < position("## [1.0.1] - 2025-03-01"));
+    assert!(position("## [1.0.1] - 2025-03-01") < position("## 1.0.0 (2025-01-01)"));
+    assert!(
+        text.contains("```\n\n## [1.0.1] - 2025-03-01\n\n### Fixed\n\n- Typo in help\n\n## 1.0.0")
+    );
+
+    // 没有版本的更新日志：插入在引言之后
+    let mut changelog = Changelog::parse("# Changelog\n\nA long intro.\nStill the intro.\n");
+    changelog.insert_release(generated("0.1.0", "- Start\n").releases.remove(0));
+    assert_eq!(
+        changelog.to_string(),
+        "# Changelog\n\nA long intro.\nStill the intro.\n\n## [0.1.0] - 2025-03-01\n\n- Start\n\n"
+    );
+}
+
+// 测试合并到 Unreleased 时跳过已有条目
+#[test]
+fn test_merge_into_unreleased() {
+    let mut changelog = Changelog::parse(EXISTING);
+    let release = generated(
+        "Unreleased",
+        "### Added\n\n- Export to CSV\n- Import from JSON\n\n### Removed\n\n- The old API\n",
+    )
+    .releases
+    .remove(0);
+    assert_eq!(changelog.merge_into_unreleased(&release), 2);
+    assert_eq!(changelog.merge_into_unreleased(&release), 0);
+    assert!(changelog.to_string().contains(
+        "## [Unreleased]\n\n### Added\n\n- Export to CSV\n- Import from JSON\n\n### Removed\n\n- The old API\n\n## [1.1.0]"
+    ));
+
+    let mut changelog = Changelog::parse("# Changelog\n\n## [1.0.0]\n\n- First\n");
+    changelog.merge_into_unreleased(&release);
+    assert!(changelog.to_string().starts_with(
+        "# Changelog\n\n## [Unreleased]\n\n### Added\n\n- Export to CSV\n- Import from JSON\n"
+    ));
+}
+
+// 测试将 Unreleased 发布为新版本并重新生成链接
+#[test]
+fn test_promote_unreleased() {
+    let mut changelog = Changelog::parse(EXISTING);
+    assert!(changelog.promote_unreleased("1.1.0", "2025-03-01").is_err());
+    changelog.promote_unreleased("1.2.0", "2025-03-01").unwrap();
+    changelog.regenerate_links("https://github.com/owner/repo", "v");
+
+    let text = changelog.to_string();
+    assert!(text.contains(
+        "## [Unreleased]\n\n## [1.2.0] - 2025-03-01\n\n### Added\n\n- Export to CSV\n\n## [1.1.0]"
+    ));
+    assert!(text.ends_with(
+        "[Unreleased]: https://github.com/owner/repo/compare/v1.2.0...HEAD
+[1.2.0]: https://github.com/owner/repo/compare/v1.1.0...v1.2.0
+[1.1.0]: https://github.com/owner/repo/compare/v1.0.0...v1.1.0
+[1.0.0]: https://github.com/owner/repo/releases/tag/v1.0.0
+[docs]: https://example.com/docs
+"
+    ));
+
+    let mut empty = Changelog::parse("# Changelog\n");
+    assert!(empty.promote_unreleased("1.0.0", "2025-03-01").is_err());
+}
+
+// 测试新的最新版本接管 Unreleased 中已有的条目
+#[test]
+fn test_add_release_takes_over_unreleased() {
+    let mut changelog = Changelog::parse(EXISTING);
+    let release = generated("1.2.0", "### Added\n\n- Export to CSV\n- Dark mode\n")
+        .releases
+        .remove(0);
+    changelog.add_release(release);
+
+    let text = changelog.to_string();
+    assert!(text.contains(
+        "## [Unreleased]\n\n## [1.2.0] - 2025-03-01\n\n### Added\n\n- Export to CSV\n- Dark mode\n\n## [1.1.0]"
+    ));
+    assert_eq!(text.matches("Export to CSV").count(), 1);
+}
+
+// 测试已有同一版本时的替换、合并与跳过
+#[test]
+fn test_update_modes() {
+    let release = || {
+        generated(
+            "1.1.0",
+            "### Fixed\n\n- Crash on empty input\n  when the file is missing\n- Slow start\n",
+        )
+        .releases
+        .remove(0)
+    };
+
+    let mut skipped = Changelog::parse(EXISTING);
+    skipped.update(release(), UpdateMode::Skip);
+    assert_eq!(skipped.to_string(), EXISTING);
+
+    let mut merged = Changelog::parse(EXISTING);
+    merged.update(release(), UpdateMode::Merge);
+    merged.update(release(), UpdateMode::Merge);
+    let text = merged.to_string();
+    assert_eq!(text.matches("## [1.1.0]").count(), 1);
+    assert_eq!(text.matches("Crash on empty input").count(), 1);
+    assert_eq!(text.matches("- Slow start").count(), 1);
+    assert!(text.contains("## [1.1.0] - 2025-02-01\n\nMaintenance release."));
+
+    let mut replaced = Changelog::parse(EXISTING);
+    replaced.update(release(), UpdateMode::Replace);
+    let text = replaced.to_string();
+    assert!(text.contains(
+        "## [1.1.0] - 2025-03-01\n\n### Fixed\n\n- Crash on empty input\n  when the file is missing\n- Slow start\n\n## 1.0.0"
+    ));
+    assert!(!text.contains("Maintenance release."));
+}
+
+// 测试记录最后处理的提交并识别已提及的提交
+#[test]
+fn test_last_commit_and_mentions() {
+    let mut changelog = Changelog::parse(EXISTING);
+    assert_eq!(changelog.last_commit, None);
+    changelog.last_commit = Some("0123456789abcdef0123456789abcdef01234567".to_string());
+    let text = changelog.to_string();
+    assert!(text.ends_with(
+        "[docs]: https://example.com/docs\n\n<!-- git-automessage last-commit: 0123456789abcdef0123456789abcdef01234567 -->\n"
+    ));
+    let reparsed = Changelog::parse(&text);
+    assert_eq!(reparsed, changelog);
+    assert_eq!(reparsed.to_string(), text);
+
+    let changelog = Changelog::parse(
+        "## [1.0.0]\n\n- abcdef12 (feat: add export) - Alice\n- Fixed typo (#123)\n- Build 20250101 is defaced\n",
+    );
+    let mentions = changelog.mentions();
+    assert!(mentions.contains("abcdef1234567890abcdef1234567890abcdef12"));
+    assert!(mentions.contains("ABCDEF1234567890abcdef1234567890abcdef12"));
+    assert!(!mentions.contains("abcdef9934567890abcdef1234567890abcdef12"));
+    assert!(!mentions.contains("1230000000000000000000000000000000000000"));
+    // 纯数字的短哈希同样算作提及
+    assert!(mentions.contains("2025010100000000000000000000000000000000"));
+    assert!(!mentions.contains("2025010200000000000000000000000000000000"));
+}
@@ -0,0 +1,149 @@
+//     _         _        __  __
+//    / \  _   _| |_ ___ |  \/  | ___  ___ ___  __ _  __ _  ___
+//   / _ \| | | | __/ _ \| |\/| |/ _ \/ __/ __|/ _` |/ _` |/ _ \
+//  / ___ \ |_| | || (_) | |  | |  __/\__ \__ \ (_| | (_| |  __/
+// /_/   \_\__,_|\__\___/|_|  |_|\___||___/___/\__,_|\__, |\___|
+//                                                   |___/
+//
+// Author: Sidney Zhang <zly@lyzhang.me>
+// Date: 2025-08-05
+// License: MIT
+//
+// A test for reading commitlint configurations
+
+use anyhow::Result;
+use git_automessage::commitlint::{self, CommitlintRules};
+use git_automessage::config::{ConfigLoader, ConfigSource};
+use std::fs;
+use tempfile::TempDir;
+
+// 测试读取 JSON 和 YAML 配置
+#[test]
+fn test_load_json_and_yaml() -> Result<()> {
+    let dir = TempDir::new()?;
+
+    let json = dir.path().join(".commitlintrc.json");
+    fs::write(
+        &json,
+        r#"{
+  "rules": {
+    "type-enum": [2, "always", ["feat", "fix"]],
+    "scope-enum": [2, "always", ["api", "cli"]],
+    "header-max-length": [2, "always", 60],
+    "subject-case": [2, "never", ["upper-case"]]
+  }
+}"#,
+    )?;
+    let rules = commitlint::load(&json)?;
+    assert_eq!(rules.types, ["feat", "fix"]);
+    assert_eq!(rules.scopes, ["api", "cli"]);
+    assert_eq!(rules.header_max_length, Some(60));
+    assert_eq!(rules.body_max_line_length, None);
+
+    // 继承 config-conventional，并关闭其中一条规则
+    let yaml = dir.path().join(".commitlintrc.yaml");
+    fs::write(
+        &yaml,
+        "extends:\n  - '@commitlint/config-conventional'\nrules:\n  body-max-line-length: [0, always, 100]\n",
+    )?;
+    let rules = commitlint::load(&yaml)?;
+    assert_eq!(rules.types, CommitlintRules::conventional().types);
+    assert_eq!(rules.header_max_length, Some(100));
+    assert_eq!(rules.body_max_line_length, None);
+
+    Ok(())
+}
+
+// 测试从 JS 配置中读取静态的 rules 对象
+#[test]
+fn test_load_script() -> Result<()> {
+    let dir = TempDir::new()?;
+    let script = dir.path().join("commitlint.config.js");
+    fs::write(
+        &script,
+        r#"// Team rules, see https://commitlint.js.org
+const { RuleConfigSeverity } = require('@commitlint/types');
+
+module.exports = {
+  /* no presets */
+  rules: {
+    'type-enum': [RuleConfigSeverity.Error, 'always', ['feat', 'fix', 'docs',]],
+    "scope-enum": [2, 'always', ['web']], // the only scope
+    'header-max-length': [1, 'always', 80],
+  },
+  prompt: { messages: { skip: '{ not a rule }' } },
+};
+"#,
+    )?;
+    let rules = commitlint::load(&script)?;
+    assert_eq!(rules.types, ["feat", "fix", "docs"]);
+    assert_eq!(rules.scopes, ["web"]);
+    assert_eq!(rules.header_max_length, Some(80));
+
+    let preset = dir.path().join(".commitlintrc.cjs");
+    fs::write(
+        &preset,
+        "module.exports = { extends: ['@commitlint/config-conventional'] };\n",
+    )?;
+    assert_eq!(commitlint::load(&preset)?, CommitlintRules::conventional());
+
+    Ok(())
+}
+
+// 测试查找配置文件，package.json 只有包含 commitlint 时才算
+#[test]
+fn test_find() -> Result<()> {
+    let dir = TempDir::new()?;
+    assert_eq!(commitlint::find(dir.path()), None);
+
+    fs::write(dir.path().join("package.json"), r#"{"name": "app"}"#)?;
+    fs::write(
+        dir.path().join("commitlint.config.js"),
+        "module.exports = {};",
+    )?;
+    assert_eq!(
+        commitlint::find(dir.path()),
+        Some(dir.path().join("commitlint.config.js"))
+    );
+
+    fs::write(
+        dir.path().join("package.json"),
+        r#"{"name": "app", "commitlint": {"rules": {"type-enum": [2, "always", ["feat"]]}}}"#,
+    )?;
+    let found = commitlint::find(dir.path()).unwrap();
+    assert!(found.ends_with("package.json"));
+    assert_eq!(commitlint::load(&found)?.types, ["feat"]);
+
+    Ok(())
+}
+
+// 测试 commitlint 规则作为配置层：优先于全局配置，低于仓库配置
+#[test]
+fn test_config_layer() -> Result<()> {
+    let dir = TempDir::new()?;
+    let global = dir.path().join("config.yaml");
+    fs::write(&global, "types: [chore]\nscopes: [global]\n")?;
+    let rc = dir.path().join(".commitlintrc.json");
+    fs::write(
+        &rc,
+        r#"{"rules": {"type-enum": [2, "always", ["feat", "fix"]], "scope-enum": [2, "always", ["api"]], "header-max-length": [2, "always", 50]}}"#,
+    )?;
+    let repo = dir.path().join(".git-automessage.yaml");
+    fs::write(&repo, "scopes: [api, cli]\n")?;
+
+    let layered = ConfigLoader::empty()
+        .global_file(&global)
+        .commitlint_file(&rc)
+        .repo_file(&repo)
+        .load()?;
+    assert_eq!(layered.config.types, ["feat", "fix"]);
+    assert_eq!(layered.config.scopes, ["api", "cli"]);
+    assert_eq!(layered.config.lint.max_header_length, Some(50));
+    assert_eq!(
+        layered.origin("types"),
+        Some(&ConfigSource::Commitlint(rc.clone()))
+    );
+    assert_eq!(layered.origin("scopes"), Some(&ConfigSource::Repo(repo)));
+
+    Ok(())
+}
@@ -0,0 +1,146 @@
+//     _         _        __  __
+//    / \  _   _| |_ ___ |  \/  | ___  ___ ___  __ _  __ _  ___
+//   / _ \| | | | __/ _ \| |\/| |/ _ \/ __/ __|/ _` |/ _` |/ _ \
+//  / ___ \ |_| | || (_) | |  | |  __/\__ \__ \ (_| | (_| |  __/
+// /_/   \_\__,_|\__\___/|_|  |_|\___||___/___/\__,_|\__, |\___|
+//                                                   |___/
+//
+// Author: Sidney Zhang <zly@lyzhang.me>
+// Date: 2025-08-05
+// License: MIT
+//
+// A test for diff compaction
+
+use git_automessage::compact::{self, estimate_tokens};
+
+fn file_diff(path: &str, hunks: usize, lines: usize) -> String {
+    let mut diff = format!(
+        "diff --git a/{path} b/{path}\nindex 1111111..2222222 100644\n--- a/{path}\n+++ b/{path}\n"
+    );
+    for hunk in 0..hunks {
+        diff.push_str(&format!(
+            "@@ -{start},{lines} +{start},{lines} @@ fn block_{hunk}()\n",
+            start = hunk * 100 + 1
+        ));
+        for line in 0..lines {
+            diff.push_str(&format!("-    let old_value_{line} = compute({line});\n"));
+            diff.push_str(&format!(
+                "+    let new_value_{line} = compute_better({line});\n"
+            ));
+        }
+    }
+    diff
+}
+
+// 测试 token 估算
+#[test]
+fn test_estimate_tokens() {
+    assert_eq!(estimate_tokens(""), 0);
+    assert_eq!(estimate_tokens("abcd"), 1);
+    assert_eq!(estimate_tokens("abcde"), 2);
+    assert_eq!(estimate_tokens("修复错误"), 4);
+}
+
+// 测试识别锁文件、生成文件和第三方代码
+#[test]
+fn test_is_noise() {
+    assert!(compact::is_noise("Cargo.lock"));
+    assert!(compact::is_noise("web/package-lock.json"));
+    assert!(compact::is_noise("go.sum"));
+    assert!(compact::is_noise("static/app.min.js"));
+    assert!(compact::is_noise("api/service.pb.go"));
+    assert!(compact::is_noise("vendor/github.com/pkg/errors/errors.go"));
+    assert!(compact::is_noise("web/node_modules/left-pad/index.js"));
+    assert!(!compact::is_noise("src/main.rs"));
+    assert!(!compact::is_noise("src/vendor.rs"));
+}
+
+// 测试解析统一差异格式
+#[test]
+fn test_parse_diff() {
+    let diff = format!(
+        "{}{}",
+        file_diff("src/a.rs", 2, 3),
+        file_diff("src/b.rs", 1, 1)
+    );
+    let files = compact::parse_diff(&diff);
+    assert_eq!(files.len(), 2);
+    assert_eq!(files[0].path, "src/a.rs");
+    assert_eq!((files[0].added, files[0].removed), (6, 6));
+    assert_eq!(files[1].path, "src/b.rs");
+}
+
+// 测试未超出上限时差异保持不变
+#[test]
+fn test_small_diff_untouched() {
+    let diff = file_diff("src/a.rs", 1, 2);
+    let compaction = compact::compact_diff(&diff, 10_000);
+    assert_eq!(compaction.diff, diff);
+    assert!(!compaction.is_partial());
+}
+
+// 测试按优先级精简：先去掉锁文件，再折叠代码块，最后只保留统计
+#[test]
+fn test_compaction_order() {
+    let source = file_diff("src/lib.rs", 2, 5);
+    let lockfile = file_diff("Cargo.lock", 4, 40);
+    let diff = format!("{}{}", source, lockfile);
+
+    // 去掉锁文件就足够
+    let compaction = compact::compact_diff(&diff, estimate_tokens(&source) + 10);
+    assert_eq!(compaction.dropped, vec!["Cargo.lock"]);
+    assert!(compaction.collapsed.is_empty());
+    assert!(compaction.diff.contains("new_value_4"));
+    assert!(!compaction.diff.contains("Cargo.lock"));
+
+    // 再把最大的文件折叠为代码块标题
+    let large = file_diff("src/large.rs", 3, 30);
+    let diff = format!("{}{}{}", source, large, lockfile);
+    let compaction = compact::compact_diff(&diff, estimate_tokens(&source) + 100);
+    assert_eq!(compaction.collapsed, vec!["src/large.rs"]);
+    assert!(
+        compaction
+            .diff
+            .contains("@@ -1,30 +1,30 @@ fn block_0() (+30 -30)")
+    );
+    assert!(compaction.diff.contains("new_value_4"));
+    assert!(!compaction.exceeds(estimate_tokens(&source) + 100));
+
+    // 上限很小时只保留每个文件的增删行数
+    let compaction = compact::compact_diff(&diff, 30);
+    assert_eq!(compaction.stats_only.len(), 2);
+    assert!(compaction.diff.contains("src/large.rs | +90 -90"));
+    assert!(compaction.tokens < compaction.original_tokens);
+}
+
+// 测试按 token 上限分组
+#[test]
+fn test_split_by_budget() {
+    let items: Vec<String> = (0..6)
+        .map(|i| format!("- commit {i:02} abcdefgh"))
+        .collect();
+    let chunks = compact::split_by_budget(&items, 12);
+    assert_eq!(chunks.len(), 3);
+    assert_eq!(chunks[0], "- commit 00 abcdefgh\n- commit 01 abcdefgh");
+
+    // 单个超出上限的条目单独成组
+    let large = vec!["x".repeat(400), "short".to_string()];
+    assert_eq!(compact::split_by_budget(&large, 10).len(), 2);
+}
+
+// 测试把差异按文件分段，去掉锁文件并精简过大的文件
+#[test]
+fn test_split_diff() {
+    let diff = format!(
+        "{}{}{}",
+        file_diff("src/a.rs", 1, 2),
+        file_diff("Cargo.lock", 1, 2),
+        file_diff("src/b.rs", 3, 30)
+    );
+    let budget = estimate_tokens(&file_diff("src/a.rs", 1, 2)) + 5;
+    let parts = compact::split_diff(&diff, budget);
+    assert_eq!(parts.len(), 2);
+    assert!(parts[0].contains("src/a.rs"));
+    assert!(parts.iter().all(|part| !part.contains("Cargo.lock")));
+    assert!(parts[1].contains("(+30 -30)"));
+}
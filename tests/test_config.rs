@@ -1,12 +1,434 @@
-//     _         _        __  __
-//    / \  _   _| |_ ___ |  \/  | ___  ___ ___  __ _  __ _  ___
-//   / _ \| | | | __/ _ \| |\/| |/ _ \/ __/ __|/ _` |/ _` |/ _ \
-//  / ___ \ |_| | || (_) | |  | |  __/\__ \__ \ (_| | (_| |  __/
-// /_/   \_\__,_|\__\___/|_|  |_|\___||___/___/\__,_|\__, |\___|
-//                                                   |___/
-//
-// Author: Sidney Zhang <zly@lyzhang.me>
-// Date: 2025-08-05
-// License: MIT
-//
-// A test for the Config struct
+//     _         _        __  __
+//    / \  _   _| |_ ___ |  \/  | ___  ___ ___  __ _  __ _  ___
+//   / _ \| | | | __/ _ \| |\/| |/ _ \/ __/ __|/ _` |/ _` |/ _ \
+//  / ___ \ |_| | || (_) | |  | |  __/\__ \__ \ (_| | (_| |  __/
+// /_/   \_\__,_|\__\___/|_|  |_|\___||___/___/\__,_|\__, |\___|
+//                                                   |___/
+//
+// Author: Sidney Zhang <zly@lyzhang.me>
+// Date: 2025-08-05
+// License: MIT
+//
+// A test for the Config struct
+
+use anyhow::Result;
+use git_automessage::config::{
+    Config, ConfigFile, ConfigLoader, ConfigScope, ConfigSource, DEFAULT_OLLAMA_TOKEN_BUDGET,
+    DEFAULT_TOKEN_BUDGET, LLMProvider, mask_secret,
+};
+use std::fs;
+use std::process::{Command, Stdio};
+use tempfile::TempDir;
+
+fn create_test_config(extra: &str) -> Result<Config> {
+    let config_content = format!(
+        r#"
+llm:
+  provider: OpenAI
+  api_key: "test-api-key"
+  base_url: ""
+  model: "gpt-4o-mini"
+{}
+"#,
+        extra
+    );
+
+    Ok(serde_yaml::from_str(&config_content)?)
+}
+
+// 测试验证时检查提示词模板
+#[test]
+fn test_validate_checks_templates() -> Result<()> {
+    create_test_config("")?.validate()?;
+    create_test_config("prompt: \"Describe {diff} on {branch}\"")?.validate()?;
+
+    let unknown = create_test_config("prompt: \"Describe {changes}\"")?;
+    let error = format!("{:#}", unknown.validate().unwrap_err());
+    assert!(error.contains("{changes}"));
+
+    let dir = TempDir::new()?;
+    let path = dir.path().join("tag.tmpl");
+    fs::write(&path, "Tag {tag_name} from {commits} by {owner}")?;
+    let from_file = create_test_config(&format!("templates:\n  tag: {:?}", path))?;
+    let error = format!("{:#}", from_file.validate().unwrap_err());
+    assert!(error.contains("{owner}"));
+
+    let missing = create_test_config("templates:\n  commit: /nonexistent/commit.tmpl")?;
+    assert!(missing.validate().is_err());
+
+    Ok(())
+}
+
+// 测试各层配置的优先级和来源
+#[test]
+fn test_layered_loading() -> Result<()> {
+    let dir = TempDir::new()?;
+    let global = dir.path().join("config.yaml");
+    fs::write(
+        &global,
+        "llm:\n  provider: DeepSeek\n  api_key: sk-global-secret-key\n  model: deepseek-chat\nlanguage: zh-CN\nemoji: true\n",
+    )?;
+    let repo = dir.path().join(".git-automessage.yaml");
+    fs::write(
+        &repo,
+        "language: en\ntypes: [feat, fix]\nscopes:\n  - api\n",
+    )?;
+
+    let layered = ConfigLoader::empty()
+        .global_file(&global)
+        .repo_file(&repo)
+        .env_vars([
+            ("GAM_MODEL".to_string(), "deepseek-reasoner".to_string()),
+            ("GAM_EMOJI".to_string(), "false".to_string()),
+        ])
+        .overrides(&["scopes=api, cli".to_string()])?
+        .load()?;
+    let config = &layered.config;
+
+    assert_eq!(config.llm.api_key, "sk-global-secret-key");
+    assert_eq!(config.language, "en");
+    assert_eq!(config.llm.model, "deepseek-reasoner");
+    assert!(!config.emoji);
+    assert_eq!(config.types, ["feat", "fix"]);
+    assert_eq!(config.scopes, ["api", "cli"]);
+    assert!(!config.multi_line);
+
+    assert_eq!(
+        layered.origin("llm.provider"),
+        Some(&ConfigSource::Global(global.clone()))
+    );
+    assert_eq!(
+        layered.origin("language"),
+        Some(&ConfigSource::Repo(repo.clone()))
+    );
+    assert_eq!(
+        layered.origin("llm.model"),
+        Some(&ConfigSource::Env("GAM_MODEL".to_string()))
+    );
+    assert_eq!(layered.origin("scopes"), Some(&ConfigSource::Cli));
+    assert_eq!(layered.origin("multi_line"), Some(&ConfigSource::Default));
+
+    // 显示时遮盖 API Key
+    let entries = layered.entries();
+    let (_, api_key, _) = entries
+        .iter()
+        .find(|(key, _, _)| key == "llm.api_key")
+        .unwrap();
+    assert_eq!(api_key, &mask_secret("sk-global-secret-key"));
+    assert!(api_key.ends_with("-key") && !api_key.contains("secret"));
+
+    Ok(())
+}
+
+// 测试环境变量与命令行覆盖中的提供商名称不区分大小写
+#[test]
+fn test_provider_case_insensitive() -> Result<()> {
+    let layered = ConfigLoader::empty()
+        .env_vars([("GAM_PROVIDER".to_string(), "openai".to_string())])
+        .load()?;
+    assert_eq!(layered.config.llm.provider, LLMProvider::OpenAI);
+
+    let layered = ConfigLoader::empty()
+        .overrides(&["llm.provider=anthropic".to_string()])?
+        .load()?;
+    assert_eq!(layered.config.llm.provider, LLMProvider::Anthropic);
+
+    let result = ConfigLoader::empty()
+        .env_vars([("GAM_PROVIDER".to_string(), "nobody".to_string())])
+        .load();
+    assert!(result.is_err());
+
+    Ok(())
+}
+
+// 测试仓库配置不能修改密钥和地址，模板路径相对于仓库根目录
+#[test]
+fn test_repo_file_restrictions() -> Result<()> {
+    let dir = TempDir::new()?;
+    let repo = dir.path().join(".git-automessage.yaml");
+    fs::write(
+        &repo,
+        "llm:\n  api_key: stolen\n  base_url: https://attacker.example\n  model: gpt-4o\ntemplates:\n  commit: prompts/commit.tmpl\n",
+    )?;
+
+    let layered = ConfigLoader::empty().repo_file(&repo).load()?;
+    assert_eq!(layered.config.llm.api_key, "");
+    assert_eq!(layered.config.llm.base_url, "");
+    assert_eq!(layered.config.llm.model, "gpt-4o");
+    assert_eq!(
+        layered.config.templates.commit,
+        Some(dir.path().join("prompts/commit.tmpl"))
+    );
+
+    Ok(())
+}
+
+// 测试无效的覆盖项
+#[test]
+fn test_invalid_overrides() {
+    assert!(
+        ConfigLoader::empty()
+            .overrides(&["language".to_string()])
+            .is_err()
+    );
+    assert!(
+        ConfigLoader::empty()
+            .overrides(&["colour=red".to_string()])
+            .is_err()
+    );
+
+    let result = ConfigLoader::empty()
+        .overrides(&["emoji=maybe".to_string()])
+        .and_then(|loader| loader.load());
+    assert!(result.is_err());
+}
+
+// 测试非交互方式创建配置
+#[test]
+fn test_create_non_interactive() -> Result<()> {
+    let config = Config::create(Some("ollama"), None, None, Some("llama3"), false)?;
+    assert_eq!(config.llm.provider, LLMProvider::Ollama);
+    assert_eq!(config.llm.model, "llama3");
+    config.validate()?;
+
+    let config = Config::create(
+        Some("DeepSeek"),
+        None,
+        Some("sk-test"),
+        Some("deepseek-chat"),
+        false,
+    )?;
+    assert_eq!(config.llm.api_key, "sk-test");
+    assert_eq!(config.llm.base_url, "https://api.deepseek.com");
+
+    Ok(())
+}
+
+fn run_cli(config_home: &std::path::Path, args: &[&str]) -> Result<std::process::Output> {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_git-automessage"));
+    for (name, _) in std::env::vars() {
+        if name.starts_with("GAM_") {
+            command.env_remove(name);
+        }
+    }
+    Ok(command
+        .args(args)
+        .current_dir(config_home)
+        .env("HOME", config_home)
+        .env("XDG_CONFIG_HOME", config_home)
+        .stdin(Stdio::null())
+        .output()?)
+}
+
+// 测试 CI 环境下 config init --non-interactive 以及缺少配置时不会等待输入
+#[test]
+fn test_cli_non_interactive_bootstrap() -> Result<()> {
+    let dir = TempDir::new()?;
+    let config_path = dir.path().join("git-automessage/config.yaml");
+
+    // 缺少模型时直接失败
+    let output = run_cli(
+        dir.path(),
+        &[
+            "config",
+            "init",
+            "--non-interactive",
+            "--provider",
+            "OpenAI",
+            "--api-key",
+            "sk-test",
+        ],
+    )?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("GAM_MODEL"));
+    assert!(!config_path.exists());
+
+    let output = run_cli(
+        dir.path(),
+        &[
+            "config",
+            "init",
+            "--non-interactive",
+            "--provider",
+            "OpenAI",
+            "--api-key",
+            "sk-test",
+            "--model",
+            "gpt-4o-mini",
+            "--language",
+            "en",
+        ],
+    )?;
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let config: Config = serde_yaml::from_str(&fs::read_to_string(&config_path)?)?;
+    assert_eq!(config.llm.model, "gpt-4o-mini");
+    assert_eq!(config.language, "en");
+    assert_eq!(config.llm.base_url, "https://api.openai.com/v1");
+
+    // 没有配置文件时，交互初始化在非终端下报错而不是阻塞
+    let empty = TempDir::new()?;
+    let output = run_cli(empty.path(), &["config", "--init"])?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("标准输入不是终端"));
+
+    Ok(())
+}
+
+// 测试 set_config 对无效值报错而不是崩溃
+#[test]
+fn test_set_config_validation() -> Result<()> {
+    let mut config = create_test_config("")?;
+    config.set_config("llm.provider", "anthropic")?;
+    assert_eq!(config.llm.provider, LLMProvider::Anthropic);
+    config.set_config("emoji", "yes")?;
+    assert!(config.emoji);
+    config.set_config("types", "feat, fix")?;
+    assert_eq!(config.types, ["feat", "fix"]);
+
+    assert!(config.set_config("llm.provider", "unknown-ai").is_err());
+    assert!(config.set_config("emoji", "maybe").is_err());
+    assert!(config.set_config("colour", "red").is_err());
+    assert!(config.set_config("prompt", "{#if emoji}").is_err());
+
+    Ok(())
+}
+
+// 测试读写单个配置文件
+#[test]
+fn test_config_file_edits() -> Result<()> {
+    let dir = TempDir::new()?;
+    let path = dir.path().join("config.yaml");
+    fs::write(
+        &path,
+        "llm:\n  provider: OpenAI\n  api_key: sk-0123456789abcdef\n# comment\n",
+    )?;
+
+    let mut file = ConfigFile::open_path(ConfigScope::Global, &path)?;
+    file.set("llm.model", "gpt-4o")?;
+    file.set("multi_line", "true")?;
+    file.save()?;
+
+    let file = ConfigFile::open_path(ConfigScope::Global, &path)?;
+    assert_eq!(file.get("llm.model")?.as_deref(), Some("gpt-4o"));
+    assert_eq!(file.get("multi_line")?.as_deref(), Some("true"));
+    assert_eq!(
+        file.get("llm.api_key")?.as_deref(),
+        Some("***************cdef")
+    );
+    assert_eq!(file.get("language")?, None);
+    assert!(file.get("colour").is_err());
+
+    // 文件中保存的仍是原始值
+    let saved: serde_yaml::Value = serde_yaml::from_str(&fs::read_to_string(&path)?)?;
+    assert_eq!(
+        saved["llm"]["api_key"].as_str(),
+        Some("sk-0123456789abcdef")
+    );
+
+    let mut file = ConfigFile::open_path(ConfigScope::Global, &path)?;
+    assert!(file.unset("llm.model")?);
+    assert!(!file.unset("llm.model")?);
+
+    // 仓库配置文件不能保存密钥和地址
+    let mut local =
+        ConfigFile::open_path(ConfigScope::Local, dir.path().join(".git-automessage.yaml"))?;
+    assert!(local.set("llm.api_key", "sk-test").is_err());
+    assert!(local.set("llm.base_url", "https://example.com").is_err());
+    local.set("scopes", "api,cli")?;
+    assert_eq!(
+        local.entries(),
+        [("scopes".to_string(), "api, cli".to_string())]
+    );
+
+    Ok(())
+}
+
+// 测试 config get/set/list 命令
+#[test]
+fn test_cli_get_set_list() -> Result<()> {
+    let dir = TempDir::new()?;
+    let output = run_cli(
+        dir.path(),
+        &[
+            "config",
+            "set",
+            "llm.api_key",
+            "sk-0123456789abcdef",
+            "--plain",
+        ],
+    )?;
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    run_cli(
+        dir.path(),
+        &["config", "set", "llm.model", "gpt-4o", "--global"],
+    )?;
+
+    let output = run_cli(dir.path(), &["config", "get", "llm.api_key", "--global"])?;
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        "***************cdef"
+    );
+
+    let output = run_cli(dir.path(), &["config", "list", "--global"])?;
+    let listed = String::from_utf8_lossy(&output.stdout);
+    assert!(listed.contains("llm.model=gpt-4o"));
+    assert!(!listed.contains("sk-0123456789abcdef"));
+
+    // 引用原样保存和显示
+    let output = run_cli(
+        dir.path(),
+        &["config", "set", "llm.api_key", "cmd:pass show openai"],
+    )?;
+    assert!(output.status.success());
+    let output = run_cli(dir.path(), &["config", "get", "llm.api_key"])?;
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        "cmd:pass show openai"
+    );
+
+    let output = run_cli(dir.path(), &["config", "set", "emoji", "maybe"])?;
+    assert!(!output.status.success());
+
+    let output = run_cli(dir.path(), &["config", "get", "language", "--global"])?;
+    assert!(!output.status.success());
+
+    Ok(())
+}
+
+// 测试按模型选择 token 上限
+#[test]
+fn test_token_budget() -> Result<()> {
+    assert_eq!(create_test_config("")?.token_budget(), DEFAULT_TOKEN_BUDGET);
+
+    let mut config =
+        create_test_config("token_budget:\n  default: 8000\n  models:\n    gpt-4o-mini: 60000")?;
+    assert_eq!(config.token_budget(), 60_000);
+    config.llm.model = "other-model".to_string();
+    assert_eq!(config.token_budget(), 8_000);
+
+    config.token_budget.default = None;
+    config.llm.provider = LLMProvider::Ollama;
+    assert_eq!(config.token_budget(), DEFAULT_OLLAMA_TOKEN_BUDGET);
+
+    let layered = ConfigLoader::empty()
+        .overrides(&["token_budget.default=2000".to_string()])?
+        .load()?;
+    assert_eq!(layered.config.token_budget.default, Some(2000));
+    assert!(
+        ConfigLoader::empty()
+            .overrides(&["token_budget.default=lots".to_string()])?
+            .load()
+            .is_err()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,199 @@
+//     _         _        __  __
+//    / \  _   _| |_ ___ |  \/  | ___  ___ ___  __ _  __ _  ___
+//   / _ \| | | | __/ _ \| |\/| |/ _ \/ __/ __|/ _` |/ _` |/ _ \
+//  / ___ \ |_| | || (_) | |  | |  __/\__ \__ \ (_| | (_| |  __/
+// /_/   \_\__,_|\__\___/|_|  |_|\___||___/___/\__,_|\__, |\___|
+//                                                   |___/
+//
+// Author: Sidney Zhang <zly@lyzhang.me>
+// Date: 2025-08-05
+// License: MIT
+//
+// A test for the Conventional Commits parser
+
+use git_automessage::conventional::{self, ConventionalCommit};
+
+// 测试解析标题、正文和脚注
+#[test]
+fn test_parse_full_message() {
+    let message = "feat(api)!: drop the v1 endpoints\n\n\
+                   The v1 endpoints were deprecated last year.\n\n\
+                   BREAKING CHANGE: clients must call /v2\n\
+                   Refs: #123\n\
+                   Closes #7\n";
+    let commit = ConventionalCommit::parse(message).unwrap();
+    assert_eq!(commit.kind, "feat");
+    assert_eq!(commit.scope.as_deref(), Some("api"));
+    assert!(commit.exclamation);
+    assert_eq!(commit.description, "drop the v1 endpoints");
+    assert_eq!(
+        commit.body.as_deref(),
+        Some("The v1 endpoints were deprecated last year.")
+    );
+    assert_eq!(commit.footers.len(), 3);
+    assert_eq!(commit.footer("refs"), Some("#123"));
+    assert_eq!(commit.footer("Closes"), Some("7"));
+    assert!(commit.is_breaking());
+    assert_eq!(commit.breaking_description(), Some("clients must call /v2"));
+    assert_eq!(commit.to_string(), message.trim_end());
+}
+
+// 测试表情前缀和只用 ! 标记的破坏性变更
+#[test]
+fn test_parse_emoji_and_exclamation() {
+    let commit = ConventionalCommit::parse("✨ feat!: new config format").unwrap();
+    assert_eq!(commit.emoji.as_deref(), Some("✨"));
+    assert_eq!(commit.header(), "✨ feat!: new config format");
+    assert_eq!(commit.breaking_description(), Some("new config format"));
+
+    let commit = ConventionalCommit::parse(":bug: fix: handle empty diff").unwrap();
+    assert_eq!(commit.emoji.as_deref(), Some(":bug:"));
+    assert!(commit.is("FIX"));
+    assert!(!commit.is_breaking());
+}
+
+// 测试不是约定式提交的消息
+#[test]
+fn test_parse_errors() {
+    // "feature-flag cleanup" 不应被当作 feat
+    assert!(ConventionalCommit::parse("feature-flag cleanup").is_err());
+
+    let error = ConventionalCommit::parse("feat(api: add login").unwrap_err();
+    assert_eq!(error.span, 4..19);
+    assert_eq!((error.line, error.column), (1, 5));
+
+    let error = ConventionalCommit::parse("fix:typo").unwrap_err();
+    assert_eq!(error.span, 3..4);
+    assert!(error.message.contains("space"));
+
+    let error = ConventionalCommit::parse("fix: typo\nbody").unwrap_err();
+    assert_eq!((error.line, error.column), (2, 1));
+
+    assert!(conventional::validate("fix(): typo").is_err());
+    assert!(conventional::validate("fix: ").is_err());
+    assert!(conventional::validate("docs: update readme").is_ok());
+}
+
+// 测试 ! 标记在类型或范围之后的位置
+#[test]
+fn test_parse_exclamation_positions() {
+    let commit = ConventionalCommit::parse("refactor!: drop Node 16").unwrap();
+    assert!(commit.exclamation);
+    assert_eq!(commit.scope, None);
+    assert_eq!(commit.breaking_description(), Some("drop Node 16"));
+
+    let commit = ConventionalCommit::parse("feat(core)!: new plugin API").unwrap();
+    assert_eq!(commit.scope.as_deref(), Some("core"));
+    assert!(commit.is_breaking());
+
+    // ! 只能紧跟在类型或范围之后
+    assert!(ConventionalCommit::parse("feat!(core): new plugin API").is_err());
+    assert!(ConventionalCommit::parse("feat !: new plugin API").is_err());
+    assert!(ConventionalCommit::parse("feat!!: new plugin API").is_err());
+}
+
+// 测试 BREAKING CHANGE 脚注的各种写法
+#[test]
+fn test_parse_breaking_change_footers() {
+    let commit = ConventionalCommit::parse(
+        "fix(parser): reject empty input\n\nBREAKING-CHANGE: empty input is an error",
+    )
+    .unwrap();
+    assert!(!commit.exclamation);
+    assert!(commit.is_breaking());
+    assert_eq!(commit.body, None);
+    assert_eq!(
+        commit.breaking_description(),
+        Some("empty input is an error")
+    );
+
+    // 脚注的值可以跨多行，直到下一个脚注
+    let commit = ConventionalCommit::parse(
+        "feat: new config\n\nBREAKING CHANGE: the config file moved\nto ~/.config/app.yaml\nReviewed-by: Z",
+    )
+    .unwrap();
+    assert_eq!(
+        commit.breaking_description(),
+        Some("the config file moved\nto ~/.config/app.yaml")
+    );
+    assert_eq!(commit.footer("reviewed-by"), Some("Z"));
+
+    // 小写的 breaking change 不是破坏性变更脚注，! 的描述优先级更低
+    let commit =
+        ConventionalCommit::parse("feat!: new config\n\nbreaking change: lower case").unwrap();
+    assert_eq!(commit.breaking_description(), Some("new config"));
+    let commit =
+        ConventionalCommit::parse("feat!: new config\n\nBREAKING CHANGE: explained").unwrap();
+    assert_eq!(commit.breaking_description(), Some("explained"));
+}
+
+// 测试多段正文与正文中类似脚注的行
+#[test]
+fn test_parse_multi_paragraph_body() {
+    let message = "docs: explain the release flow\n\n\
+                   First paragraph.\n\
+                   Still the first paragraph.\n\n\
+                   Second paragraph mentions Note: this is not a footer.\n\n\
+                   Refs: #42";
+    let commit = ConventionalCommit::parse(message).unwrap();
+    assert_eq!(
+        commit.body.as_deref(),
+        Some(
+            "First paragraph.\nStill the first paragraph.\n\n\
+             Second paragraph mentions Note: this is not a footer."
+        )
+    );
+    assert_eq!(commit.footers.len(), 1);
+    assert_eq!(commit.footer("Refs"), Some("#42"));
+    assert_eq!(commit.to_string(), message);
+
+    // 只有脚注没有正文
+    let commit = ConventionalCommit::parse("fix: typo\n\nCloses #9\nRefs: #10").unwrap();
+    assert_eq!(commit.body, None);
+    assert_eq!(commit.footers.len(), 2);
+}
+
+// 测试格式错误的范围
+#[test]
+fn test_parse_malformed_scopes() {
+    for (message, problem) in [
+        ("feat(): add login", "empty"),
+        ("feat( ): add login", "empty"),
+        ("feat(api(v2)): add login", "'('"),
+        ("feat(api: add login", "closing"),
+        ("feat(api)x: add login", "before ':'"),
+        ("feat (api): add login", "before ':'"),
+    ] {
+        let error = ConventionalCommit::parse(message).unwrap_err();
+        assert!(
+            error.message.contains(problem),
+            "{}: {}",
+            message,
+            error.message
+        );
+    }
+
+    let commit = ConventionalCommit::parse("feat(api-v2, cli): add login").unwrap();
+    assert_eq!(commit.scope.as_deref(), Some("api-v2, cli"));
+}
+
+// 测试宽松解析：正文前缺少空行时仍能识别类型和破坏性变更
+#[test]
+fn test_parse_lenient() {
+    let message = "feat(api): add export\nThe body starts right away.\nBREAKING CHANGE: v1 is gone";
+    assert!(ConventionalCommit::parse(message).is_err());
+
+    let commit = ConventionalCommit::parse_lenient(message).unwrap();
+    assert!(commit.is("feat"));
+    assert_eq!(commit.description, "add export");
+    assert_eq!(commit.body.as_deref(), Some("The body starts right away."));
+    assert_eq!(commit.breaking_description(), Some("v1 is gone"));
+
+    let commit = ConventionalCommit::parse_lenient("fix: typo\nRefs: #3\n").unwrap();
+    assert_eq!(commit.body, None);
+    assert_eq!(commit.footer("refs"), Some("#3"));
+
+    // 标题仍然必须符合规范
+    assert!(ConventionalCommit::parse_lenient("Update stuff\n\nfeat: no").is_err());
+    assert!(ConventionalCommit::parse_lenient("fix:typo").is_err());
+}
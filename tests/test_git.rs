@@ -0,0 +1,488 @@
+//     _         _        __  __
+//    / \  _   _| |_ ___ |  \/  | ___  ___ ___  __ _  __ _  ___
+//   / _ \| | | | __/ _ \| |\/| |/ _ \/ __/ __|/ _` |/ _` |/ _ \
+//  / ___ \ |_| | || (_) | |  | |  __/\__ \__ \ (_| | (_| |  __/
+// /_/   \_\__,_|\__\___/|_|  |_|\___||___/___/\__,_|\__, |\___|
+//                                                   |___/
+//
+// Author: Sidney Zhang <zly@lyzhang.me>
+// Date: 2025-08-05
+// License: MIT
+//
+// A test for the GitRepo struct
+
+use anyhow::Result;
+use git_automessage::git::{DiffMode, GitRepo};
+use git2::{Repository, Signature};
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+// 创建测试用的仓库
+fn init_repo() -> Result<(TempDir, Repository)> {
+    let dir = TempDir::new()?;
+    let repo = Repository::init(dir.path())?;
+    {
+        let mut config = repo.config()?;
+        config.set_str("user.name", "Test User")?;
+        config.set_str("user.email", "test@example.com")?;
+    }
+    Ok((dir, repo))
+}
+
+fn stage(repo: &Repository, path: &str) -> Result<()> {
+    let mut index = repo.index()?;
+    index.add_path(Path::new(path))?;
+    index.write()?;
+    Ok(())
+}
+
+fn commit_index(repo: &Repository, message: &str) -> Result<()> {
+    let signature = Signature::now("Test User", "test@example.com")?;
+    let tree = repo.find_tree(repo.index()?.write_tree()?)?;
+    let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+    let parents: Vec<&git2::Commit> = parent.iter().collect();
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &parents,
+    )?;
+    Ok(())
+}
+
+fn paths(repo: &GitRepo, mode: DiffMode) -> Result<Vec<String>> {
+    let mut paths: Vec<String> = repo
+        .get_changed_files(mode)?
+        .into_iter()
+        .map(|f| f.path)
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+// 测试未提交过的仓库（unborn branch）中的暂存文件
+#[test]
+fn test_staged_files_on_unborn_branch() -> Result<()> {
+    let (dir, repo) = init_repo()?;
+    fs::write(dir.path().join("a.txt"), "hello\n")?;
+    fs::write(dir.path().join("b.txt"), "untracked\n")?;
+    stage(&repo, "a.txt")?;
+
+    let git = GitRepo::open_path(dir.path())?;
+    let staged = git.get_staged_files()?;
+    assert_eq!(staged.len(), 1);
+    assert_eq!(staged[0].path, "a.txt");
+    assert_eq!(staged[0].status, "added");
+
+    let diff = git.get_staged_diff()?;
+    assert!(diff.contains("+hello"));
+    assert!(!diff.contains("untracked"));
+
+    Ok(())
+}
+
+// 测试部分暂存的文件：暂存区、工作区和全部三种模式
+#[test]
+fn test_partially_staged_file() -> Result<()> {
+    let (dir, repo) = init_repo()?;
+    fs::write(dir.path().join("a.txt"), "one\n")?;
+    stage(&repo, "a.txt")?;
+    commit_index(&repo, "initial")?;
+
+    fs::write(dir.path().join("a.txt"), "one\nstaged line\n")?;
+    stage(&repo, "a.txt")?;
+    fs::write(
+        dir.path().join("a.txt"),
+        "one\nstaged line\nunstaged line\n",
+    )?;
+    fs::write(dir.path().join("new.txt"), "brand new\n")?;
+
+    let git = GitRepo::open_path(dir.path())?;
+
+    let staged = git.get_diff(DiffMode::Staged)?;
+    assert!(staged.contains("+staged line"));
+    assert!(!staged.contains("unstaged line"));
+    assert_eq!(paths(&git, DiffMode::Staged)?, vec!["a.txt"]);
+
+    let unstaged = git.get_diff(DiffMode::Unstaged)?;
+    assert!(unstaged.contains("+unstaged line"));
+    assert!(!unstaged.contains("+staged line"));
+    assert!(unstaged.contains("+brand new"));
+    assert_eq!(paths(&git, DiffMode::Unstaged)?, vec!["a.txt", "new.txt"]);
+
+    let all = git.get_diff(DiffMode::All)?;
+    assert!(all.contains("+staged line"));
+    assert!(all.contains("+unstaged line"));
+    assert!(all.contains("+brand new"));
+
+    Ok(())
+}
+
+// 测试 create_commit 记录的内容与暂存区一致
+#[test]
+fn test_create_commit_records_index() -> Result<()> {
+    let (dir, repo) = init_repo()?;
+    fs::write(dir.path().join("a.txt"), "one\n")?;
+    stage(&repo, "a.txt")?;
+
+    let git = GitRepo::open_path(dir.path())?;
+    git.create_commit("feat: initial commit")?;
+    assert!(git.get_staged_files()?.is_empty());
+
+    fs::write(dir.path().join("a.txt"), "one\ntwo\n")?;
+    fs::write(dir.path().join("b.txt"), "b\n")?;
+    git.stage_all()?;
+    assert_eq!(paths(&git, DiffMode::Staged)?, vec!["a.txt", "b.txt"]);
+    git.create_commit("feat: add b")?;
+
+    let head = repo.head()?.peel_to_commit()?;
+    assert_eq!(head.message(), Some("feat: add b"));
+    assert_eq!(head.parent_count(), 1);
+    assert!(git.get_changed_files(DiffMode::All)?.is_empty());
+
+    Ok(())
+}
+
+// 测试提交和标签使用 git 配置中的身份
+#[test]
+fn test_commit_uses_configured_identity() -> Result<()> {
+    let (dir, repo) = init_repo()?;
+    fs::write(dir.path().join("a.txt"), "one\n")?;
+    stage(&repo, "a.txt")?;
+
+    let git = GitRepo::open_path(dir.path())?;
+    git.create_commit("feat: initial commit")?;
+    git.create_annotated_tag("v0.1.0", "Release 0.1.0", "HEAD")?;
+
+    let head = repo.head()?.peel_to_commit()?;
+    assert_eq!(head.author().name(), Some("Test User"));
+    assert_eq!(head.author().email(), Some("test@example.com"));
+    assert_eq!(head.committer().email(), Some("test@example.com"));
+
+    let tag = repo.revparse_single("v0.1.0")?.peel_to_tag()?;
+    assert_eq!(tag.tagger().unwrap().name(), Some("Test User"));
+
+    Ok(())
+}
+
+// 测试 GIT_*_DATE 支持的日期格式
+#[test]
+fn test_parse_git_date() {
+    let time = git_automessage::git::parse_git_date("@1700000000 +0130").unwrap();
+    assert_eq!(time.seconds(), 1700000000);
+    assert_eq!(time.offset_minutes(), 90);
+
+    let time = git_automessage::git::parse_git_date("1700000000 -0500").unwrap();
+    assert_eq!(time.offset_minutes(), -300);
+
+    let time = git_automessage::git::parse_git_date("Tue, 14 Nov 2023 22:13:20 +0000").unwrap();
+    assert_eq!(time.seconds(), 1700000000);
+
+    let time = git_automessage::git::parse_git_date("2023-11-14T22:13:20+02:00").unwrap();
+    assert_eq!(time.seconds(), 1700000000 - 7200);
+    assert_eq!(time.offset_minutes(), 120);
+
+    assert!(git_automessage::git::parse_git_date("yesterday").is_none());
+}
+
+#[cfg(unix)]
+fn write_script(path: &Path, content: &str) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::write(path, content)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    Ok(())
+}
+
+// 测试通过 gpg 协议签名提交和标签
+#[cfg(unix)]
+#[test]
+fn test_gpg_signed_commit_and_tag() -> Result<()> {
+    let (dir, repo) = init_repo()?;
+    let program = dir.path().join("fake-gpg");
+    write_script(
+        &program,
+        "#!/bin/sh\ncat > /dev/null\necho \"$3\" > \"$(dirname \"$0\")/gpg-key\"\n\
+         echo '[GNUPG:] SIG_CREATED D 1 8 00 1700000000 FINGERPRINT' >&2\n\
+         printf -- '-----BEGIN PGP SIGNATURE-----\\n\\nfake\\n-----END PGP SIGNATURE-----\\n'\n",
+    )?;
+    {
+        let mut config = repo.config()?;
+        config.set_bool("commit.gpgsign", true)?;
+        config.set_bool("tag.gpgsign", true)?;
+        config.set_str("gpg.program", program.to_str().unwrap())?;
+    }
+
+    fs::write(dir.path().join("a.txt"), "one\n")?;
+    stage(&repo, "a.txt")?;
+    let git = GitRepo::open_path(dir.path())?;
+    git.create_commit("feat: signed commit")?;
+
+    let head = repo.head()?.peel_to_commit()?;
+    assert_eq!(head.message(), Some("feat: signed commit"));
+    let (signature, _) = repo.extract_signature(&head.id(), None)?;
+    assert!(signature.as_str().unwrap().contains("fake"));
+    assert_eq!(
+        fs::read_to_string(dir.path().join("gpg-key"))?.trim(),
+        "Test User <test@example.com>"
+    );
+
+    git.create_annotated_tag("v1.0.0", "Release 1.0.0", "HEAD")?;
+    let tag = repo.revparse_single("v1.0.0")?.peel_to_tag()?;
+    assert_eq!(tag.target_id(), head.id());
+    assert_eq!(tag.tagger().unwrap().name(), Some("Test User"));
+    let message = tag.message().unwrap();
+    assert!(message.starts_with("Release 1.0.0\n"));
+    assert!(message.contains("-----BEGIN PGP SIGNATURE-----"));
+
+    Ok(())
+}
+
+// 测试 gpg.format=ssh 时通过 ssh-keygen 协议签名
+#[cfg(unix)]
+#[test]
+fn test_ssh_signed_commit() -> Result<()> {
+    let (dir, repo) = init_repo()?;
+    let program = dir.path().join("fake-ssh-keygen");
+    // ssh-keygen -Y sign -n git -f <key> <file> writes <file>.sig
+    write_script(
+        &program,
+        "#!/bin/sh\nfor last; do :; done\n\
+         printf -- '-----BEGIN SSH SIGNATURE-----\\nfake\\n-----END SSH SIGNATURE-----\\n' > \"$last.sig\"\n",
+    )?;
+    {
+        let mut config = repo.config()?;
+        config.set_bool("commit.gpgsign", true)?;
+        config.set_str("gpg.format", "ssh")?;
+        config.set_str("gpg.ssh.program", program.to_str().unwrap())?;
+        config.set_str(
+            "user.signingkey",
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIFake test",
+        )?;
+    }
+
+    fs::write(dir.path().join("a.txt"), "one\n")?;
+    stage(&repo, "a.txt")?;
+    let git = GitRepo::open_path(dir.path())?;
+    git.create_commit("feat: ssh signed commit")?;
+
+    let head = repo.head()?.peel_to_commit()?;
+    let (signature, _) = repo.extract_signature(&head.id(), None)?;
+    assert!(
+        signature
+            .as_str()
+            .unwrap()
+            .starts_with("-----BEGIN SSH SIGNATURE-----")
+    );
+
+    Ok(())
+}
+
+// 测试 --amend 描述 HEAD 与暂存区的合并差异，并替换 HEAD
+#[test]
+fn test_amend_commit() -> Result<()> {
+    let (dir, repo) = init_repo()?;
+    fs::write(dir.path().join("a.txt"), "one\n")?;
+    stage(&repo, "a.txt")?;
+    commit_index(&repo, "initial")?;
+    fs::write(dir.path().join("b.txt"), "b\n")?;
+    stage(&repo, "b.txt")?;
+    commit_index(&repo, "add b")?;
+    fs::write(dir.path().join("c.txt"), "c\n")?;
+    stage(&repo, "c.txt")?;
+
+    let git = GitRepo::open_path(dir.path())?;
+    assert_eq!(paths(&git, DiffMode::Amend)?, vec!["b.txt", "c.txt"]);
+    assert!(!git.get_diff(DiffMode::Amend)?.contains("+one"));
+
+    let before = repo.head()?.peel_to_commit()?;
+    git.amend_commit("feat: add b and c")?;
+    let head = repo.head()?.peel_to_commit()?;
+    assert_ne!(head.id(), before.id());
+    assert_eq!(head.message(), Some("feat: add b and c"));
+    assert_eq!(head.parent_id(0)?, before.parent_id(0)?);
+    assert!(head.tree()?.get_name("c.txt").is_some());
+    assert!(git.get_staged_files()?.is_empty());
+
+    Ok(())
+}
+
+// 测试改写较早的提交：后续提交被重放，原提交保留在备份引用中
+#[test]
+fn test_reword_commit() -> Result<()> {
+    let (dir, repo) = init_repo()?;
+    for (file, message) in [("a.txt", "initial"), ("b.txt", "wip"), ("c.txt", "add c")] {
+        fs::write(dir.path().join(file), file)?;
+        stage(&repo, file)?;
+        commit_index(&repo, message)?;
+    }
+    let original = repo.revparse_single("HEAD~1")?.peel_to_commit()?;
+    let old_head = repo.head()?.peel_to_commit()?;
+
+    let git = GitRepo::open_path(dir.path())?;
+    let (files, diff) = git.get_commit_changes("HEAD~1")?;
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].path, "b.txt");
+    assert!(diff.contains("+b.txt"));
+
+    let reworded = git.reword_commit("HEAD~1", "feat: add b", false)?;
+    assert_eq!(reworded.rebased, 1);
+
+    let head = repo.head()?.peel_to_commit()?;
+    assert_eq!(head.message(), Some("add c"));
+    assert_eq!(head.tree_id(), old_head.tree_id());
+    assert_eq!(head.parent_id(0)?, reworded.commits[0]);
+    let new = repo.find_commit(reworded.commits[0])?;
+    assert_eq!(new.message(), Some("feat: add b"));
+    assert_eq!(new.parent_id(0)?, original.parent_id(0)?);
+
+    let backup = repo
+        .find_reference(&reworded.backups[0])?
+        .peel_to_commit()?;
+    assert_eq!(backup.id(), original.id());
+    assert_eq!(backup.message(), Some("wip"));
+
+    Ok(())
+}
+
+// 测试已推送的提交需要 --force 才能改写
+#[test]
+fn test_reword_refuses_published_commit() -> Result<()> {
+    let (dir, repo) = init_repo()?;
+    fs::write(dir.path().join("a.txt"), "one\n")?;
+    stage(&repo, "a.txt")?;
+    commit_index(&repo, "initial")?;
+    let head = repo.head()?.peel_to_commit()?.id();
+    repo.reference("refs/remotes/origin/main", head, false, "push")?;
+
+    let git = GitRepo::open_path(dir.path())?;
+    let error = git
+        .reword_commit("HEAD", "feat: initial", false)
+        .unwrap_err();
+    assert!(error.to_string().contains("origin/main"));
+    assert_eq!(repo.head()?.peel_to_commit()?.id(), head);
+
+    git.reword_commit("HEAD", "feat: initial", true)?;
+    assert_eq!(
+        repo.head()?.peel_to_commit()?.message(),
+        Some("feat: initial")
+    );
+
+    Ok(())
+}
+
+// 测试一次改写多个提交：保留作者与日期，分支一次性更新
+#[test]
+fn test_reword_commits() -> Result<()> {
+    let (dir, repo) = init_repo()?;
+    let author = Signature::new(
+        "Author",
+        "author@example.com",
+        &git2::Time::new(1_700_000_000, 60),
+    )?;
+    let committer = Signature::new(
+        "Committer",
+        "committer@example.com",
+        &git2::Time::new(1_700_000_100, 60),
+    )?;
+    let mut oids = Vec::new();
+    for (file, message) in [
+        ("a.txt", "initial"),
+        ("b.txt", "wip"),
+        ("c.txt", "fix stuff"),
+        ("d.txt", "add d"),
+    ] {
+        fs::write(dir.path().join(file), file)?;
+        stage(&repo, file)?;
+        let tree = repo.find_tree(repo.index()?.write_tree()?)?;
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        oids.push(repo.commit(Some("HEAD"), &author, &committer, message, &tree, &parents)?);
+    }
+    let old_head = repo.head()?.peel_to_commit()?;
+
+    let git = GitRepo::open_path(dir.path())?;
+    let reworded = git.reword_commits(
+        &[
+            (oids[1], "feat: add b".to_string()),
+            (oids[2], "feat: add c".to_string()),
+        ],
+        false,
+    )?;
+    assert_eq!(reworded.commits.len(), 2);
+    assert_eq!(reworded.rebased, 1);
+    assert_eq!(reworded.backups.len(), 2);
+
+    let head = repo.head()?.peel_to_commit()?;
+    assert_eq!(head.message(), Some("add d"));
+    assert_eq!(head.tree_id(), old_head.tree_id());
+    assert_eq!(head.parent_id(0)?, reworded.commits[1]);
+    let c = repo.find_commit(reworded.commits[1])?;
+    assert_eq!(c.message(), Some("feat: add c"));
+    assert_eq!(c.parent_id(0)?, reworded.commits[0]);
+    let b = repo.find_commit(reworded.commits[0])?;
+    assert_eq!(b.message(), Some("feat: add b"));
+    assert_eq!(b.parent_id(0)?, oids[0]);
+
+    for commit in [&head, &c, &b] {
+        assert_eq!(commit.author().name(), Some("Author"));
+        assert_eq!(commit.author().when().seconds(), 1_700_000_000);
+        assert_eq!(commit.committer().name(), Some("Committer"));
+        assert_eq!(commit.committer().when().seconds(), 1_700_000_100);
+    }
+
+    Ok(())
+}
+
+// 测试查找引用之前最近的标签
+#[test]
+fn test_previous_tag() -> Result<()> {
+    let (dir, repo) = init_repo()?;
+    let mut oids = Vec::new();
+    for file in ["a.txt", "b.txt", "c.txt", "d.txt"] {
+        fs::write(dir.path().join(file), file)?;
+        stage(&repo, file)?;
+        commit_index(&repo, file)?;
+        oids.push(repo.head()?.peel_to_commit()?.id());
+    }
+    let tag = |name: &str, index: usize| -> Result<()> {
+        let object = repo.find_object(oids[index], None)?;
+        repo.tag_lightweight(name, &object, false)?;
+        Ok(())
+    };
+    tag("v0.9.0", 0)?;
+    tag("v1.9.0", 1)?;
+    tag("v1.10.0", 1)?;
+    tag("v2.0.0", 3)?;
+
+    let git = GitRepo::open_path(dir.path())?;
+    assert_eq!(git.previous_tag("HEAD")?.as_deref(), Some("v1.10.0"));
+    assert_eq!(git.previous_tag("HEAD~2")?.as_deref(), Some("v0.9.0"));
+    assert_eq!(git.previous_tag("HEAD~3")?, None);
+    assert_eq!(git.get_commits_reachable("HEAD~1")?.len(), 3);
+
+    Ok(())
+}
+
+// 测试判断提交是否为引用的祖先
+#[test]
+fn test_is_ancestor() -> Result<()> {
+    let (dir, repo) = init_repo()?;
+    for file in ["a.txt", "b.txt"] {
+        fs::write(dir.path().join(file), file)?;
+        stage(&repo, file)?;
+        commit_index(&repo, file)?;
+    }
+    let first = repo.revparse_single("HEAD~1")?.id().to_string();
+
+    let git = GitRepo::open_path(dir.path())?;
+    assert!(git.is_ancestor(&first, "HEAD")?);
+    assert!(git.is_ancestor("HEAD", "HEAD")?);
+    assert!(!git.is_ancestor("HEAD", &first)?);
+    assert!(!git.is_ancestor("0123456789abcdef0123456789abcdef01234567", "HEAD")?);
+
+    Ok(())
+}
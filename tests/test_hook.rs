@@ -0,0 +1,155 @@
+//     _         _        __  __
+//    / \  _   _| |_ ___ |  \/  | ___  ___ ___  __ _  __ _  ___
+//   / _ \| | | | __/ _ \| |\/| |/ _ \/ __/ __|/ _` |/ _` |/ _ \
+//  / ___ \ |_| | || (_) | |  | |  __/\__ \__ \ (_| | (_| |  __/
+// /_/   \_\__,_|\__\___/|_|  |_|\___||___/___/\__,_|\__, |\___|
+//                                                   |___/
+//
+// Author: Sidney Zhang <zly@lyzhang.me>
+// Date: 2025-08-05
+// License: MIT
+//
+// A test for the prepare-commit-msg hook
+
+use anyhow::Result;
+use git_automessage::git::GitRepo;
+use git_automessage::hook::{self, HookManager, HookStatus};
+use git2::Repository;
+use std::fs;
+use tempfile::TempDir;
+
+fn init_repo() -> Result<(TempDir, Repository)> {
+    let dir = TempDir::new()?;
+    let repo = Repository::init(dir.path())?;
+    Ok((dir, repo))
+}
+
+// 测试安装、状态和卸载
+#[test]
+fn test_install_and_uninstall() -> Result<()> {
+    let (dir, _repo) = init_repo()?;
+    let manager = HookManager::new(&GitRepo::open_path(dir.path())?)?;
+
+    assert_eq!(manager.status()?, HookStatus::NotInstalled);
+    assert_eq!(manager.install()?, HookStatus::Installed { chained: false });
+    assert!(
+        manager
+            .hook_path()
+            .starts_with(dir.path().join(".git/hooks"))
+    );
+
+    let script = fs::read_to_string(manager.hook_path())?;
+    assert!(script.starts_with("#!/bin/sh"));
+    assert!(script.contains("hook run \"$@\""));
+
+    // 重复安装不应把自己当作已有钩子链接起来
+    assert_eq!(manager.install()?, HookStatus::Installed { chained: false });
+
+    assert_eq!(manager.uninstall()?, HookStatus::NotInstalled);
+    assert!(!manager.hook_path().exists());
+
+    Ok(())
+}
+
+// 测试已有钩子会被保留并链式调用
+#[test]
+fn test_install_chains_existing_hook() -> Result<()> {
+    let (dir, _repo) = init_repo()?;
+    let manager = HookManager::new(&GitRepo::open_path(dir.path())?)?;
+
+    fs::create_dir_all(manager.hook_path().parent().unwrap())?;
+    fs::write(manager.hook_path(), "#!/bin/sh\necho existing\n")?;
+    assert_eq!(manager.status()?, HookStatus::Foreign);
+    assert!(manager.uninstall().is_err());
+
+    assert_eq!(manager.install()?, HookStatus::Installed { chained: true });
+    assert_eq!(
+        fs::read_to_string(manager.chained_path())?,
+        "#!/bin/sh\necho existing\n"
+    );
+
+    assert_eq!(manager.uninstall()?, HookStatus::Foreign);
+    assert_eq!(
+        fs::read_to_string(manager.hook_path())?,
+        "#!/bin/sh\necho existing\n"
+    );
+    assert!(!manager.chained_path().exists());
+
+    Ok(())
+}
+
+// 测试 core.hooksPath 配置
+#[test]
+fn test_respects_core_hooks_path() -> Result<()> {
+    let (dir, repo) = init_repo()?;
+    repo.config()?.set_str("core.hooksPath", ".githooks")?;
+
+    let manager = HookManager::new(&GitRepo::open_path(dir.path())?)?;
+    manager.install()?;
+
+    assert!(dir.path().join(".githooks/prepare-commit-msg").exists());
+    assert!(!dir.path().join(".git/hooks/prepare-commit-msg").exists());
+
+    Ok(())
+}
+
+// 测试只在普通提交时生成消息
+#[test]
+fn test_should_generate_only_for_plain_commits() {
+    assert!(hook::should_generate(None));
+    assert!(hook::should_generate(Some("template")));
+    assert!(!hook::should_generate(Some("message")));
+    assert!(!hook::should_generate(Some("merge")));
+    assert!(!hook::should_generate(Some("squash")));
+    assert!(!hook::should_generate(Some("commit")));
+}
+
+// 测试消息文件的读写
+#[test]
+fn test_write_message_file_keeps_comments() -> Result<()> {
+    let dir = TempDir::new()?;
+    let path = dir.path().join("COMMIT_EDITMSG");
+    let comments = "\n# Please enter the commit message for your changes.\n";
+    fs::write(&path, comments)?;
+
+    assert!(!hook::has_message(comments, None, '#'));
+    hook::write_message_file(&path, "feat: add hook\n", '#')?;
+
+    let content = fs::read_to_string(&path)?;
+    assert!(content.starts_with("feat: add hook\n"));
+    assert!(content.ends_with(comments));
+    assert!(hook::has_message(&content, None, '#'));
+
+    Ok(())
+}
+
+// 测试未修改的提交模板与 commit -v 的差异不算作已有消息
+#[test]
+fn test_template_and_verbose_diff() -> Result<()> {
+    let dir = TempDir::new()?;
+    let path = dir.path().join("COMMIT_EDITMSG");
+    let template = "Subject line\n\nWhy this change:\n";
+    let scissors = "# ------------------------ >8 ------------------------\n# Do not modify or remove the line above.\ndiff --git a/a.txt b/a.txt\n+added\n";
+    let prepared = format!(
+        "{}\n# Please enter the commit message.\n{}",
+        template, scissors
+    );
+
+    assert!(!hook::has_message(&prepared, Some(template), '#'));
+    assert!(hook::has_message(&prepared, None, '#'));
+    let edited = prepared.replace("Subject line", "fix: a real subject");
+    assert!(hook::has_message(&edited, Some(template), '#'));
+    assert!(!hook::has_message(&format!("\n{}", scissors), None, '#'));
+
+    fs::write(&path, &prepared)?;
+    hook::write_message_file(&path, "feat: add hook", '#')?;
+    assert_eq!(
+        fs::read_to_string(&path)?,
+        format!(
+            "feat: add hook\n\n# Please enter the commit message.\n{}",
+            scissors
+        )
+    );
+
+    Ok(())
+}
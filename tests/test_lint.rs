@@ -0,0 +1,116 @@
+//     _         _        __  __
+//    / \  _   _| |_ ___ |  \/  | ___  ___ ___  __ _  __ _  ___
+//   / _ \| | | | __/ _ \| |\/| |/ _ \/ __/ __|/ _` |/ _` |/ _ \
+//  / ___ \ |_| | || (_) | |  | |  __/\__ \__ \ (_| | (_| |  __/
+// /_/   \_\__,_|\__\___/|_|  |_|\___||___/___/\__,_|\__, |\___|
+//                                                   |___/
+//
+// Author: Sidney Zhang <zly@lyzhang.me>
+// Date: 2025-08-05
+// License: MIT
+//
+// A test for linting commit messages
+
+use git_automessage::lint::{self, LintRules, Report};
+
+fn team_rules() -> LintRules {
+    LintRules {
+        types: vec!["feat".to_string(), "fix".to_string()],
+        scopes: vec!["api".to_string()],
+        max_header_length: 50,
+        body_width: 72,
+        required_trailers: vec!["Signed-off-by".to_string()],
+        allow_wip: false,
+    }
+}
+
+fn rules_of(message: &str, rules: &LintRules) -> Vec<&'static str> {
+    lint::lint(message, rules)
+        .into_iter()
+        .map(|diagnostic| diagnostic.rule)
+        .collect()
+}
+
+// 测试符合规则的消息
+#[test]
+fn test_lint_valid_message() {
+    let message = "feat(api): add login\n\nExplain why.\n\nSigned-off-by: A <a@example.com>";
+    assert!(lint::lint(message, &team_rules()).is_empty());
+    assert!(lint::lint("Merge branch 'main' into dev", &team_rules()).is_empty());
+    assert!(lint::lint("docs: anything goes", &LintRules::default()).is_empty());
+}
+
+// 测试各条规则及其位置
+#[test]
+fn test_lint_rules() {
+    let rules = team_rules();
+    let signed = "\n\nSigned-off-by: A <a@example.com>";
+
+    let diagnostics = lint::lint(&format!("docs(web): update readme{}", signed), &rules);
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(
+        (diagnostics[0].rule, diagnostics[0].column),
+        ("type-enum", 1)
+    );
+    assert_eq!(
+        (diagnostics[1].rule, diagnostics[1].column),
+        ("scope-enum", 6)
+    );
+
+    let diagnostics = lint::lint(&format!("feat(api: add login{}", signed), &rules);
+    assert_eq!(diagnostics[0].rule, "header-format");
+    assert_eq!((diagnostics[0].line, diagnostics[0].column), (1, 5));
+
+    assert_eq!(
+        rules_of(&format!("fix: {}{}", "x".repeat(60), signed), &rules),
+        ["header-max-length"]
+    );
+    assert_eq!(
+        rules_of(
+            &format!("fix: typo\n\n{}{}", "word ".repeat(20), signed),
+            &rules
+        ),
+        ["body-max-line-length"]
+    );
+    assert_eq!(rules_of("fix: typo", &rules), ["trailer-required"]);
+    assert_eq!(
+        rules_of(&format!("WIP: fix: typo{}", signed), &rules),
+        ["no-wip", "type-enum"]
+    );
+    assert_eq!(
+        rules_of(&format!("fixup! fix: typo{}", signed), &rules),
+        ["no-wip", "header-format"]
+    );
+    assert_eq!(rules_of("", &rules), ["header-empty"]);
+}
+
+// 测试提交消息文件的清理
+#[test]
+fn test_clean_message() {
+    let text = "fix: typo\n\n# Please enter the commit message\n\
+                # ------------------------ >8 ------------------------\n\
+                diff --git a/x b/x\n";
+    assert_eq!(lint::clean_message(text, '#'), "fix: typo");
+}
+
+// 测试文本和 JSON 输出
+#[test]
+fn test_format_reports() {
+    let rules = team_rules();
+    let reports = vec![
+        Report::new("abc12345", "feat: add login\n\nSigned-off-by: A", &rules),
+        Report::new("def67890", "update readme", &rules),
+    ];
+
+    let text = lint::format_text(&reports);
+    assert!(!text.contains("abc12345"));
+    assert!(text.contains("def67890: update readme"));
+    assert!(text.contains("[header-format]"));
+    assert!(text.ends_with("2 problem(s) in 1 of 2 message(s).\n"));
+
+    let json: serde_json::Value =
+        serde_json::from_str(&lint::format_json(&reports).unwrap()).unwrap();
+    assert_eq!(json[1]["source"], "def67890");
+    assert_eq!(json[1]["diagnostics"][0]["rule"], "header-format");
+    assert_eq!(json[0]["diagnostics"].as_array().unwrap().len(), 0);
+}
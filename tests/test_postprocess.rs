@@ -0,0 +1,105 @@
+//     _         _        __  __
+//    / \  _   _| |_ ___ |  \/  | ___  ___ ___  __ _  __ _  ___
+//   / _ \| | | | __/ _ \| |\/| |/ _ \/ __/ __|/ _` |/ _` |/ _ \
+//  / ___ \ |_| | || (_) | |  | |  __/\__ \__ \ (_| | (_| |  __/
+// /_/   \_\__,_|\__\___/|_|  |_|\___||___/___/\__,_|\__, |\___|
+//                                                   |___/
+//
+// Author: Sidney Zhang <zly@lyzhang.me>
+// Date: 2025-08-05
+// License: MIT
+//
+// A test for cleaning up and fixing generated messages
+
+use git_automessage::candidates::{self, Convention, MessageKind};
+use git_automessage::postprocess;
+
+fn commit_convention() -> Convention {
+    Convention {
+        kind: MessageKind::Commit,
+        max_length: 50,
+        types: vec!["feat".to_string(), "fix".to_string(), "chore".to_string()],
+        scopes: vec!["api".to_string()],
+        conventional: true,
+    }
+}
+
+// 测试去掉模型添加的前言、代码块和引号
+#[test]
+fn test_strip_preamble() {
+    assert_eq!(
+        postprocess::strip_preamble("Here is your commit message:\n\nfeat: add login"),
+        "feat: add login"
+    );
+    assert_eq!(
+        postprocess::strip_preamble("```text\nfix: handle empty diff\n```"),
+        "fix: handle empty diff"
+    );
+    assert_eq!(
+        postprocess::strip_preamble("以下是提交消息：\n\"docs: 更新说明\""),
+        "docs: 更新说明"
+    );
+    // 约定式标题本身不是前言
+    assert_eq!(
+        postprocess::strip_preamble("feat: add login\n\nRefs: #1"),
+        "feat: add login\n\nRefs: #1"
+    );
+}
+
+// 测试正文按 72 列换行，列表缩进，脚注保持不变
+#[test]
+fn test_wrap_body() {
+    let long = "word ".repeat(20);
+    let footer = format!("Refs: {}", "#1 ".repeat(30).trim());
+    let message = format!(
+        "feat: add login\n\n{}\n- {}\n    {}\n\n{}",
+        long.trim(),
+        long.trim(),
+        long.trim(),
+        footer
+    );
+    let wrapped = postprocess::wrap_body(&message, 72);
+    let lines: Vec<&str> = wrapped.lines().collect();
+
+    assert_eq!(lines[0], "feat: add login");
+    assert!(lines[2].chars().count() <= 72);
+    assert!(lines[3].starts_with("word"));
+    assert!(lines[4].starts_with("- word"));
+    assert!(lines[5].starts_with("  word"));
+    assert!(wrapped.contains(&format!("    {}", long.trim())));
+    assert!(wrapped.ends_with(&footer));
+}
+
+// 测试本地修正类型、范围、长度和空行
+#[test]
+fn test_fix_up() {
+    let convention = commit_convention();
+
+    let fixed = postprocess::fix_up("Feat(api): add login.", &convention);
+    assert_eq!(fixed, "feat(api): add login");
+
+    let fixed = postprocess::fix_up("docs(web): update readme", &convention);
+    assert_eq!(fixed, "chore: update readme");
+
+    let fixed = postprocess::fix_up("Update the readme\nand the changelog", &convention);
+    assert_eq!(fixed, "chore: Update the readme\n\nand the changelog");
+
+    let fixed = postprocess::fix_up(
+        "fix(api): handle the empty diff when nothing is staged in the index",
+        &convention,
+    );
+    assert_eq!(fixed, "fix(api): handle the empty diff when nothing is");
+
+    for message in [
+        "Feat(api): add login.",
+        "docs(web): update readme",
+        "Update the readme\nand the changelog",
+    ] {
+        let fixed = postprocess::fix_up(message, &convention);
+        assert!(
+            candidates::check(&fixed, &convention).is_empty(),
+            "{}",
+            fixed
+        );
+    }
+}
@@ -0,0 +1,222 @@
+//     _         _        __  __
+//    / \  _   _| |_ ___ |  \/  | ___  ___ ___  __ _  __ _  ___
+//   / _ \| | | | __/ _ \| |\/| |/ _ \/ __/ __|/ _` |/ _` |/ _ \
+//  / ___ \ |_| | || (_) | |  | |  __/\__ \__ \ (_| | (_| |  __/
+// /_/   \_\__,_|\__\___/|_|  |_|\___||___/___/\__,_|\__, |\___|
+//                                                   |___/
+//
+// Author: Sidney Zhang <zly@lyzhang.me>
+// Date: 2025-08-05
+// License: MIT
+//
+// A test for the Prompt struct
+
+use anyhow::Result;
+use git_automessage::config::Config;
+use git_automessage::prompts::{
+    self, INFORMATION_VARIABLE, PLACEHOLDER, Prompt, Template, Variables,
+};
+use std::fs;
+use tempfile::TempDir;
+
+fn create_test_config(language: &str, emoji: bool) -> Result<Config> {
+    let config_content = format!(
+        r#"
+llm:
+  provider: OpenAI
+  api_key: "test-api-key"
+  base_url: ""
+  model: "gpt-4o-mini"
+language: "{}"
+emoji: {}
+"#,
+        language, emoji
+    );
+
+    Ok(serde_yaml::from_str(&config_content)?)
+}
+
+// 测试根据语言和 emoji 选择提交模板
+#[test]
+fn test_commit_template_selection() -> Result<()> {
+    let en = Prompt::new("commit", &create_test_config("en", false)?);
+    assert!(en.get_prompt().starts_with("## Role Setting"));
+    assert!(!en.get_prompt().contains("GitMoji"));
+    assert!(!en.is_chinese());
+
+    let en_emoji = Prompt::new("commit", &create_test_config("en-US", true)?);
+    assert!(en_emoji.get_prompt().contains("Use GitMoji convention"));
+    // emoji 列表放在代码变更信息之前
+    let gitmoji = en_emoji.get_prompt().find("## Gitmoji").unwrap();
+    let information = en_emoji
+        .get_prompt()
+        .find("## Code Change Information")
+        .unwrap();
+    assert!(gitmoji < information);
+
+    let zh = Prompt::new("commit", &create_test_config("zh-CN", false)?);
+    assert!(zh.get_prompt().starts_with("## 角色设定"));
+    assert!(!zh.get_prompt().contains("gitmoji"));
+    assert!(zh.is_chinese());
+
+    let zh_emoji = Prompt::new("commit", &create_test_config("zh-CN", true)?);
+    assert!(zh_emoji.get_prompt().contains("gitmoji"));
+
+    Ok(())
+}
+
+// 测试标签和变更日志模板
+#[test]
+fn test_tag_and_changelog_templates() -> Result<()> {
+    for language in ["en", "zh-CN"] {
+        let config = create_test_config(language, false)?;
+        for kind in ["tag", "changelog"] {
+            let prompt = Prompt::new(kind, &config);
+            assert!(
+                prompt.get_prompt().contains(PLACEHOLDER),
+                "{kind} ({language})"
+            );
+        }
+    }
+
+    let changelog = Prompt::new("changelog", &create_test_config("en", false)?);
+    assert!(changelog.get_prompt().contains("Keep a Changelog"));
+
+    Ok(())
+}
+
+// 测试占位符填充与额外要求
+#[test]
+fn test_fill_and_instructions() -> Result<()> {
+    let config = create_test_config("en", false)?;
+    let prompt = Prompt::new("commit", &config).with_instructions(Some("Mention the migration"));
+    let mut variables = Variables::new();
+    variables
+        .set(INFORMATION_VARIABLE, "Diff:\n+added line\n")
+        .set("max_length", "72");
+    let filled = prompt.render(&variables)?;
+
+    assert!(!filled.contains(PLACEHOLDER));
+    assert!(filled.contains("+added line"));
+    let instructions = filled.find("Mention the migration").unwrap();
+    let diff = filled.find("+added line").unwrap();
+    assert!(instructions < diff);
+    assert!(filled.contains("within 72 characters"));
+
+    // 其他语言使用英文模板并要求模型使用该语言
+    let ja = Prompt::new("commit", &create_test_config("ja", false)?);
+    assert!(ja.get_prompt().contains("\"ja\""));
+
+    Ok(())
+}
+
+// 测试模板变量、条件和转义
+#[test]
+fn test_template_rendering() -> Result<()> {
+    let template = Template::parse(
+        "{#if emoji}Use gitmoji{#else}No emoji{/if} on {branch}.\n\
+         {#if !recent_commits}No history.{/if}\n\
+         Literal {{diff}}, code {{ x }} and fn main() { }",
+    )?;
+
+    let mut variables = Variables::new();
+    variables.set("emoji", "true").set("branch", "main");
+    let rendered = template.render(&variables);
+    assert!(rendered.starts_with("Use gitmoji on main."));
+    assert!(rendered.contains("No history."));
+    assert!(rendered.contains("Literal {diff}, code { x } and fn main() { }"));
+
+    variables
+        .set("emoji", "false")
+        .set("recent_commits", "- fix: typo");
+    let rendered = template.render(&variables);
+    assert!(rendered.starts_with("No emoji on main."));
+    assert!(!rendered.contains("No history."));
+
+    let names = template.variables();
+    assert!(names.contains("emoji") && names.contains("branch"));
+    assert!(!names.contains("diff"));
+
+    Ok(())
+}
+
+// 测试模板语法错误和未知变量
+#[test]
+fn test_template_errors() {
+    let unclosed = Template::parse("line one\n{#if emoji}\nmissing end").unwrap_err();
+    assert!(unclosed.to_string().contains("第 2 行"));
+    assert!(Template::parse("{/if}").is_err());
+    assert!(Template::parse("{#if a}{#else}{#else}{/if}").is_err());
+
+    let unknown = prompts::check_template("{diff} by {author}").unwrap_err();
+    assert!(unknown.to_string().contains("{author}"));
+    assert!(prompts::check_template("{#if multi_line}{diff}{/if}").is_ok());
+
+    assert!(prompts::is_template("Summarize {diff}"));
+    assert!(!prompts::is_template("Mention the ticket number"));
+}
+
+// 测试从文件加载模板，未引用变更内容时自动附加
+#[test]
+fn test_template_files() -> Result<()> {
+    let dir = TempDir::new()?;
+    let path = dir.path().join("commit.tmpl");
+    fs::write(&path, "Write a commit message in {language}.")?;
+
+    let mut config = create_test_config("en", false)?;
+    config.templates.commit = Some(path.clone());
+    let prompt = Prompt::for_config("commit", &config)?;
+
+    let mut variables = Variables::new();
+    variables
+        .set("language", "en")
+        .set(INFORMATION_VARIABLE, "Diff:\n+added line");
+    let rendered = prompt.render(&variables)?;
+    assert_eq!(
+        rendered,
+        "Write a commit message in en.\n\nDiff:\n+added line"
+    );
+
+    // 标签仍使用内置模板
+    let tag = Prompt::for_config("tag", &config)?;
+    assert!(tag.get_prompt().contains(PLACEHOLDER));
+
+    fs::write(&path, "{#if emoji}unterminated")?;
+    assert!(Prompt::for_config("commit", &config).is_err());
+
+    Ok(())
+}
+
+// 测试限定的类型和范围会写入内置提交模板
+#[test]
+fn test_allowed_types_and_scopes() -> Result<()> {
+    let mut config = create_test_config("en", false)?;
+    config.types = vec!["feat".to_string(), "fix".to_string()];
+    config.scopes = vec!["api".to_string()];
+
+    let prompt = Prompt::new("commit", &config);
+    let allowed = prompt
+        .get_prompt()
+        .find("## Allowed Types and Scopes")
+        .unwrap();
+    let information = prompt
+        .get_prompt()
+        .find("## Code Change Information")
+        .unwrap();
+    assert!(allowed < information);
+    assert!(prompt.get_prompt().contains("types: feat, fix"));
+    assert!(
+        prompt
+            .get_prompt()
+            .contains("scopes, or omit the scope: api")
+    );
+
+    // 标签模板不受影响
+    assert!(
+        !Prompt::new("tag", &config)
+            .get_prompt()
+            .contains("Allowed Types")
+    );
+
+    Ok(())
+}
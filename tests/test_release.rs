@@ -0,0 +1,276 @@
+//     _         _        __  __
+//    / \  _   _| |_ ___ |  \/  | ___  ___ ___  __ _  __ _  ___
+//   / _ \| | | | __/ _ \| |\/| |/ _ \/ __/ __|/ _` |/ _` |/ _ \
+//  / ___ \ |_| | || (_) | |  | |  __/\__ \__ \ (_| | (_| |  __/
+// /_/   \_\__,_|\__\___/|_|  |_|\___||___/___/\__,_|\__, |\___|
+//                                                   |___/
+//
+// Author: Sidney Zhang <zly@lyzhang.me>
+// Date: 2025-08-05
+// License: MIT
+//
+// A test for release planning and version file updates
+
+use anyhow::Result;
+use git_automessage::config::VersionFile;
+use git_automessage::git::GitRepo;
+use git_automessage::release::{self, FileChange, ReleasePlan};
+use git_automessage::version::{self, Bump, Version};
+use git2::{Repository, Signature};
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+// 测试更新 Cargo.toml 与 Cargo.lock 中的版本号
+#[test]
+fn test_bump_cargo() {
+    let manifest = "[package]\nname = \"demo\"\nversion = \"0.3.1\" # keep\n\n[dependencies]\nserde = { version = \"1.0\" }\n";
+    let (old, updated) = release::bump_cargo_toml(manifest, "0.4.0").unwrap();
+    assert_eq!(old, "0.3.1");
+    assert_eq!(
+        updated,
+        "[package]\nname = \"demo\"\nversion = \"0.4.0\" # keep\n\n[dependencies]\nserde = { version = \"1.0\" }\n"
+    );
+
+    let workspace = "[workspace]\nmembers = [\"a\"]\n\n[workspace.package]\nversion = \"2.0.0\"\n";
+    let (old, updated) = release::bump_cargo_toml(workspace, "2.1.0").unwrap();
+    assert_eq!(old, "2.0.0");
+    assert!(updated.contains("version = \"2.1.0\""));
+
+    let inherited = "[package]\nname = \"a\"\nversion.workspace = true\n";
+    assert_eq!(release::bump_cargo_toml(inherited, "1.0.0"), None);
+
+    let lock = "[[package]]\nname = \"anyhow\"\nversion = \"1.0.0\"\n\n[[package]]\nname = \"demo\"\nversion = \"0.3.1\"\n";
+    assert_eq!(
+        release::bump_cargo_lock(lock, "demo", "0.4.0").unwrap(),
+        "[[package]]\nname = \"anyhow\"\nversion = \"1.0.0\"\n\n[[package]]\nname = \"demo\"\nversion = \"0.4.0\"\n"
+    );
+    assert_eq!(release::bump_cargo_lock(lock, "other", "0.4.0"), None);
+}
+
+// 测试更新 package.json、pyproject.toml 与自定义文件中的版本号
+#[test]
+fn test_bump_other_files() {
+    let package = "{\n  \"name\": \"demo\",\n  \"version\": \"1.2.3\",\n  \"dependencies\": {\n    \"x\": \"1.2.3\"\n  }\n}\n";
+    let (old, updated) = release::bump_package_json(package, "1.3.0").unwrap();
+    assert_eq!(old, "1.2.3");
+    assert_eq!(updated, package.replacen("\"1.2.3\"", "\"1.3.0\"", 1));
+    assert_eq!(
+        release::bump_package_json("{\"name\": \"x\"}", "1.0.0"),
+        None
+    );
+
+    let pyproject = "[build-system]\nrequires = [\"hatchling\"]\n\n[project]\nname = \"demo\"\nversion = '0.9.0'\n";
+    let (old, updated) = release::bump_pyproject(pyproject, "1.0.0").unwrap();
+    assert_eq!(old, "0.9.0");
+    assert!(updated.ends_with("version = '1.0.0'\n"));
+
+    let poetry = "[tool.poetry]\nname = \"demo\"\nversion = \"0.1.0\"\n";
+    assert!(release::bump_pyproject(poetry, "0.2.0").is_some());
+
+    let pattern = Regex::new(r#"VERSION: &str = "([^"]+)""#).unwrap();
+    let (old, updated) =
+        release::bump_pattern("pub const VERSION: &str = \"1.0.0\";\n", &pattern, "1.1.0").unwrap();
+    assert_eq!(old, "1.0.0");
+    assert_eq!(updated, "pub const VERSION: &str = \"1.1.0\";\n");
+}
+
+// 测试收集仓库中需要更新版本号的文件
+#[test]
+fn test_version_changes() -> Result<()> {
+    let dir = TempDir::new()?;
+    fs::write(
+        dir.path().join("Cargo.toml"),
+        "[package]\nname = \"demo\"\nversion = \"1.0.0\"\n",
+    )?;
+    fs::write(
+        dir.path().join("Cargo.lock"),
+        "[[package]]\nname = \"demo\"\nversion = \"1.0.0\"\n",
+    )?;
+    fs::write(dir.path().join("version.txt"), "release 1.0.0\n")?;
+
+    let files = vec![VersionFile {
+        path: PathBuf::from("version.txt"),
+        pattern: r"release (\S+)".to_string(),
+    }];
+    let changes = release::version_changes(dir.path(), &Version::new(1, 1, 0), &files)?;
+    let paths: Vec<_> = changes.iter().map(|change| change.path.clone()).collect();
+    assert_eq!(
+        paths,
+        vec![
+            PathBuf::from("Cargo.toml"),
+            PathBuf::from("Cargo.lock"),
+            PathBuf::from("version.txt")
+        ]
+    );
+    assert!(
+        changes
+            .iter()
+            .all(|change| change.summary == "version 1.0.0 -> 1.1.0")
+    );
+    assert_eq!(changes[2].after, "release 1.1.0\n");
+
+    let missing = vec![VersionFile {
+        path: PathBuf::from("version.txt"),
+        pattern: r"v(\d+\.\d+\.\d+)".to_string(),
+    }];
+    assert!(release::version_changes(dir.path(), &Version::new(1, 1, 0), &missing).is_err());
+
+    Ok(())
+}
+
+fn init_repo() -> Result<(TempDir, Repository)> {
+    let dir = TempDir::new()?;
+    let repo = Repository::init(dir.path())?;
+    {
+        let mut config = repo.config()?;
+        config.set_str("user.name", "Test User")?;
+        config.set_str("user.email", "test@example.com")?;
+    }
+    fs::write(
+        dir.path().join("Cargo.toml"),
+        "[package]\nname = \"demo\"\nversion = \"1.0.0\"\n",
+    )?;
+    {
+        let mut index = repo.index()?;
+        index.add_path(Path::new("Cargo.toml"))?;
+        index.write()?;
+        let signature = Signature::now("Test User", "test@example.com")?;
+        let tree = repo.find_tree(index.write_tree()?)?;
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            "feat: init",
+            &tree,
+            &[],
+        )?;
+    }
+    Ok((dir, repo))
+}
+
+// 测试没有版本标签时从清单中的版本开始计算
+#[test]
+fn test_untagged_release_follows_manifest() -> Result<()> {
+    let (dir, _repo) = init_repo()?;
+    fs::write(
+        dir.path().join("Cargo.toml"),
+        "[package]\nname = \"demo\"\nversion = \"1.4.2\"\n",
+    )?;
+    fs::write(dir.path().join("package.json"), r#"{"version": "1.2.0"}"#)?;
+
+    let current = release::manifest_version(dir.path())?;
+    assert_eq!(current, Some(Version::new(1, 4, 2)));
+    let git = GitRepo::open_path(dir.path())?;
+    let next = version::next_release(&git, "v", None, current.as_ref())?;
+    assert_eq!(next.previous, None);
+    assert_eq!(next.released, Some(Version::new(1, 4, 2)));
+    assert_eq!(next.next, Some(Version::new(1, 5, 0)));
+
+    assert_eq!(release::manifest_version(TempDir::new()?.path())?, None);
+
+    Ok(())
+}
+
+fn plan(dir: &Path, tag: &str) -> ReleasePlan {
+    let manifest = fs::read_to_string(dir.join("Cargo.toml")).unwrap();
+    ReleasePlan {
+        previous: None,
+        version: Version::new(1, 1, 0),
+        bump: Bump::Minor,
+        commits: 1,
+        changes: vec![
+            FileChange {
+                path: PathBuf::from("Cargo.toml"),
+                after: manifest.replace("1.0.0", "1.1.0"),
+                before: Some(manifest),
+                summary: "version 1.0.0 -> 1.1.0".to_string(),
+            },
+            FileChange {
+                path: PathBuf::from("CHANGELOG.md"),
+                before: None,
+                after: "# Changelog\n\n## [1.1.0]\n".to_string(),
+                summary: "add section [1.1.0]".to_string(),
+            },
+        ],
+        commit_message: format!("chore(release): {}", tag),
+        tag: tag.to_string(),
+        tag_message: "Release 1.1.0".to_string(),
+    }
+}
+
+// 测试执行发布：写入文件、提交并创建标签
+#[test]
+fn test_apply_release() -> Result<()> {
+    let (dir, repo) = init_repo()?;
+    let git = GitRepo::open_path(dir.path())?;
+
+    let plan = plan(dir.path(), "v1.1.0");
+    let description = plan.describe();
+    assert!(description.contains("Cargo.toml: version 1.0.0 -> 1.1.0"));
+    assert!(description.contains("CHANGELOG.md:\n# Changelog\n\n## [1.1.0]"));
+    assert!(description.contains("Tag v1.1.0:\nRelease 1.1.0"));
+
+    plan.apply(&git)?;
+
+    let head = repo.head()?.peel_to_commit()?;
+    assert_eq!(head.message(), Some("chore(release): v1.1.0"));
+    let tree = head.tree()?;
+    assert!(tree.get_name("CHANGELOG.md").is_some());
+    let tag = repo.revparse_single("v1.1.0")?.peel_to_tag()?;
+    assert_eq!(tag.target_id(), head.id());
+    assert_eq!(tag.message(), Some("Release 1.1.0"));
+    assert!(fs::read_to_string(dir.path().join("Cargo.toml"))?.contains("1.1.0"));
+
+    Ok(())
+}
+
+// 测试发布失败时回滚文件与提交
+#[test]
+fn test_apply_release_rolls_back() -> Result<()> {
+    let (dir, repo) = init_repo()?;
+    let git = GitRepo::open_path(dir.path())?;
+    let head = repo.head()?.peel_to_commit()?;
+    repo.tag_lightweight("v1.1.0", head.as_object(), false)?;
+
+    let error = plan(dir.path(), "v1.1.0").apply(&git).unwrap_err();
+    assert!(error.to_string().contains("rolled back"));
+
+    assert_eq!(repo.head()?.peel_to_commit()?.id(), head.id());
+    assert!(!dir.path().join("CHANGELOG.md").exists());
+    assert_eq!(
+        fs::read_to_string(dir.path().join("Cargo.toml"))?,
+        "[package]\nname = \"demo\"\nversion = \"1.0.0\"\n"
+    );
+    assert!(git.get_staged_files()?.is_empty());
+
+    Ok(())
+}
+
+// 测试提交失败（签名程序出错）时同样回滚文件与暂存区
+#[test]
+fn test_apply_release_rolls_back_failed_commit() -> Result<()> {
+    let (dir, repo) = init_repo()?;
+    {
+        let mut config = repo.config()?;
+        config.set_bool("commit.gpgsign", true)?;
+        config.set_str("gpg.program", "false")?;
+    }
+    let git = GitRepo::open_path(dir.path())?;
+    let head = repo.head()?.peel_to_commit()?;
+
+    let error = plan(dir.path(), "v1.1.0").apply(&git).unwrap_err();
+    assert!(error.to_string().contains("rolled back"));
+
+    assert_eq!(repo.head()?.peel_to_commit()?.id(), head.id());
+    assert!(!dir.path().join("CHANGELOG.md").exists());
+    assert!(git.get_staged_files()?.is_empty());
+    assert!(
+        repo.index()?
+            .get_path(Path::new("CHANGELOG.md"), 0)
+            .is_none()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,143 @@
+//     _         _        __  __
+//    / \  _   _| |_ ___ |  \/  | ___  ___ ___  __ _  __ _  ___
+//   / _ \| | | | __/ _ \| |\/| |/ _ \/ __/ __|/ _` |/ _` |/ _ \
+//  / ___ \ |_| | || (_) | |  | |  __/\__ \__ \ (_| | (_| |  __/
+// /_/   \_\__,_|\__\___/|_|  |_|\___||___/___/\__,_|\__, |\___|
+//                                                   |___/
+//
+// Author: Sidney Zhang <zly@lyzhang.me>
+// Date: 2025-08-05
+// License: MIT
+//
+// A test for the interactive review helpers
+
+use anyhow::Result;
+use git_automessage::review::{self, ReviewAction};
+use std::fs;
+use tempfile::TempDir;
+
+// 测试菜单选项解析
+#[test]
+fn test_parse_action() {
+    assert_eq!(review::parse_action(""), Some(ReviewAction::Accept));
+    assert_eq!(review::parse_action("A"), Some(ReviewAction::Accept));
+    assert_eq!(review::parse_action("e"), Some(ReviewAction::Edit));
+    assert_eq!(review::parse_action("r"), Some(ReviewAction::Regenerate));
+    assert_eq!(
+        review::parse_action("f mention the migration"),
+        Some(ReviewAction::Feedback("mention the migration".to_string()))
+    );
+    assert_eq!(
+        review::parse_action("f"),
+        Some(ReviewAction::Feedback(String::new()))
+    );
+    assert_eq!(
+        review::parse_action("t fix(api)"),
+        Some(ReviewAction::TypeScope("fix(api)".to_string()))
+    );
+    assert_eq!(review::parse_action("q"), Some(ReviewAction::Abort));
+    assert_eq!(review::parse_action("x"), None);
+}
+
+// 测试替换类型和范围
+#[test]
+fn test_replace_type_scope() -> Result<()> {
+    let message = "feat(auth): add login\n\nUse OAuth.";
+    assert_eq!(
+        review::replace_type_scope(message, "fix")?,
+        "fix(auth): add login\n\nUse OAuth."
+    );
+    assert_eq!(
+        review::replace_type_scope(message, "(api)")?,
+        "feat(api): add login\n\nUse OAuth."
+    );
+    assert_eq!(
+        review::replace_type_scope(message, "refactor()")?,
+        "refactor: add login\n\nUse OAuth."
+    );
+
+    // 保留 emoji 和破坏性变更标记
+    assert_eq!(
+        review::replace_type_scope("✨ feat!: drop v1 API", "fix(api)")?,
+        "✨ fix(api)!: drop v1 API"
+    );
+    assert_eq!(
+        review::replace_type_scope(":sparkles: feat: add login", "fix")?,
+        ":sparkles: fix: add login"
+    );
+
+    // 没有类型的消息需要指定类型
+    assert_eq!(
+        review::replace_type_scope("Add login", "feat")?,
+        "feat: Add login"
+    );
+    assert!(review::replace_type_scope("Add login", "(api)").is_err());
+    assert!(review::replace_type_scope(message, "fix(api").is_err());
+
+    Ok(())
+}
+
+// 测试通过编辑器修改消息并去掉注释行
+#[cfg(unix)]
+#[test]
+fn test_edit_message_with_editor() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = TempDir::new()?;
+    let editor = dir.path().join("editor.sh");
+    fs::write(
+        &editor,
+        "#!/bin/sh\nsed -i 's/add login/add OAuth login/' \"$1\"\n",
+    )?;
+    fs::set_permissions(&editor, fs::Permissions::from_mode(0o755))?;
+
+    let edited = review::edit_message(
+        &editor.to_string_lossy(),
+        "feat: add login\n\nBody line.",
+        '#',
+    )?;
+    assert_eq!(edited, "feat: add OAuth login\n\nBody line.");
+
+    // 编辑器失败时返回错误
+    assert!(review::edit_message("false", "feat: add login", '#').is_err());
+
+    Ok(())
+}
+
+// 测试按 git 的方式清理注释
+#[test]
+fn test_strip_comments() {
+    assert_eq!(
+        review::strip_comments("\nfix: typo\n\n; note\n", ';'),
+        "fix: typo"
+    );
+}
+
+// 测试改写前后对照表
+#[test]
+fn test_before_after_table() {
+    let rows = vec![
+        (
+            "1a2b3c4d".to_string(),
+            "wip".to_string(),
+            "feat(api): add pagination to the list endpoint\n\nBody".to_string(),
+        ),
+        (
+            "5e6f7a8b".to_string(),
+            "fix stuff in the parser and also some more things".to_string(),
+            "fix(parser): handle empty input".to_string(),
+        ),
+    ];
+    let table = review::before_after_table(&rows, 20);
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0], "commit    before                after");
+    assert_eq!(
+        lines[1],
+        "1a2b3c4d  wip                   feat(api): add pagi…"
+    );
+    assert_eq!(
+        lines[2],
+        "5e6f7a8b  fix stuff in the pa…  fix(parser): handle…"
+    );
+}
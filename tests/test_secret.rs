@@ -0,0 +1,208 @@
+//     _         _        __  __
+//    / \  _   _| |_ ___ |  \/  | ___  ___ ___  __ _  __ _  ___
+//   / _ \| | | | __/ _ \| |\/| |/ _ \/ __/ __|/ _` |/ _` |/ _ \
+//  / ___ \ |_| | || (_) | |  | |  __/\__ \__ \ (_| | (_| |  __/
+// /_/   \_\__,_|\__\___/|_|  |_|\___||___/___/\__,_|\__, |\___|
+//                                                   |___/
+//
+// Author: Sidney Zhang <zly@lyzhang.me>
+// Date: 2025-08-05
+// License: MIT
+//
+// A test for API key references and the keyring store
+
+use anyhow::Result;
+use git_automessage::config::{ConfigFile, ConfigScope, LLMConfig, LLMProvider};
+use git_automessage::secret::{KeyringStore, SecretRef};
+use keyring::credential::{Credential, CredentialApi, CredentialBuilderApi};
+use std::any::Any;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+
+type Secrets = Arc<Mutex<HashMap<(String, String), Vec<u8>>>>;
+
+/// 模拟 Secret Service：同一服务和用户名的条目共享存储
+#[derive(Default)]
+struct MockSecretService {
+    secrets: Secrets,
+}
+
+struct MockCredential {
+    key: (String, String),
+    secrets: Secrets,
+}
+
+impl CredentialApi for MockCredential {
+    fn set_secret(&self, secret: &[u8]) -> keyring::Result<()> {
+        self.secrets
+            .lock()
+            .unwrap()
+            .insert(self.key.clone(), secret.to_vec());
+        Ok(())
+    }
+
+    fn get_secret(&self) -> keyring::Result<Vec<u8>> {
+        self.secrets
+            .lock()
+            .unwrap()
+            .get(&self.key)
+            .cloned()
+            .ok_or(keyring::Error::NoEntry)
+    }
+
+    fn delete_credential(&self) -> keyring::Result<()> {
+        self.secrets
+            .lock()
+            .unwrap()
+            .remove(&self.key)
+            .map(|_| ())
+            .ok_or(keyring::Error::NoEntry)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl CredentialBuilderApi for MockSecretService {
+    fn build(
+        &self,
+        _target: Option<&str>,
+        service: &str,
+        user: &str,
+    ) -> keyring::Result<Box<Credential>> {
+        Ok(Box::new(MockCredential {
+            key: (service.to_string(), user.to_string()),
+            secrets: self.secrets.clone(),
+        }))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+fn mock_store() -> (KeyringStore, Secrets) {
+    let service = MockSecretService::default();
+    let secrets = service.secrets.clone();
+    (
+        KeyringStore::with_credential_builder(Box::new(service)),
+        secrets,
+    )
+}
+
+// 测试解析各种引用格式
+#[test]
+fn test_parse_references() {
+    assert_eq!(
+        SecretRef::parse("keyring:openai"),
+        SecretRef::Keyring("openai".to_string())
+    );
+    assert_eq!(
+        SecretRef::parse("env:OPENAI_API_KEY"),
+        SecretRef::Env("OPENAI_API_KEY".to_string())
+    );
+    assert_eq!(
+        SecretRef::parse("cmd:pass show openai"),
+        SecretRef::Command("pass show openai".to_string())
+    );
+    assert_eq!(
+        SecretRef::parse("file:/run/secrets/key"),
+        SecretRef::File(PathBuf::from("/run/secrets/key"))
+    );
+    assert_eq!(
+        SecretRef::parse("ENV"),
+        SecretRef::Env("GAM_API_KEY".to_string())
+    );
+    assert_eq!(
+        SecretRef::parse("sk-abc:def"),
+        SecretRef::Plain("sk-abc:def".to_string())
+    );
+    assert!(SecretRef::parse("keyring:").check().is_err());
+}
+
+// 测试从环境变量、命令和文件读取密钥
+#[test]
+fn test_resolve_references() -> Result<()> {
+    let (store, _) = mock_store();
+
+    // cargo 在运行测试时会设置 CARGO_PKG_NAME
+    assert_eq!(
+        SecretRef::parse("env:CARGO_PKG_NAME").resolve(&store)?,
+        "git-automessage"
+    );
+    assert!(
+        SecretRef::parse("env:GAM_TEST_UNSET_VARIABLE")
+            .resolve(&store)
+            .is_err()
+    );
+
+    assert_eq!(
+        SecretRef::parse("cmd:printf 'sk-command\\nignored\\n'").resolve(&store)?,
+        "sk-command"
+    );
+    assert!(SecretRef::parse("cmd:exit 3").resolve(&store).is_err());
+
+    let dir = TempDir::new()?;
+    let path = dir.path().join("key");
+    fs::write(&path, "sk-file\n")?;
+    assert_eq!(
+        SecretRef::parse(&format!("file:{}", path.display())).resolve(&store)?,
+        "sk-file"
+    );
+
+    Ok(())
+}
+
+// 测试 config set 把密钥写入密钥环，配置文件只保存引用
+#[test]
+fn test_set_secret_uses_keyring() -> Result<()> {
+    let (store, secrets) = mock_store();
+    let dir = TempDir::new()?;
+    let path = dir.path().join("config.yaml");
+    fs::write(
+        &path,
+        "llm:\n  provider: DeepSeek\n  model: deepseek-chat\n",
+    )?;
+
+    let mut file = ConfigFile::open_path(ConfigScope::Global, &path)?;
+    let stored = file.set_secret("llm.api_key", "sk-very-secret", &store)?;
+    file.save()?;
+    assert_eq!(stored, "keyring:deepseek");
+
+    let content = fs::read_to_string(&path)?;
+    assert!(content.contains("keyring:deepseek"));
+    assert!(!content.contains("sk-very-secret"));
+    assert_eq!(store.get("deepseek")?, "sk-very-secret");
+
+    let llm = LLMConfig {
+        provider: LLMProvider::DeepSeek,
+        base_url: String::new(),
+        api_key: stored,
+        model: "deepseek-chat".to_string(),
+    };
+    assert_eq!(llm.resolved_api_key_with(&store)?, "sk-very-secret");
+
+    // 已经是引用的值不写入密钥环
+    let mut file = ConfigFile::open_path(ConfigScope::Global, &path)?;
+    assert_eq!(
+        file.set_secret("llm.api_key", "env:DEEPSEEK_API_KEY", &store)?,
+        "env:DEEPSEEK_API_KEY"
+    );
+
+    // 删除引用时一并删除密钥环条目
+    let mut file = ConfigFile::open_path(ConfigScope::Global, &path)?;
+    assert!(file.unset_secret("llm.api_key", &store)?);
+    assert!(secrets.lock().unwrap().is_empty());
+
+    // 仓库配置文件不能保存密钥，也不会写入密钥环
+    let mut local =
+        ConfigFile::open_path(ConfigScope::Local, dir.path().join(".git-automessage.yaml"))?;
+    assert!(local.set_secret("llm.api_key", "sk-leak", &store).is_err());
+    assert!(secrets.lock().unwrap().is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,228 @@
+//     _         _        __  __
+//    / \  _   _| |_ ___ |  \/  | ___  ___ ___  __ _  __ _  ___
+//   / _ \| | | | __/ _ \| |\/| |/ _ \/ __/ __|/ _` |/ _` |/ _ \
+//  / ___ \ |_| | || (_) | |  | |  __/\__ \__ \ (_| | (_| |  __/
+// /_/   \_\__,_|\__\___/|_|  |_|\___||___/___/\__,_|\__, |\___|
+//                                                   |___/
+//
+// Author: Sidney Zhang <zly@lyzhang.me>
+// Date: 2025-08-05
+// License: MIT
+//
+// A test for semantic versions and version bumps
+
+use anyhow::Result;
+use git_automessage::git::GitRepo;
+use git_automessage::version::{self, Bump, Version};
+use git2::{Repository, Signature};
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn v(text: &str) -> Version {
+    Version::parse(text).unwrap()
+}
+
+// 测试版本号解析与格式化
+#[test]
+fn test_parse_version() {
+    assert_eq!(v("1.2.3"), Version::new(1, 2, 3));
+    assert_eq!(v("1.2.3-rc.1").pre.as_deref(), Some("rc.1"));
+    assert_eq!(v("1.2.3+build.5"), Version::new(1, 2, 3));
+    assert_eq!(v("1.2.3-beta.2+sha.abc").to_string(), "1.2.3-beta.2");
+    assert_eq!(v("1.2.3-rc.2").channel(), Some(("rc", 2)));
+    assert_eq!(v("1.2.3-alpha").channel(), Some(("alpha", 0)));
+
+    for invalid in [
+        "1.2",
+        "1.2.3.4",
+        "01.2.3",
+        "1.2.x",
+        "1.2.3-",
+        "1.2.3-rc..1",
+        "",
+    ] {
+        assert_eq!(Version::parse(invalid), None, "{}", invalid);
+    }
+
+    assert_eq!(
+        Version::from_tag("v1.0.0", "v"),
+        Some(Version::new(1, 0, 0))
+    );
+    assert_eq!(
+        Version::from_tag("pkg@2.1.0", "pkg@"),
+        Some(Version::new(2, 1, 0))
+    );
+    assert_eq!(Version::from_tag("1.0.0", "v"), None);
+    assert_eq!(Version::from_tag("other@1.0.0", "pkg@"), None);
+}
+
+// 测试版本号的先后顺序
+#[test]
+fn test_version_precedence() {
+    let ordered = [
+        "1.0.0-alpha",
+        "1.0.0-alpha.1",
+        "1.0.0-alpha.beta",
+        "1.0.0-beta",
+        "1.0.0-beta.2",
+        "1.0.0-beta.11",
+        "1.0.0-rc.1",
+        "1.0.0",
+        "1.0.1",
+        "1.1.0",
+        "2.0.0",
+    ];
+    for pair in ordered.windows(2) {
+        assert!(v(pair[0]) < v(pair[1]), "{} < {}", pair[0], pair[1]);
+    }
+}
+
+// 测试根据提交消息判断版本升级幅度
+#[test]
+fn test_bump_of_message() {
+    assert_eq!(Bump::of("feat(api)!: drop v1 endpoints"), Bump::Major);
+    assert_eq!(
+        Bump::of("fix: handle nulls\n\nBREAKING CHANGE: null is an error now"),
+        Bump::Major
+    );
+    assert_eq!(Bump::of("feat: add export"), Bump::Minor);
+    assert_eq!(Bump::of("fix(parser): empty input"), Bump::Patch);
+    assert_eq!(Bump::of("perf: cache lookups"), Bump::Patch);
+    // 正文前缺少空行时仍按标题和脚注判断
+    assert_eq!(Bump::of("feat: add export\nno blank line"), Bump::Minor);
+    assert_eq!(
+        Bump::of("fix: nulls\nBREAKING CHANGE: null is an error now"),
+        Bump::Major
+    );
+    assert_eq!(Bump::of("docs: typo"), Bump::None);
+    assert_eq!(Bump::of("update stuff"), Bump::None);
+}
+
+// 测试计算下一个版本号
+#[test]
+fn test_next_version() {
+    let next = |version: &str, bump, channel| {
+        v(version)
+            .next(bump, channel)
+            .map(|version| version.to_string())
+    };
+
+    assert_eq!(next("1.2.3", Bump::Patch, None).as_deref(), Some("1.2.4"));
+    assert_eq!(next("1.2.3", Bump::Minor, None).as_deref(), Some("1.3.0"));
+    assert_eq!(next("1.2.3", Bump::Major, None).as_deref(), Some("2.0.0"));
+    assert_eq!(next("1.2.3", Bump::None, None), None);
+
+    // 0.x：破坏性变更升级次版本号，新功能升级修订号
+    assert_eq!(next("0.3.1", Bump::Major, None).as_deref(), Some("0.4.0"));
+    assert_eq!(next("0.3.1", Bump::Minor, None).as_deref(), Some("0.3.2"));
+    assert_eq!(next("0.3.1", Bump::Patch, None).as_deref(), Some("0.3.2"));
+
+    // 预发布通道
+    assert_eq!(
+        next("1.2.3", Bump::Minor, Some("rc")).as_deref(),
+        Some("1.3.0-rc.1")
+    );
+    assert_eq!(
+        next("1.3.0-rc.1", Bump::Patch, Some("rc")).as_deref(),
+        Some("1.3.0-rc.2")
+    );
+    assert_eq!(
+        next("1.3.0-rc.1", Bump::Minor, Some("rc")).as_deref(),
+        Some("1.3.0-rc.2")
+    );
+    assert_eq!(
+        next("1.3.0-rc.1", Bump::Major, Some("rc")).as_deref(),
+        Some("2.0.0-rc.1")
+    );
+    assert_eq!(
+        next("1.3.0-beta.4", Bump::Patch, Some("rc")).as_deref(),
+        Some("1.3.0-rc.1")
+    );
+    assert_eq!(next("1.3.0-rc.1", Bump::None, Some("rc")), None);
+
+    // 从预发布版本发布正式版本
+    assert_eq!(
+        next("1.3.0-rc.2", Bump::None, None).as_deref(),
+        Some("1.3.0")
+    );
+    assert_eq!(
+        next("1.3.0-rc.2", Bump::Patch, None).as_deref(),
+        Some("1.3.0")
+    );
+}
+
+// 测试从标签中找出最新的版本
+#[test]
+fn test_latest_tag() {
+    let tags: Vec<String> = ["v1.2.0", "v1.10.0", "v1.10.0-rc.1", "nightly", "pkg@9.0.0"]
+        .iter()
+        .map(|tag| tag.to_string())
+        .collect();
+    let latest = version::latest_tag(&tags, "v").unwrap();
+    assert_eq!(latest.tag, "v1.10.0");
+    assert_eq!(latest.version, Version::new(1, 10, 0));
+    assert_eq!(version::latest_tag(&tags, "pkg@").unwrap().tag, "pkg@9.0.0");
+    assert_eq!(version::latest_tag(&tags, "app-"), None);
+}
+
+fn commit(repo: &Repository, dir: &Path, file: &str, message: &str) -> Result<git2::Oid> {
+    fs::write(dir.join(file), message)?;
+    let mut index = repo.index()?;
+    index.add_path(Path::new(file))?;
+    index.write()?;
+    let signature = Signature::now("Test User", "test@example.com")?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+    let parents: Vec<&git2::Commit> = parent.iter().collect();
+    Ok(repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &parents,
+    )?)
+}
+
+// 测试根据仓库中的标签和提交计算下一个版本
+#[test]
+fn test_next_release() -> Result<()> {
+    let dir = TempDir::new()?;
+    let repo = Repository::init(dir.path())?;
+    let git = GitRepo::open_path(dir.path())?;
+
+    commit(&repo, dir.path(), "a.txt", "chore: initial")?;
+    let release = version::next_release(&git, "v", None, None)?;
+    assert_eq!(release.previous, None);
+    assert_eq!(release.next, Some(version::INITIAL_VERSION));
+    // 清单中的版本不超过 0.1.0 时，首个版本仍为 0.1.0
+    let release = version::next_release(&git, "v", None, Some(&Version::new(0, 1, 0)))?;
+    assert_eq!(release.released, None);
+    assert_eq!(release.next, Some(version::INITIAL_VERSION));
+
+    let tagged = commit(&repo, dir.path(), "b.txt", "feat: first feature")?;
+    let object = repo.find_object(tagged, None)?;
+    repo.tag_lightweight("v1.4.2", &object, false)?;
+    repo.tag_lightweight("nightly", &object, false)?;
+
+    let release = version::next_release(&git, "v", None, None)?;
+    assert_eq!(release.previous.unwrap().tag, "v1.4.2");
+    assert_eq!(release.released, Some(Version::new(1, 4, 2)));
+    assert!(release.commits.is_empty());
+    assert_eq!(release.next, None);
+
+    commit(&repo, dir.path(), "c.txt", "fix: a bug")?;
+    commit(&repo, dir.path(), "d.txt", "feat: a feature")?;
+    commit(&repo, dir.path(), "e.txt", "docs: the feature")?;
+
+    let release = version::next_release(&git, "v", None, None)?;
+    assert_eq!(release.commits.len(), 3);
+    assert_eq!(release.bump, Bump::Minor);
+    assert_eq!(release.next, Some(Version::new(1, 5, 0)));
+
+    let release = version::next_release(&git, "v", Some("beta"), None)?;
+    assert_eq!(release.next.unwrap().to_string(), "1.5.0-beta.1");
+
+    Ok(())
+}